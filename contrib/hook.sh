@@ -18,14 +18,19 @@ main() {
 
   [ -d .git ] && git_update
 
-  if [[ ( -f $asset_path && "$update_type" != "add" ) || ( ! -f $asset_path && "$update_type" != "delete" ) ]]; then
+  if [[ ( -f $asset_path && "$update_type" == "delete" ) || ( ! -f $asset_path && "$update_type" != "delete" ) ]]; then
     echo >2 invalid update_type
     exit 1
   fi
 
   echo "Registry in `pwd` updated, $update_type asset $asset_id at $asset_path"
 
-  index_${update_type}_asset $asset_id $asset_path
+  if [ $update_type = "delete" ]; then
+    index_delete_asset $asset_id $asset_path
+  else
+    # add or update, replacing the existing entry
+    index_add_asset $asset_id $asset_path
+  fi
 
   # Commit to git and push
   if [ -d .git ]; then
@@ -41,7 +46,7 @@ main() {
   fi
 
   # Update the asset in the public www dir only *after* it was successfully synced with git
-  if [ $update_type = "add" ]; then
+  if [ $update_type != "delete" ]; then
     ln -fs `realpath $asset_path` $WWW_PATH/$asset_id.json
     sub_index_add_asset $asset_id $asset_path
   elif [ $update_type = "delete" ]; then
@@ -1,37 +1,120 @@
-use std::{fs, path};
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path;
 
 use failure::ResultExt;
 use regex::Regex;
-use serde_json::Value;
+use reqwest::Url;
+use serde_json::{value::RawValue, Value};
 #[cfg(feature = "cli")]
 use structopt::StructOpt;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
 
+use bitcoin::Txid;
 use bitcoin_hashes::{hex::FromHex, hex::ToHex, sha256, Hash};
-use elements::{issuance::ContractHash, AssetId, OutPoint};
+use elements::{issuance::ContractHash, Address, AssetId, OutPoint};
 use secp256k1::Secp256k1;
 
-use crate::chain::{verify_asset_issuance_tx, ChainQuery};
-use crate::entity::{verify_asset_link, AssetEntity};
-use crate::errors::{OptionExt, Result};
+#[cfg(feature = "async")]
+use crate::chain::verify_asset_issuance_tx_async;
+use crate::chain::{verify_asset_issuance_tx, BlockId, ChainQuery};
+use crate::context::{domain_allowed, VerifyContext};
+#[cfg(feature = "async")]
+use crate::entity::verify_asset_link_async;
+use crate::entity::{verify_asset_link, verify_asset_link_capturing, AssetEntity, LinkProof};
+use crate::errors::{join_err, AssetError, Error, OptionExt, Result};
+use crate::icon::verify_icon;
+use crate::network::Network;
+use crate::oracle::verify_oracle;
+#[cfg(feature = "async")]
+use crate::oracle::verify_oracle_async;
+use crate::schema::validate_contract_schema;
+use crate::trace::in_step;
 use crate::util::{
-    serde_from_hex, serde_to_hex, verify_bitcoin_msg, verify_domain_name, verify_pubkey, TxInput,
+    decode_npub, read_json_file, serde_from_hex, serde_from_hex_list, serde_to_hex,
+    serde_to_hex_list, verify_custom_entity, verify_domain_name, verify_github_user,
+    verify_msg_sig, verify_pubkey, verify_twitter_handle, TxInput,
 };
 
+// The address types derivable from a single public key, see Asset::issuer_address()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressType {
+    P2pkh,
+    P2wpkh,
+}
+
+// Maximum number of keys in a multisig issuer
+const MAX_ISSUER_PUBKEYS: usize = 15;
+
+// Limits for the contract version 1 metadata fields, in characters (and for the icon url)
+const ISSUER_NAME_MAX_LEN: usize = 255;
+const DESCRIPTION_MAX_LEN: usize = 1024;
+const WEBSITE_MAX_LEN: usize = 255;
+
+// The keys allowed in contracts of each version. Any other key would be committed to without
+// being accounted for by the asset fields.
+const CONTRACT_KEYS_V0: &[&str] = &[
+    "version",
+    "issuer_pubkey",
+    "issuer_pubkeys",
+    "issuer_threshold",
+    "name",
+    "ticker",
+    "collection",
+    "display_locale",
+    "precision",
+    "entity",
+];
+const CONTRACT_KEYS_V1: &[&str] = &["issuer_name", "description", "website", "icon"];
+
+// Protocol limits for the length of asset names
+pub const NAME_MIN_LEN: usize = 1;
+pub const NAME_MAX_LEN: usize = 255;
+// Limit on the utf-8 encoded size of names, which may be reached before the character limit
+// for names with multi-byte characters
+pub const NAME_MAX_BYTES: usize = 255;
+
 lazy_static! {
     static ref EC: Secp256k1<secp256k1::VerifyOnly> = Secp256k1::verification_only();
-    static ref RE_NAME: Regex = Regex::new(r"^[[:ascii:]]{1,255}$").unwrap();
     static ref RE_TICKER: Regex = Regex::new(r"^[a-zA-Z0-9.\-]{3,24}$").unwrap();
     static ref RE_COLLECTION: Regex = Regex::new(r"^[[:ascii:]]{1,255}$").unwrap();
 }
 
+// Codepoints that are not allowed in asset names despite not being control characters: bidi
+// embeddings/overrides/isolates and marks, which can visually reorder text, and zero-width
+// characters, which can make distinct names render identically.
+const NAME_BLACKLIST: &[(char, char)] = &[
+    ('\u{061C}', '\u{061C}'),
+    ('\u{200B}', '\u{200F}'),
+    ('\u{202A}', '\u{202E}'),
+    ('\u{2060}', '\u{2069}'),
+    ('\u{FEFF}', '\u{FEFF}'),
+];
+
+// Locale tags accepted as a `display_locale` hint for formatting amounts
+const DISPLAY_LOCALES: &[&str] = &[
+    "en-US", "en-GB", "en-IN", "de-DE", "de-CH", "fr-FR", "fr-CH", "es-ES", "es-MX", "it-IT",
+    "pt-BR", "pt-PT", "nl-NL", "ru-RU", "ja-JP", "ko-KR", "zh-CN", "zh-TW", "hi-IN", "ar-SA",
+];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Asset {
     pub asset_id: AssetId,
     pub contract: Value,
 
+    // The exact contract json bytes as submitted by the issuer, if retained. When available,
+    // these are used for the commitment instead of re-serializing `contract`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract_raw: Option<String>,
+
     pub issuance_txin: TxInput,
     pub issuance_prevout: OutPoint,
 
+    // The block where the issuance was confirmed, recorded when verified against the chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuance_block: Option<BlockId>,
+
     #[serde(flatten)]
     pub fields: AssetFields,
 
@@ -39,6 +122,18 @@ pub struct Asset {
     pub signature: Option<String>,
 }
 
+// The results of a successful verification
+#[derive(Debug)]
+pub struct Verified {
+    // The proof served by the linked domain (if any), to be kept as evidence of the
+    // proof-of-control at verification time
+    pub proof: Option<LinkProof>,
+    // The block where the issuance was confirmed, if verified against the chain
+    pub issuance_block: Option<BlockId>,
+    // The icon served at verification time (if any), to be stored without fetching it again
+    pub icon: Option<Vec<u8>>,
+}
+
 // Issuer-supplied fields
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct AssetFields {
@@ -47,54 +142,378 @@ pub struct AssetFields {
     #[serde(deserialize_with = "serde_from_hex", serialize_with = "serde_to_hex")]
     pub issuer_pubkey: Vec<u8>,
 
+    // Optional m-of-n multisig issuer keys (including `issuer_pubkey`). When set, issuer
+    // operations require valid signatures from `issuer_threshold` of them.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "serde_from_hex_list",
+        serialize_with = "serde_to_hex_list"
+    )]
+    pub issuer_pubkeys: Option<Vec<Vec<u8>>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer_threshold: Option<usize>,
+
     pub name: String,
 
     pub ticker: Option<String>,
 
     pub collection: Option<String>,
 
+    // Locale hint for how wallets should group/separate the digits of amounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_locale: Option<String>,
+
     #[serde(default = "default_precision")]
     pub precision: u8,
 
     pub entity: AssetEntity,
+
+    // Optional metadata, available from contract version 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    // The issuer's canonical website, as an https url
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+
+    // An https url of a static PNG or SVG icon, verified to be served when registering
+    // (see icon::verify_icon)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+// The independent checks of the issuer fields, run in order by validate() and all together by
+// Asset::verify_all()
+const FIELD_CHECKS: &[(&str, fn(&AssetFields) -> Result<()>)] = &[
+    ("name", AssetFields::check_name),
+    ("ticker", AssetFields::check_ticker),
+    ("metadata", |fields| invalid_field(fields.validate_other())),
+    ("precision", |fields| {
+        invalid_field(fields.validate_precision())
+    }),
+    ("issuer pubkey", |fields| {
+        invalid_field(fields.validate_pubkeys())
+    }),
+    ("entity", |fields| invalid_field(fields.validate_entity())),
+];
+
+fn invalid_field(result: Result<()>) -> Result<()> {
+    result.map_err(|err| AssetError::InvalidField(join_err(&err)).into())
+}
+
+// A named verification check, run lazily so that verification can stop at the first failure
+type Check<'a> = (&'static str, Box<dyn Fn() -> Result<()> + 'a>);
+
+fn run_checks(checks: Vec<Check>) -> Result<()> {
+    for (name, check) in checks {
+        in_step(name, check)?;
+    }
+    Ok(())
+}
+
+fn run_all_checks(checks: Vec<Check>) -> Vec<(&'static str, Error)> {
+    checks
+        .into_iter()
+        .filter_map(|(name, check)| in_step(name, check).err().map(|err| (name, err)))
+        .collect()
 }
 
 impl AssetFields {
+    // The keys authorized to act on behalf of the issuer, and how many of them are required
+    pub fn issuer_pubkeys(&self) -> (Vec<&[u8]>, usize) {
+        match (&self.issuer_pubkeys, self.issuer_threshold) {
+            (Some(pubkeys), Some(threshold)) => {
+                (pubkeys.iter().map(Vec::as_slice).collect(), threshold)
+            }
+            _ => (vec![&self.issuer_pubkey], 1),
+        }
+    }
+
+    // Contracts are checked against the contract schema first, for clearer errors than serde's
+    // and to reject unknown fields
     fn from_contract(contract: &Value) -> Result<Self> {
+        validate_contract_schema(contract)?;
         Ok(serde_json::from_value(contract.clone())?)
     }
 
     fn validate(&self) -> Result<()> {
-        ensure!(self.version == 0, "unknown `version`");
+        for (_, check) in FIELD_CHECKS {
+            check(self)?;
+        }
+        Ok(())
+    }
 
-        ensure!(self.precision <= 8, "`precision` out of range");
-        ensure!(RE_NAME.is_match(&self.name), "invalid `name`");
+    fn check_name(&self) -> Result<()> {
+        validate_name(&self.name)
+            .and_then(|()| {
+                validate_name_length(&self.name, NAME_MIN_LEN, NAME_MAX_LEN, NAME_MAX_BYTES)
+            })
+            .map_err(|err| AssetError::InvalidName(err.to_string()).into())
+    }
 
+    fn check_ticker(&self) -> Result<()> {
         if let Some(ticker) = &self.ticker {
-            ensure!(RE_TICKER.is_match(ticker), "invalid `ticker`");
+            if !RE_TICKER.is_match(ticker) {
+                return Err(AssetError::InvalidTicker.into());
+            }
         }
+        Ok(())
+    }
+
+    fn validate_other(&self) -> Result<()> {
+        ensure!(self.version <= 1, "unknown `version`");
+        self.validate_metadata()?;
 
         if let Some(collection) = &self.collection {
             ensure!(RE_COLLECTION.is_match(collection), "invalid `collection`");
         }
 
+        if let Some(display_locale) = &self.display_locale {
+            ensure!(
+                DISPLAY_LOCALES.contains(&display_locale.as_str()),
+                "unsupported `display_locale`"
+            );
+        }
+        Ok(())
+    }
+
+    fn validate_precision(&self) -> Result<()> {
+        // unknown versions are reported by validate_other()
+        if let Some(range) = precision_range(self.version) {
+            ensure!(range.contains(&self.precision), "`precision` out of range");
+        }
+        Ok(())
+    }
+
+    fn validate_pubkeys(&self) -> Result<()> {
         verify_pubkey(&self.issuer_pubkey).context("invalid `issuer_pubkey`")?;
 
-        let AssetEntity::DomainName(domain) = &self.entity;
-        verify_domain_name(domain).context("invalid `entity` domain name")?;
+        match (&self.issuer_pubkeys, self.issuer_threshold) {
+            (None, None) => (),
+            (Some(pubkeys), Some(threshold)) => {
+                ensure!(
+                    pubkeys.len() <= MAX_ISSUER_PUBKEYS,
+                    "too many `issuer_pubkeys`"
+                );
+                ensure!(
+                    threshold >= 1 && threshold <= pubkeys.len(),
+                    "`issuer_threshold` out of range"
+                );
+                for pubkey in pubkeys {
+                    verify_pubkey(pubkey).context("invalid `issuer_pubkeys`")?;
+                }
+                ensure!(
+                    pubkeys.iter().collect::<HashSet<_>>().len() == pubkeys.len(),
+                    "duplicate `issuer_pubkeys`"
+                );
+                ensure!(
+                    pubkeys.contains(&self.issuer_pubkey),
+                    "`issuer_pubkeys` must include `issuer_pubkey`"
+                );
+            }
+            _ => bail!("`issuer_pubkeys` and `issuer_threshold` must be provided together"),
+        }
+        Ok(())
+    }
+
+    fn validate_entity(&self) -> Result<()> {
+        match &self.entity {
+            AssetEntity::DomainName(domain) | AssetEntity::DomainTxt(domain) => {
+                verify_domain_name(domain).context("invalid `entity` domain name")?
+            }
+            AssetEntity::Twitter(handle) => {
+                verify_twitter_handle(handle).context("invalid `entity` twitter handle")?
+            }
+            AssetEntity::GitHub(user) => {
+                verify_github_user(user).context("invalid `entity` github user")?
+            }
+            AssetEntity::Nostr(npub) => {
+                decode_npub(npub).context("invalid `entity` nostr npub")?;
+            }
+            AssetEntity::Custom { kind, value } => {
+                verify_custom_entity(kind, value).context("invalid `entity` custom entity")?
+            }
+        }
+        Ok(())
+    }
+
+    // The metadata fields introduced in contract version 1
+    fn validate_metadata(&self) -> Result<()> {
+        if self.version == 0 {
+            for (field, value) in &[
+                ("issuer_name", &self.issuer_name),
+                ("description", &self.description),
+                ("website", &self.website),
+                ("icon", &self.icon),
+            ] {
+                ensure!(value.is_none(), "`{}` requires contract version 1", field);
+            }
+            return Ok(());
+        }
+
+        if let Some(issuer_name) = &self.issuer_name {
+            let len = issuer_name.chars().count();
+            ensure!(
+                len >= 1 && len <= ISSUER_NAME_MAX_LEN,
+                "`issuer_name` length out of range"
+            );
+            ensure!(
+                !issuer_name.chars().any(char::is_control),
+                "invalid `issuer_name`"
+            );
+        }
+
+        if let Some(description) = &self.description {
+            ensure!(
+                description.chars().count() <= DESCRIPTION_MAX_LEN,
+                "`description` too long (maximum {} characters)",
+                DESCRIPTION_MAX_LEN
+            );
+        }
+
+        if let Some(website) = &self.website {
+            ensure!(website.len() <= WEBSITE_MAX_LEN, "`website` too long");
+            let url = Url::parse(website).context("invalid `website`")?;
+            ensure!(
+                url.scheme() == "https" && url.host_str().is_some(),
+                "`website` must be an https url"
+            );
+        }
+
+        if let Some(icon) = &self.icon {
+            ensure!(icon.len() <= WEBSITE_MAX_LEN, "`icon` too long");
+            let url = Url::parse(icon).context("invalid `icon`")?;
+            ensure!(
+                url.scheme() == "https" && url.host_str().is_some(),
+                "`icon` must be an https url"
+            );
+        }
+
+        Ok(())
+    }
+
+    // Operator policy checks, on top of the protocol rules enforced by validate()
+    fn validate_policy(&self, ctx: &VerifyContext) -> Result<()> {
+        validate_name_length(
+            &self.name,
+            ctx.name_min_len,
+            ctx.name_max_len,
+            ctx.name_max_bytes,
+        )?;
+
+        if self.ticker.is_none() && ctx.tickerless_min_name_chars > 0 {
+            let name_chars = self.name.chars().filter(|c| !c.is_whitespace()).count();
+            ensure!(
+                name_chars >= ctx.tickerless_min_name_chars,
+                "`name` of assets without a `ticker` must have at least {} non-whitespace characters",
+                ctx.tickerless_min_name_chars
+            );
+        }
+
+        Ok(())
+    }
 
+    // Reject tickers and names reserved by the operator, regardless of case
+    fn check_reserved_symbols(&self, ctx: &VerifyContext) -> Result<()> {
+        if let Some(ticker) = &self.ticker {
+            if ctx.reserved_symbols.contains(&ticker.to_lowercase()) {
+                return Err(
+                    AssetError::Reserved(format!("ticker `{}` is reserved", ticker)).into(),
+                );
+            }
+        }
+        if ctx.reserved_symbols.contains(&self.name.to_lowercase()) {
+            return Err(AssetError::Reserved(format!("name `{}` is reserved", self.name)).into());
+        }
         Ok(())
     }
 }
 
+// Names may contain any unicode text, but must be NFC-normalized so that the contract hash
+// committed to on-chain is stable regardless of how the issuer's input was composed.
+fn validate_name(name: &str) -> Result<()> {
+    ensure!(is_nfc(name), "`name` must be NFC-normalized");
+    for c in name.chars() {
+        ensure!(
+            !c.is_control()
+                && !NAME_BLACKLIST
+                    .iter()
+                    .any(|(from, to)| c >= *from && c <= *to),
+            "`name` contains a disallowed character (U+{:04X})",
+            c as u32
+        );
+    }
+    Ok(())
+}
+
+// NFC-normalize the `name` field of a contract, in place. This should be done before hashing
+// the contract for issuance, as non-normalized names are rejected by the registry.
+pub fn normalize_contract(contract: &mut Value) {
+    if let Some(Value::String(name)) = contract.get_mut("name") {
+        *name = name.nfc().collect();
+    }
+}
+
+fn validate_name_length(
+    name: &str,
+    min_len: usize,
+    max_len: usize,
+    max_bytes: usize,
+) -> Result<()> {
+    let len = name.chars().count();
+    ensure!(
+        len >= min_len,
+        "`name` too short (minimum {} characters)",
+        min_len
+    );
+    ensure!(
+        len <= max_len,
+        "`name` too long (maximum {} characters)",
+        max_len
+    );
+    ensure!(
+        name.len() <= max_bytes,
+        "`name` too long (maximum {} bytes when utf-8 encoded, got {})",
+        max_bytes,
+        name.len()
+    );
+    Ok(())
+}
+
+// The `precision` allowed for each contract version, or None for unknown versions
+pub fn precision_range(version: u8) -> Option<RangeInclusive<u8>> {
+    match version {
+        0 | 1 => Some(0..=8),
+        _ => None,
+    }
+}
+
+// The same for all versions. A version-specific default would require deserializing `precision`
+// as optional and filling it in once the version is known.
 fn default_precision() -> u8 {
     0
 }
 
 impl Asset {
+    // Load an asset json file, which may be gzip-compressed (as `<asset id>.json.gz`)
     pub fn load(path: path::PathBuf) -> Result<Asset> {
-        let contents = fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&contents)?)
+        let contents = read_json_file(&path)?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    // Parse an asset while retaining the exact bytes of its contract, which must be provided
+    // exactly as committed to
+    pub fn from_json(json: &str) -> Result<Asset> {
+        let mut asset: Asset = serde_json::from_str(json)?;
+        if asset.contract_raw.is_none() {
+            asset.contract_raw = Some(raw_contract_json(json)?);
+        }
+        Ok(asset)
     }
 
     pub fn id(&self) -> &AssetId {
@@ -109,53 +528,481 @@ impl Asset {
         &self.fields.entity
     }
 
+    // The (unconfidential) address of the `issuer_pubkey` on the network, for displaying which
+    // key controls the asset. Multisig issuers have no single address, this is only their first
+    // key's.
+    pub fn issuer_address(&self, network: Network, kind: AddressType) -> Result<String> {
+        verify_pubkey(&self.fields.issuer_pubkey).context("invalid `issuer_pubkey`")?;
+        let pubkey = bitcoin::PublicKey::from_slice(&self.fields.issuer_pubkey)
+            .context("invalid `issuer_pubkey`")?;
+        let params = network.address_params();
+        let address = match kind {
+            AddressType::P2pkh => Address::p2pkh(&pubkey, None, params),
+            AddressType::P2wpkh => Address::p2wpkh(&pubkey, None, params),
+        };
+        Ok(address.to_string())
+    }
+
+    // A copy of the asset recording the block its issuance was confirmed in (when known)
+    pub fn with_issuance_block(&self, issuance_block: Option<BlockId>) -> Asset {
+        let mut asset = self.clone();
+        if issuance_block.is_some() {
+            asset.issuance_block = issuance_block;
+        }
+        asset
+    }
+
     pub fn verify(&self, chain: Option<&ChainQuery>) -> Result<()> {
-        self.fields.validate()?;
+        self.verify_with(chain, &VerifyContext::default())
+    }
+
+    pub fn verify_with(&self, chain: Option<&ChainQuery>, ctx: &VerifyContext) -> Result<()> {
+        self.verify_capturing(chain, ctx).map(|_| ())
+    }
+
+    // Only check that the asset id commits to the contract and issuance prevout, which requires
+    // no network access
+    pub fn verify_commitment(&self) -> Result<()> {
+        verify_asset_commitment(self)
+    }
+
+    // Like verify_with(), but also returns what was observed during verification
+    pub fn verify_capturing(
+        &self,
+        chain: Option<&ChainQuery>,
+        ctx: &VerifyContext,
+    ) -> Result<Verified> {
+        enter_span!("verify", asset_id = %self.asset_id);
+        self.verify_offline(ctx)?;
+
+        let issuance_block = match chain {
+            Some(chain) => Some(in_step("on-chain issuance", || {
+                verify_asset_issuance_tx(chain, self, ctx)
+                    .and_then(|block| self.check_issuance_block(block))
+                    .context(AssetError::IssuanceFailed)
+            })?),
+            None => None,
+        };
+
+        let proof = in_step("entity link", || {
+            verify_asset_link_capturing(self, ctx).context(AssetError::EntityLinkFailed)
+        })?;
+
+        let icon = match &self.fields.icon {
+            Some(icon) => Some(
+                in_step("icon", || {
+                    verify_icon(icon, ctx).context(AssetError::InvalidIcon)
+                })?
+                .0,
+            ),
+            None => None,
+        };
 
-        verify_asset_commitment(self).context("failed verifying issuance commitment")?;
+        if let Some(oracle_url) = &ctx.oracle_url {
+            in_step("oracle", || {
+                verify_oracle(self, oracle_url).context(AssetError::OracleRejected)
+            })?;
+        }
+
+        Ok(Verified {
+            proof,
+            issuance_block,
+            icon,
+        })
+    }
+
+    // Like verify_capturing(), but using non-blocking requests
+    #[cfg(feature = "async")]
+    pub async fn verify_async(
+        &self,
+        chain: Option<&ChainQuery>,
+        ctx: &VerifyContext,
+    ) -> Result<Verified> {
+        self.verify_offline(ctx)?;
+
+        let issuance_block = match chain {
+            Some(chain) => Some(
+                verify_asset_issuance_tx_async(chain, self, ctx)
+                    .await
+                    .and_then(|block| self.check_issuance_block(block))
+                    .context(AssetError::IssuanceFailed)?,
+            ),
+            None => None,
+        };
+
+        let proof = verify_asset_link_async(self, ctx)
+            .await
+            .context(AssetError::EntityLinkFailed)?;
+
+        let icon = match &self.fields.icon {
+            Some(icon) => {
+                let (icon, ctx) = (icon.clone(), ctx.clone());
+                let (icon, _) = tokio::task::spawn_blocking(move || verify_icon(&icon, &ctx))
+                    .await?
+                    .context(AssetError::InvalidIcon)?;
+                Some(icon)
+            }
+            None => None,
+        };
+
+        if let Some(oracle_url) = &ctx.oracle_url {
+            verify_oracle_async(self, oracle_url)
+                .await
+                .context(AssetError::OracleRejected)?;
+        }
+
+        Ok(Verified {
+            proof,
+            issuance_block,
+            icon,
+        })
+    }
+
+    // Assets that were already verified against the chain must remain confirmed in the same
+    // block, a different one means the issuance was reorged
+    pub(crate) fn check_issuance_block(&self, block: BlockId) -> Result<BlockId> {
+        if let Some(recorded) = &self.issuance_block {
+            ensure!(
+                recorded.block_hash == block.block_hash,
+                "issuance block changed from {} to {} (reorg?)",
+                recorded.block_hash,
+                block.block_hash
+            );
+        }
+        Ok(block)
+    }
+
+    // Run each verification step separately, for diagnosing why an asset is rejected. Unlike
+    // verify_with(), all steps are run even if earlier ones fail. Steps that don't apply (like the
+    // on-chain issuance without a chain backend) are returned as None.
+    pub fn verify_steps(
+        &self,
+        chain: Option<&ChainQuery>,
+        ctx: &VerifyContext,
+    ) -> Vec<(&'static str, Option<Result<()>>)> {
+        enter_span!("verify_steps", asset_id = %self.asset_id);
+        let icon = self.fields.icon.as_ref();
+        vec![
+            ("fields", Some(self.verify_policy(ctx))),
+            ("commitment", Some(verify_asset_commitment(self))),
+            ("contract fields", Some(verify_asset_fields(self))),
+            ("entity link", Some(verify_asset_link(self, ctx))),
+            ("icon", icon.map(|icon| verify_icon(icon, ctx).map(|_| ()))),
+            (
+                "on-chain issuance",
+                chain.map(|chain| {
+                    verify_asset_issuance_tx(chain, self, ctx)
+                        .and_then(|block| self.check_issuance_block(block))
+                        .map(|_| ())
+                }),
+            ),
+            (
+                "oracle",
+                ctx.oracle_url.as_ref().map(|url| verify_oracle(self, url)),
+            ),
+        ]
+    }
 
-        verify_asset_fields(self).context("failed verifying asset fields")?;
+    // Like verify_with(), but runs all independent checks instead of stopping at the first
+    // failure, returning every failure along with the name of its check. The on-chain issuance,
+    // icon and oracle are only checked when available, and the entity link once the entity is
+    // well-formed.
+    pub fn verify_all(
+        &self,
+        chain: Option<&ChainQuery>,
+        ctx: &VerifyContext,
+    ) -> Vec<(&'static str, Error)> {
+        enter_span!("verify_all", asset_id = %self.asset_id);
+        let mut failures = run_all_checks(self.offline_checks(ctx));
 
+        let mut checks: Vec<Check<'_>> = vec![];
+        if !failures.iter().any(|(name, _)| *name == "entity") {
+            checks.push((
+                "entity link",
+                Box::new(move || {
+                    Ok(verify_asset_link(self, ctx).context(AssetError::EntityLinkFailed)?)
+                }),
+            ));
+        }
+        if let Some(icon) = &self.fields.icon {
+            checks.push((
+                "icon",
+                Box::new(move || {
+                    Ok(verify_icon(icon, ctx)
+                        .map(|_| ())
+                        .context(AssetError::InvalidIcon)?)
+                }),
+            ));
+        }
         if let Some(chain) = chain {
-            verify_asset_issuance_tx(chain, self).context("failed verifying on-chain issuance")?;
-            // XXX keep block id?
+            checks.push((
+                "on-chain issuance",
+                Box::new(move || {
+                    Ok(verify_asset_issuance_tx(chain, self, ctx)
+                        .and_then(|block| self.check_issuance_block(block))
+                        .map(|_| ())
+                        .context(AssetError::IssuanceFailed)?)
+                }),
+            ));
         }
+        if let Some(oracle_url) = &ctx.oracle_url {
+            checks.push((
+                "oracle",
+                Box::new(move || {
+                    Ok(verify_oracle(self, oracle_url).context(AssetError::OracleRejected)?)
+                }),
+            ));
+        }
+        failures.extend(run_all_checks(checks));
+        failures
+    }
 
-        verify_asset_link(self).context("failed verifying linked entity")?;
+    // The verification checks that don't require network access
+    pub(crate) fn verify_offline(&self, ctx: &VerifyContext) -> Result<()> {
+        run_checks(self.offline_checks(ctx))
+    }
+
+    fn offline_checks<'a>(&'a self, ctx: &'a VerifyContext) -> Vec<Check<'a>> {
+        let mut checks = self.policy_checks(ctx);
+        checks.push((
+            "commitment",
+            Box::new(move || {
+                Ok(
+                    verify_asset_commitment(self)
+                        .context("failed verifying issuance commitment")?,
+                )
+            }),
+        ));
+        checks.push((
+            "contract fields",
+            Box::new(move || {
+                Ok(verify_asset_fields(self).context("failed verifying asset fields")?)
+            }),
+        ));
+        checks
+    }
+
+    // The checks of the asset id and the issuer fields against the protocol rules and the
+    // operator's policy
+    fn verify_policy(&self, ctx: &VerifyContext) -> Result<()> {
+        run_checks(self.policy_checks(ctx))
+    }
+
+    fn policy_checks<'a>(&'a self, ctx: &'a VerifyContext) -> Vec<Check<'a>> {
+        let mut checks: Vec<Check<'a>> = vec![
+            (
+                "contract size",
+                Box::new(move || verify_contract_size(&self.contract, ctx)),
+            ),
+            ("asset id", Box::new(move || self.check_reserved_asset(ctx))),
+            (
+                "entity domain",
+                Box::new(move || self.check_allowed_domain(ctx)),
+            ),
+        ];
+        for &(name, check) in FIELD_CHECKS {
+            checks.push((name, Box::new(move || check(&self.fields))));
+        }
+        checks.push((
+            "name policy",
+            Box::new(move || {
+                self.fields
+                    .validate_policy(ctx)
+                    .map_err(|err| AssetError::InvalidName(err.to_string()).into())
+            }),
+        ));
+        checks.push((
+            "reserved symbols",
+            Box::new(move || self.fields.check_reserved_symbols(ctx)),
+        ));
+        checks
+    }
 
+    fn check_reserved_asset(&self, ctx: &VerifyContext) -> Result<()> {
+        if self.asset_id == ctx.network.policy_asset() {
+            return Err(AssetError::Reserved("cannot register the policy asset".into()).into());
+        }
+        if ctx.reserved_assets.contains(&self.asset_id) {
+            return Err(AssetError::Reserved("cannot register a reserved asset".into()).into());
+        }
         Ok(())
     }
 
-    pub fn verify_deletion(&self, signature: &[u8]) -> Result<()> {
-        verify_bitcoin_msg(
-            &EC,
-            &self.fields.issuer_pubkey,
-            &signature,
-            &format_deletion_sig_msg(self),
-        )
+    // Checked with the other policy checks, to fail before fetching anything from the domain
+    fn check_allowed_domain(&self, ctx: &VerifyContext) -> Result<()> {
+        let allowlist = match &ctx.domain_allowlist {
+            Some(allowlist) => allowlist,
+            None => return Ok(()),
+        };
+        match self.entity() {
+            AssetEntity::DomainName(domain) | AssetEntity::DomainTxt(domain)
+                if domain_allowed(allowlist, domain) =>
+            {
+                Ok(())
+            }
+            _ => Err(AssetError::DomainNotPermitted.into()),
+        }
+    }
+
+    // Parse and fully verify a submitted asset json, returning it normalized for storing: the
+    // exact contract bytes are retained only when they differ from the contract's canonical
+    // serialization, as the commitment is then to them. Errors are annotated with the intake stage
+    // that failed.
+    pub fn intake(json: &str, chain: Option<&ChainQuery>) -> Result<Asset> {
+        Asset::intake_with(json, chain, &VerifyContext::default())
+    }
+
+    pub fn intake_with(
+        json: &str,
+        chain: Option<&ChainQuery>,
+        ctx: &VerifyContext,
+    ) -> Result<Asset> {
+        let mut asset = Asset::from_json(json).context("intake: invalid asset json")?;
+
+        // contracts that have no canonical serialization (like ones with floats) are rejected
+        // upfront with a clear error
+        let canonical = canonical_contract_json(&asset.contract)
+            .context("intake: contract cannot be canonicalized")?;
+        if asset.contract_raw.as_ref() == Some(&canonical) {
+            asset.contract_raw = None;
+        }
+
+        // repeated by verify_with() below, but checked upfront to report the stage that failed.
+        // these need no network access and are cheap.
+        asset
+            .fields
+            .validate()
+            .context("intake: invalid asset fields")?;
+        verify_asset_commitment(&asset).context("intake: asset id does not commit to contract")?;
+
+        asset
+            .verify_with(chain, ctx)
+            .context("intake: asset verification failed")?;
+
+        Ok(asset)
+    }
+
+    // Verify the issuer's signature over the deletion message of the registry identified by
+    // `registry_id`, with the authorization's sequence number. With `accept_legacy`, signatures
+    // over the legacy messages that are not bound to any registry or carry no sequence number
+    // (see legacy_deletion_sig_msg()) are accepted too, and are the only ones accepted without a
+    // registry id or sequence number.
+    //
+    // Returns the sequence number when it was covered by the signature, or None when a legacy
+    // message verified. Only signed sequence numbers may be recorded for replay protection.
+    pub fn verify_deletion(
+        &self,
+        signature: &[u8],
+        registry_id: Option<&str>,
+        sequence: Option<u64>,
+        accept_legacy: bool,
+    ) -> Result<Option<u64>> {
+        self.verify_deletion_sigs(&[signature.to_vec()], registry_id, sequence, accept_legacy)
+    }
+
+    // Verify the deletion is signed by the required number of issuer keys, for multisig issuers.
+    // All the signatures must be over the same message.
+    pub fn verify_deletion_sigs(
+        &self,
+        signatures: &[Vec<u8>],
+        registry_id: Option<&str>,
+        sequence: Option<u64>,
+        accept_legacy: bool,
+    ) -> Result<Option<u64>> {
+        let verify_legacy = || {
+            let msg = legacy_deletion_sig_msg(&self.asset_id);
+            verify_issuer_sigs(&self.fields, signatures, &msg).or_else(|err| match registry_id {
+                // version 0 messages, bound to the registry but without a sequence number
+                Some(registry_id) => {
+                    let msg = IssuerMsg {
+                        action: IssuerAction::Delete,
+                        version: 0,
+                        registry_id: registry_id.to_string(),
+                        asset_id: self.asset_id,
+                        sequence: 0,
+                    };
+                    verify_issuer_sigs(&self.fields, signatures, &msg.to_string())
+                }
+                None => Err(err),
+            })
+        };
+        let res = match (registry_id, sequence) {
+            (Some(registry_id), Some(sequence)) => {
+                let msg = deletion_sig_msg(&self.asset_id, registry_id, sequence).to_string();
+                match verify_issuer_sigs(&self.fields, signatures, &msg) {
+                    Ok(()) => Ok(Some(sequence)),
+                    // report the error for the current message format
+                    Err(err) if accept_legacy => verify_legacy().map(|_| None).map_err(|_| err),
+                    Err(err) => Err(err),
+                }
+            }
+            _ if accept_legacy => verify_legacy().map(|_| None),
+            (None, _) => Err(format_err!("deletions require a registry id")),
+            (Some(_), None) => Err(format_err!("deletions require a sequence number")),
+        };
+        res.map_err(|err| AssetError::InvalidSignature(join_err(&err)).into())
+    }
+
+    // Verify the issuer's signature over the update message of the registry identified by
+    // `registry_id`, for multisig issuers too. There are no legacy update messages, the sequence
+    // number is always signed for and is returned for recording.
+    pub fn verify_update_sigs(
+        &self,
+        signatures: &[Vec<u8>],
+        registry_id: &str,
+        sequence: u64,
+    ) -> Result<u64> {
+        let msg = update_sig_msg(&self.asset_id, registry_id, sequence).to_string();
+        verify_issuer_sigs(&self.fields, signatures, &msg)
+            .map_err(|err| AssetError::InvalidSignature(join_err(&err)))?;
+        Ok(sequence)
     }
 
     pub fn contract_hash(&self) -> Result<ContractHash> {
-        contract_json_hash(&self.contract)
+        match &self.contract_raw {
+            Some(contract_raw) => {
+                ensure!(
+                    serde_json::from_str::<Value>(contract_raw)? == self.contract,
+                    "`contract_raw` does not match `contract`"
+                );
+                Ok(contract_bytes_hash(contract_raw.as_bytes()))
+            }
+            None => contract_json_hash(&self.contract),
+        }
     }
 
     pub fn from_request(req: AssetRequest, chain: &ChainQuery) -> Result<Self> {
-        let mut asset_data = chain
-            .get_asset(&req.asset_id)?
+        let (issuance_txin, issuance_prevout) = chain
+            .get_issuance(&req.asset_id, req.issuance_txid.as_ref())?
             .or_err("asset id not found")?;
 
         let fields =
             AssetFields::from_contract(&req.contract).context("invalid contract fields")?;
 
-        let issuance_txin = serde_json::from_value(asset_data["issuance_txin"].take())?;
-        let issuance_prevout = serde_json::from_value(asset_data["issuance_prevout"].take())?;
-
         Ok(Asset {
             asset_id: req.asset_id,
             contract: req.contract,
+            contract_raw: req.contract_raw,
             fields,
             issuance_txin,
             issuance_prevout,
+            issuance_block: None,
+            signature: None,
+        })
+    }
+
+    pub fn from_elements_entry(asset_id: AssetId, entry: ElementsAssetEntry) -> Result<Self> {
+        let fields =
+            AssetFields::from_contract(&entry.contract).context("invalid contract fields")?;
+
+        Ok(Asset {
+            asset_id,
+            contract: entry.contract,
+            contract_raw: None,
+            fields,
+            issuance_txin: entry.issuance_txin,
+            issuance_prevout: entry.issuance_prevout,
+            issuance_block: None,
             signature: None,
         })
     }
@@ -173,14 +1020,171 @@ impl Asset {
     }
 }
 
+// The representation used by Elements asset registries, keyed by the asset id in a json map.
+// The issuer fields are not included, as they are derived from the contract.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ElementsAssetEntry {
+    pub contract: Value,
+    pub issuance_txin: TxInput,
+    pub issuance_prevout: OutPoint,
+}
+
+impl From<&Asset> for ElementsAssetEntry {
+    fn from(asset: &Asset) -> Self {
+        ElementsAssetEntry {
+            contract: asset.contract.clone(),
+            issuance_txin: asset.issuance_txin.clone(),
+            issuance_prevout: asset.issuance_prevout,
+        }
+    }
+}
+
 pub fn contract_json_hash(contract: &Value) -> Result<ContractHash> {
-    // serde_json sorts keys lexicographically
-    let contract_str = serde_json::to_string(contract)?;
+    let contract_str = canonical_contract_json(contract)?;
+
+    Ok(contract_bytes_hash(contract_str.as_bytes()))
+}
+
+// Serialize the contract in the canonical json form that the contract hash commits to: object keys
+// sorted lexicographically (by their utf-8 bytes) at every level, numbers as plain integers, only
+// `"`, `\` and control characters escaped in strings, and no insignificant whitespace.
+//
+// This is done explicitly rather than relying on serde_json's serialization, whose key order and
+// number formatting depend on its enabled features and version. Numbers other than integer literals
+// are rejected, as their formatting cannot be pinned down (and no contract field uses them).
+pub fn canonical_contract_json(contract: &Value) -> Result<String> {
+    let mut out = String::new();
+    write_canonical_json(contract, &mut out)?;
+    Ok(out)
+}
+
+fn write_canonical_json(value: &Value, out: &mut String) -> Result<()> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(number) => out.push_str(&canonical_number(number)?),
+        Value::String(string) => write_canonical_str(string, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_str(key, out);
+                out.push(':');
+                write_canonical_json(value, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+// Integers are written in their shortest decimal form. Floats are rejected even when their value is
+// integral (like `1.0` or `1e2`), as rewriting them as integers would change the bytes that
+// contracts issued with them were committed to.
+fn canonical_number(number: &serde_json::Number) -> Result<String> {
+    if let Some(n) = number.as_u64() {
+        return Ok(n.to_string());
+    }
+    if let Some(n) = number.as_i64() {
+        return Ok(n.to_string());
+    }
+    bail!("non-integer number {} cannot be canonicalized", number)
+}
+
+// Escapes match serde_json's, so that contracts without floats serialize identically
+fn write_canonical_str(string: &str, out: &mut String) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// The contract hash committed to by the asset id, as the hex string used for `rawissueasset`'s
+// `contract_hash` parameter. This is the SHA256 (single, not double) of the canonical json
+// serialization of the contract (see canonical_contract_json()), hex-encoded with its bytes
+// *reversed*, like other bitcoin/elements hashes.
+pub fn contract_hash_hex(contract: &Value) -> Result<String> {
+    Ok(contract_json_hash(contract)?.to_hex())
+}
+
+// Like contract_hash_hex(), for a contract that is already serialized in its canonical json
+// form. The string is hashed as-is, so any other serialization results in a different hash.
+pub fn canonical_contract_hash_hex(canonical_json: &str) -> String {
+    contract_bytes_hash(canonical_json.as_bytes()).to_hex()
+}
+
+// The asset id that issuing the contract by spending the prevout will result in (the same
+// derivation verify_asset_commitment() checks), for issuers to compute before broadcasting
+pub fn compute_asset_id(prevout: OutPoint, contract: &Value) -> Result<AssetId> {
+    let contract_hash = contract_json_hash(contract)?;
+    let entropy = AssetId::generate_asset_entropy(prevout, contract_hash);
+    Ok(AssetId::from_entropy(entropy))
+}
 
+// Reject contracts whose json serialization exceeds the configured size limit
+pub fn verify_contract_size(contract: &Value, ctx: &VerifyContext) -> Result<()> {
+    let size = serde_json::to_string(contract)?.len();
+    if size > ctx.contract_max_size {
+        return Err(AssetError::InvalidField(format!(
+            "contract too large ({} bytes, up to {} allowed)",
+            size, ctx.contract_max_size
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+// Decode a CBOR-encoded contract into its json representation. The commitment is always made to
+// the canonical json serialization, CBOR is only supported as an input format.
+#[cfg(feature = "cbor")]
+pub fn contract_from_cbor(bytes: &[u8]) -> Result<Value> {
+    let contract: Value = serde_cbor::from_slice(bytes).context("invalid cbor contract")?;
+    ensure!(contract.is_object(), "contract must be a map");
+    Ok(contract)
+}
+
+fn contract_bytes_hash(contract: &[u8]) -> ContractHash {
     // use the ContractHash representation for correct (reverse) hex encoding,
     // but use a single SHA256 instead of the double hash assumed by ContractHash::hash()
-    let hash = sha256::Hash::hash(&contract_str.as_bytes());
-    Ok(ContractHash::from_inner(hash.into_inner()))
+    let hash = sha256::Hash::hash(contract);
+    ContractHash::from_inner(hash.into_inner())
+}
+
+// Extract the exact bytes of the `contract` field from a json object
+fn raw_contract_json(json: &str) -> Result<String> {
+    #[derive(Deserialize)]
+    struct WithRawContract<'a> {
+        #[serde(borrow)]
+        contract: &'a RawValue,
+    }
+    let parsed: WithRawContract = serde_json::from_str(json)?;
+    Ok(parsed.contract.get().to_string())
 }
 
 #[cfg_attr(feature = "cli", derive(StructOpt))]
@@ -205,6 +1209,31 @@ pub struct AssetRequest {
         )
     )]
     pub contract: Value,
+
+    #[cfg_attr(feature = "cli", structopt(skip))]
+    #[serde(skip)]
+    pub contract_raw: Option<String>,
+
+    // Needed to find the issuance with chain backends that have no asset index (like elementsd)
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "issuance-txid",
+            help = "The issuance transaction id (required by registries verifying with elementsd)",
+            parse(try_from_str = Txid::from_hex)
+        )
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuance_txid: Option<Txid>,
+}
+
+impl AssetRequest {
+    // Parse a request while retaining the exact bytes of its contract
+    pub fn from_json(json: &str) -> Result<AssetRequest> {
+        let mut req: AssetRequest = serde_json::from_str(json)?;
+        req.contract_raw = Some(raw_contract_json(json)?);
+        Ok(req)
+    }
 }
 
 // Verify the asset id commits to the provided contract and prevout
@@ -213,7 +1242,28 @@ fn verify_asset_commitment(asset: &Asset) -> Result<()> {
     let entropy = AssetId::generate_asset_entropy(asset.issuance_prevout, contract_hash);
     let asset_id = AssetId::from_entropy(entropy);
 
-    ensure!(asset.asset_id == asset_id, "invalid asset commitment");
+    // reissuance tokens are derived from the same entropy, but are not assets of their own
+    if asset.asset_id == AssetId::reissuance_token_from_entropy(entropy, false)
+        || asset.asset_id == AssetId::reissuance_token_from_entropy(entropy, true)
+    {
+        return Err(AssetError::CommitmentMismatch(
+            "reissuance tokens cannot be registered".into(),
+        )
+        .into());
+    }
+    if asset.asset_id != asset_id {
+        return Err(AssetError::CommitmentMismatch("invalid asset commitment".into()).into());
+    }
+
+    // the issuance input is not committed to, and can only be checked against the prevout it
+    // spends once the issuance transaction is fetched (see chain::verify_asset_issuance_tx). a
+    // transaction can never spend its own output though, which catches some desynced assets.
+    if asset.issuance_txin.txid == asset.issuance_prevout.txid {
+        return Err(AssetError::CommitmentMismatch(
+            "`issuance_txin` cannot spend `issuance_prevout` from the same transaction".into(),
+        )
+        .into());
+    }
 
     debug!(
         "verified asset commitment, asset id {} commits to prevout {:?} and contract hash {} ({:?})",
@@ -243,20 +1293,42 @@ fn verify_asset_fields(asset: &Asset) -> Result<()> {
             */
         }
         None => {
+            verify_contract_keys(&asset.contract, asset.fields.version)?;
+
             // Otherwise, verify that the fields match the commited contract
-            ensure!(
-                asset.fields == AssetFields::from_contract(&asset.contract)?,
-                "fields mismatch commitment"
-            );
+            if asset.fields != AssetFields::from_contract(&asset.contract)? {
+                return Err(
+                    AssetError::CommitmentMismatch("fields mismatch commitment".into()).into(),
+                );
+            }
             Ok(())
         }
     }
 }
 
-// Signed fields are currently unsupported, only commited ones
-/*
-fn verify_asset_fields_sig(
-    pubkey: &[u8],
+// Verify that the contract only has the keys known for its version, even if null
+fn verify_contract_keys(contract: &Value, version: u8) -> Result<()> {
+    let contract = contract
+        .as_object()
+        .or_err("contract must be a json object")?;
+    for key in contract.keys() {
+        let known = CONTRACT_KEYS_V0.contains(&key.as_str())
+            || (version >= 1 && CONTRACT_KEYS_V1.contains(&key.as_str()));
+        if !known {
+            return Err(AssetError::CommitmentMismatch(format!(
+                "unknown contract field `{}` for version {}",
+                key, version
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+// Signed fields are currently unsupported, only commited ones
+/*
+fn verify_asset_fields_sig(
+    pubkey: &[u8],
     signature: &str,
     asset_id: &AssetId,
     fields: &AssetFields,
@@ -264,7 +1336,7 @@ fn verify_asset_fields_sig(
     let signature = base64::decode(signature).context("invalid signature base64")?;
     let msg = format_fields_sig_msg(asset_id, fields);
 
-    verify_bitcoin_msg(&EC, &pubkey, &signature, &msg)?;
+    verify_msg_sig(&EC, &pubkey, &signature, &msg)?;
 
     debug!(
         "verified asset signature, issuer pubkey {} signed fields {:?}",
@@ -285,14 +1357,130 @@ fn format_fields_sig_msg(asset_id: &AssetId, fields: &AssetFields) -> String {
 }
 */
 
-fn format_deletion_sig_msg(asset: &Asset) -> String {
-    format!("remove {} from registry", asset.asset_id)
+// Verify that enough issuer keys signed the message (each key is counted once)
+fn verify_issuer_sigs(fields: &AssetFields, signatures: &[Vec<u8>], msg: &str) -> Result<()> {
+    let (pubkeys, threshold) = fields.issuer_pubkeys();
+
+    if pubkeys.len() == 1 {
+        // report the underlying verification error for single key issuers
+        let signature = signatures.first().or_err("missing signature")?;
+        return verify_msg_sig(&EC, pubkeys[0], signature, msg);
+    }
+
+    let signed = pubkeys
+        .iter()
+        .filter(|pubkey| {
+            signatures
+                .iter()
+                .any(|signature| verify_msg_sig(&EC, pubkey, signature, msg).is_ok())
+        })
+        .count();
+
+    ensure!(
+        signed >= threshold,
+        "not enough valid issuer signatures ({} of {} required)",
+        signed,
+        threshold
+    );
+    Ok(())
+}
+
+// The version tag of the issuer message format
+pub const DELETION_MSG_VERSION: u8 = 1;
+
+// The registry operations issuers can authorize with a signed IssuerMsg
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IssuerAction {
+    Delete,
+    Update,
+}
+
+impl fmt::Display for IssuerAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            IssuerAction::Delete => "delete",
+            IssuerAction::Update => "update",
+        })
+    }
+}
+
+// The message issuers sign to authorize an operation on their asset in a registry, serialized as
+// `liquid-asset-<action>:<version>:<registry id>:<asset id>:<sequence>`. Binding the registry id
+// prevents replaying the authorization against other registries, and the sequence number (which
+// must increase with every authorization accepted for the asset, a unix timestamp will do)
+// prevents replaying it against the same registry. The sequence is shared by all the actions, so
+// that an older authorization of one cannot be used once a newer one of the other was accepted.
+//
+// Version 0 messages have no sequence number and are only accepted as legacy deletions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IssuerMsg {
+    pub action: IssuerAction,
+    pub version: u8,
+    pub registry_id: String,
+    pub asset_id: AssetId,
+    pub sequence: u64,
+}
+
+impl fmt::Display for IssuerMsg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "liquid-asset-{}:{}:{}:{}",
+            self.action,
+            self.version,
+            self.registry_id,
+            self.asset_id.to_hex()
+        )?;
+        if self.version > 0 {
+            write!(f, ":{}", self.sequence)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn deletion_sig_msg(asset_id: &AssetId, registry_id: &str, sequence: u64) -> IssuerMsg {
+    IssuerMsg {
+        action: IssuerAction::Delete,
+        version: DELETION_MSG_VERSION,
+        registry_id: registry_id.to_string(),
+        asset_id: *asset_id,
+        sequence,
+    }
+}
+
+pub fn update_sig_msg(asset_id: &AssetId, registry_id: &str, sequence: u64) -> IssuerMsg {
+    IssuerMsg {
+        action: IssuerAction::Update,
+        ..deletion_sig_msg(asset_id, registry_id, sequence)
+    }
+}
+
+// The deletion message used before registry ids, valid for any registry
+pub fn legacy_deletion_sig_msg(asset_id: &AssetId) -> String {
+    format!("remove {} from registry", asset_id)
+}
+
+// Registry ids are restricted to characters that cannot be confused with the message's separators
+pub fn validate_registry_id(registry_id: &str) -> Result<()> {
+    ensure!(
+        !registry_id.is_empty()
+            && registry_id.len() <= 64
+            && registry_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_'),
+        "invalid registry id (expected up to 64 alphanumeric, `.`, `-` or `_` characters)"
+    );
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context::{load_domain_allowlist, load_reserved_symbols};
+    use crate::errors::asset_error;
+    use bitcoin::BlockHash;
     use bitcoin_hashes::hex::ToHex;
+    use std::fs;
     use std::path::PathBuf;
 
     #[test]
@@ -311,6 +1499,744 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_tickerless_name_policy() -> Result<()> {
+        let mut fields = Asset::load(PathBuf::from("test/asset-b1405e.json"))?.fields;
+        fields.ticker = None;
+        fields.name = " x ".to_string();
+
+        let ctx = VerifyContext::default();
+        assert!(fields.validate_policy(&ctx).is_ok());
+
+        let ctx = VerifyContext {
+            tickerless_min_name_chars: 2,
+            ..Default::default()
+        };
+        assert!(fields.validate_policy(&ctx).is_err());
+
+        fields.name = "x y".to_string();
+        assert!(fields.validate_policy(&ctx).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unicode_name() -> Result<()> {
+        assert!(validate_name("Foo Coin").is_ok());
+        assert!(validate_name("Caf\u{e9} \u{20bf} \u{91d1}").is_ok());
+
+        // decomposed e + combining acute accent is not NFC
+        assert!(validate_name("Cafe\u{301}").is_err());
+        assert!(validate_name("Foo\nCoin").is_err());
+        assert!(validate_name("Foo\u{202e}nioC").is_err());
+        assert!(validate_name("Foo\u{200b}Coin").is_err());
+
+        let mut contract = json!({ "name": "Cafe\u{301}", "ticker": "CAF" });
+        normalize_contract(&mut contract);
+        assert_eq!(contract["name"], "Caf\u{e9}");
+        assert!(validate_name(contract["name"].as_str().unwrap()).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_length() -> Result<()> {
+        let mut fields = Asset::load(PathBuf::from("test/asset-b1405e.json"))?.fields;
+        let ctx = VerifyContext {
+            name_min_len: 3,
+            name_max_len: 10,
+            ..Default::default()
+        };
+
+        fields.name = "ab".to_string();
+        fields.validate()?;
+        let err = fields.validate_policy(&ctx).unwrap_err();
+        assert!(err.to_string().starts_with("`name` too short"));
+
+        fields.name = "a".repeat(11);
+        let err = fields.validate_policy(&ctx).unwrap_err();
+        assert!(err.to_string().starts_with("`name` too long"));
+
+        fields.name = "a".repeat(256);
+        let err = fields.validate().unwrap_err();
+        assert!(err.to_string().starts_with("`name` too long"));
+
+        fields.name = "".to_string();
+        let err = fields.validate().unwrap_err();
+        assert!(err.to_string().starts_with("`name` too short"));
+
+        fields.name = "δοκιμή".to_string();
+        fields.validate()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_byte_length() -> Result<()> {
+        let mut fields = Asset::load(PathBuf::from("test/asset-b1405e.json"))?.fields;
+
+        // exactly at and one over the character limit, with single-byte characters
+        fields.name = "a".repeat(NAME_MAX_LEN);
+        fields.validate()?;
+        fields.name = "a".repeat(NAME_MAX_LEN + 1);
+        let err = fields.validate().unwrap_err();
+        assert!(err.to_string().contains("characters"));
+
+        // exactly at and one over the byte limit, with 3-byte characters
+        fields.name = "金".repeat(NAME_MAX_BYTES / 3);
+        assert_eq!(fields.name.len(), NAME_MAX_BYTES);
+        fields.validate()?;
+        fields.name.push('a');
+        assert_eq!(fields.name.len(), NAME_MAX_BYTES + 1);
+        let err = fields.validate().unwrap_err();
+        assert!(err.to_string().contains("bytes"));
+
+        // within the character limit but over the byte limit
+        fields.name = "金".repeat(NAME_MAX_LEN);
+        let err = fields.validate().unwrap_err();
+        assert!(err.to_string().contains("bytes"));
+
+        let ctx = VerifyContext {
+            name_max_bytes: 12,
+            ..Default::default()
+        };
+        fields.name = "金".repeat(4);
+        fields.validate_policy(&ctx)?;
+        fields.name = "金".repeat(5);
+        let err = fields.validate_policy(&ctx).unwrap_err();
+        assert!(err.to_string().contains("maximum 12 bytes"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_contract_extra_fields() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        verify_asset_fields(&asset)?;
+
+        asset.contract["evil_field"] = json!("hidden data");
+        let err = verify_asset_fields(&asset).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown contract field `evil_field` for version 0"
+        );
+        asset.contract.as_object_mut().unwrap().remove("evil_field");
+
+        // version 1 fields are unknown to version 0 contracts, even when null
+        asset.contract["issuer_name"] = Value::Null;
+        let err = verify_asset_fields(&asset).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown contract field `issuer_name` for version 0"
+        );
+        assert_eq!(
+            AssetFields::from_contract(&asset.contract)?,
+            asset.fields,
+            "hidden from the fields"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_contract_v1_metadata() -> Result<()> {
+        let mut contract = Asset::load(PathBuf::from("test/asset-b1405e.json"))?.contract;
+        contract["issuer_name"] = json!("PPP Inc.");
+        contract["description"] = json!("The PPP coin");
+        contract["website"] = json!("https://ppp.example/");
+
+        // only available from version 1
+        let err = AssetFields::from_contract(&contract)?
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "`issuer_name` requires contract version 1");
+
+        contract["version"] = json!(1);
+        let fields = AssetFields::from_contract(&contract)?;
+        fields.validate()?;
+        assert_eq!(fields.issuer_name, Some("PPP Inc.".to_string()));
+        assert_eq!(fields.website, Some("https://ppp.example/".to_string()));
+
+        // the fields round-trip, so that they match the committed contract
+        let fields_json = serde_json::to_value(&fields)?;
+        assert_eq!(fields_json["description"], contract["description"]);
+        assert_eq!(AssetFields::from_contract(&fields_json)?, fields);
+
+        contract["website"] = json!("http://ppp.example/");
+        assert!(AssetFields::from_contract(&contract)?.validate().is_err());
+        contract["website"] = json!("ppp.example");
+        assert!(AssetFields::from_contract(&contract)?.validate().is_err());
+        contract["website"] = json!("https://ppp.example/");
+
+        contract["icon"] = json!("https://ppp.example/icon.png");
+        AssetFields::from_contract(&contract)?.validate()?;
+        contract["icon"] = json!("http://ppp.example/icon.png");
+        assert!(AssetFields::from_contract(&contract)?.validate().is_err());
+
+        contract["version"] = json!(2);
+        assert!(AssetFields::from_contract(&contract)?.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_locale() -> Result<()> {
+        let mut contract = Asset::load(PathBuf::from("test/asset-b1405e.json"))?.contract;
+        assert_eq!(AssetFields::from_contract(&contract)?.display_locale, None);
+
+        contract["display_locale"] = json!("de-DE");
+        let fields = AssetFields::from_contract(&contract)?;
+        assert_eq!(fields.display_locale, Some("de-DE".to_string()));
+        fields.validate()?;
+
+        contract["display_locale"] = json!("xx-YY");
+        assert!(AssetFields::from_contract(&contract)?.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_precision_range() -> Result<()> {
+        let mut fields = Asset::load(PathBuf::from("test/asset-b1405e.json"))?.fields;
+
+        for version in 0..=1 {
+            fields.version = version;
+            for precision in &[0, 8] {
+                fields.precision = *precision;
+                fields.validate()?;
+            }
+            fields.precision = 9;
+            let err = fields.validate().unwrap_err();
+            assert_eq!(err.to_string(), "`precision` out of range");
+            assert_eq!(precision_range(version), Some(0..=8));
+        }
+
+        // unknown versions have no precision range, and fail on the version itself
+        assert_eq!(precision_range(2), None);
+        fields.version = 2;
+        fields.precision = 0;
+        assert_eq!(
+            fields.validate().unwrap_err().to_string(),
+            "unknown `version`"
+        );
+        assert!(fields.validate_precision().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_intake() -> Result<()> {
+        crate::entity::tests::spawn_mock_verifier_server();
+        let json = fs::read_to_string("test/asset-b1405e.json")?;
+        let asset = Asset::intake(&json, None)?;
+        let expected = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        assert_eq!(asset.asset_id, expected.asset_id);
+        assert_eq!(asset.contract, expected.contract);
+        // the submitted contract is canonical, so there are no raw bytes to retain
+        assert_eq!(asset.contract_raw, None);
+
+        // the commitment is checked against the exact submitted bytes
+        let spaced = json.replace(
+            r#""name":"PPP coin","ticker""#,
+            r#""name": "PPP coin","ticker""#,
+        );
+        let err = Asset::intake(&spaced, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "intake: asset id does not commit to contract"
+        );
+
+        // contracts without a canonical serialization are rejected before verifying
+        let mut value: Value = serde_json::from_str(&json)?;
+        value["contract"]["precision"] = json!(8.0);
+        let err = Asset::intake(&value.to_string(), None).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("intake: contract cannot be canonicalized"));
+
+        let mut value: Value = serde_json::from_str(&json)?;
+        value["contract"]["name"] = json!("Other coin");
+        let err = Asset::intake(&value.to_string(), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "intake: asset id does not commit to contract"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reserved_assets() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        asset.asset_id = Network::Liquid.policy_asset();
+        let err = asset.verify(None).unwrap_err();
+        assert_eq!(err.to_string(), "cannot register the policy asset");
+
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let mut ctx = VerifyContext::default();
+        ctx.reserved_assets.insert(asset.asset_id);
+        let err = asset.verify_with(None, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "cannot register a reserved asset");
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_steps() -> Result<()> {
+        crate::entity::tests::spawn_mock_verifier_server();
+        let ctx = VerifyContext::default();
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+
+        let steps = asset.verify_steps(None, &ctx);
+        let names: Vec<_> = steps.iter().map(|(step, _)| *step).collect();
+        assert_eq!(
+            names,
+            vec![
+                "fields",
+                "commitment",
+                "contract fields",
+                "entity link",
+                "icon",
+                "on-chain issuance",
+                "oracle"
+            ]
+        );
+        for (step, result) in steps {
+            match step {
+                "icon" | "on-chain issuance" | "oracle" => assert!(result.is_none()),
+                _ => assert!(result.req()?.is_ok(), "{} failed", step),
+            }
+        }
+
+        // the following steps are run despite the failure
+        asset.fields.name = "Other coin".to_string();
+        let failed: Vec<_> = asset
+            .verify_steps(None, &ctx)
+            .into_iter()
+            .filter(|(_, result)| result.as_ref().map_or(false, Result::is_err))
+            .map(|(step, _)| step)
+            .collect();
+        assert_eq!(failed, vec!["contract fields"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_all() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let ctx = VerifyContext::default();
+        assert!(asset.verify_all(None, &ctx).is_empty());
+
+        asset.fields.ticker = Some("P!P".to_string());
+        asset.fields.precision = 9;
+        asset.fields.entity = AssetEntity::DomainName("not a domain".to_string());
+
+        // verify() stops at the first failure
+        let err = asset.verify_with(None, &ctx).unwrap_err();
+        assert_eq!(
+            crate::errors::asset_error(&err),
+            Some(&AssetError::InvalidTicker)
+        );
+
+        let failures = asset.verify_all(None, &ctx);
+        let names: Vec<_> = failures.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            vec!["ticker", "precision", "entity", "contract fields"]
+        );
+        assert_eq!(failures[1].1.to_string(), "`precision` out of range");
+        Ok(())
+    }
+
+    #[test]
+    fn test_reserved_symbols() -> Result<()> {
+        // the b1405e asset is "PPP coin" with the PPP ticker
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let path = std::env::temp_dir().join(format!("reserved-symbols-{}", std::process::id()));
+        fs::write(&path, "# impersonation targets\nBTC\n\n  ppp  \n")?;
+
+        let mut ctx = VerifyContext::default();
+        ctx.reserved_symbols = load_reserved_symbols(&path)?;
+        assert_eq!(ctx.reserved_symbols.len(), 2);
+        let err = asset.verify_with(None, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "ticker `PPP` is reserved");
+
+        ctx.reserved_symbols = vec!["ppp coin".to_string()].into_iter().collect();
+        let err = asset.verify_with(None, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "name `PPP coin` is reserved");
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_domain_allowlist() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let path = std::env::temp_dir().join(format!("domain-allowlist-{}", std::process::id()));
+        fs::write(
+            &path,
+            "# partners
+partner.com
+
+  *.Test.dev  
+*.bücher.de
+",
+        )?;
+
+        let allowlist = load_domain_allowlist(&path)?;
+        assert!(domain_allowed(&allowlist, "partner.com"));
+        assert!(domain_allowed(&allowlist, "a.test.dev"));
+        assert!(domain_allowed(&allowlist, "a.b.test.dev"));
+        assert!(domain_allowed(&allowlist, "shop.xn--bcher-kva.de"));
+        assert!(domain_allowed(&allowlist, "shop.bücher.de"));
+        assert!(!domain_allowed(&allowlist, "sub.partner.com"));
+        assert!(!domain_allowed(&allowlist, "test.dev"));
+        assert!(!domain_allowed(&allowlist, "nottest.dev"));
+
+        // rejected before fetching the proof, the verifier mock is not running
+        let mut ctx = VerifyContext::default();
+        ctx.domain_allowlist = Some(allowlist);
+        let err = asset.verify_with(None, &ctx).unwrap_err();
+        assert_eq!(asset_error(&err), Some(&AssetError::DomainNotPermitted));
+
+        asset.fields.entity = AssetEntity::DomainName("coins.test.dev".to_string());
+        asset.verify_policy(&ctx)?;
+        asset.fields.entity = AssetEntity::DomainName("partner.com".to_string());
+        asset.verify_policy(&ctx)?;
+
+        // no allowlist allows everything
+        ctx.domain_allowlist = None;
+        asset.fields.entity = AssetEntity::DomainName("test.dev".to_string());
+        asset.verify_policy(&ctx)?;
+
+        fs::write(&path, "not a domain\n")?;
+        assert!(load_domain_allowlist(&path).is_err());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_issuance_block() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+        crate::entity::tests::spawn_mock_verifier_server();
+        let chain = ChainQuery::new("http://localhost:58713".to_string());
+        let ctx = VerifyContext::default();
+
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        assert!(asset.verify_capturing(None, &ctx)?.issuance_block.is_none());
+        let block = asset
+            .verify_capturing(Some(&chain), &ctx)?
+            .issuance_block
+            .req()?;
+        assert_eq!(block.block_height, 999);
+
+        // not serialized unless recorded
+        assert!(serde_json::to_value(&asset)?
+            .get("issuance_block")
+            .is_none());
+        asset = asset.with_issuance_block(Some(block.clone()));
+        let json = serde_json::to_value(&asset)?;
+        assert_eq!(json["issuance_block"]["block_height"], 999);
+        let asset: Asset = serde_json::from_value(json)?;
+        asset.verify_with(Some(&chain), &ctx)?;
+
+        let mut reorged = asset.clone();
+        reorged.issuance_block.as_mut().req()?.block_hash = BlockHash::from_hex(&"00".repeat(32))?;
+        let err = reorged.verify_with(Some(&chain), &ctx).unwrap_err();
+        assert_eq!(
+            crate::errors::asset_error(&err),
+            Some(&AssetError::IssuanceFailed)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reissuance_token() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let entropy =
+            AssetId::generate_asset_entropy(asset.issuance_prevout, asset.contract_hash()?);
+        for &confidential in &[false, true] {
+            asset.asset_id = AssetId::reissuance_token_from_entropy(entropy, confidential);
+            let err = verify_asset_commitment(&asset).unwrap_err();
+            assert_eq!(err.to_string(), "reissuance tokens cannot be registered");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_desynced_issuance_txin() -> Result<()> {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e-desynced.json"))?;
+        let err = verify_asset_commitment(&asset).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`issuance_txin` cannot spend `issuance_prevout` from the same transaction"
+        );
+        assert!(matches!(
+            crate::errors::asset_error(&err),
+            Some(AssetError::CommitmentMismatch(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_issuance_txin_spends_other_prevout() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+        let chain = ChainQuery::new("http://localhost:58713".to_string());
+
+        // the original issuance of another asset, spending a different prevout
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        asset.issuance_txin.txid =
+            Txid::from_hex("cda700f92ff722a82b8cdd4f8489699e893e83634228ac3cc94458c2e4fddc8e")?;
+        verify_asset_commitment(&asset)?;
+
+        let err = asset
+            .verify_with(Some(&chain), &VerifyContext::default())
+            .unwrap_err();
+        assert_eq!(asset_error(&err), Some(&AssetError::IssuanceFailed));
+        assert!(join_err(&err).contains(&format!(
+            "issuance prevout mismatch, `issuance_txin` spends {}:0 rather than {}:2",
+            "669d13368ed318eec89033c64ec73354b436a9b8ecbf5f07e3dc01780a482223",
+            "c1854811ffe022a023e42769a703d434a40cb3dc16407e1a47aa6279d6cd48b4"
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn test_contract_raw() -> Result<()> {
+        let json = fs::read_to_string("test/asset-b1405e.json")?;
+        let asset = Asset::from_json(&json)?;
+        assert!(asset.contract_raw.is_some());
+        assert_eq!(asset.contract_hash()?, contract_json_hash(&asset.contract)?);
+
+        // the raw contract is retained through re-serialization
+        let reloaded: Asset = serde_json::from_str(&serde_json::to_string(&asset)?)?;
+        assert_eq!(reloaded.contract_raw, asset.contract_raw);
+
+        // non-canonical bytes hash differently
+        let spaced = json.replace(
+            r#""name":"PPP coin","ticker""#,
+            r#""name": "PPP coin","ticker""#,
+        );
+        let spaced_asset = Asset::from_json(&spaced)?;
+        assert_ne!(spaced_asset.contract_hash()?, asset.contract_hash()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_contract_max_size() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let ctx = VerifyContext::default();
+        verify_contract_size(&asset.contract, &ctx)?;
+
+        // checked before anything else
+        let strict = VerifyContext {
+            contract_max_size: 100,
+            ..Default::default()
+        };
+        let err = asset.verify_with(None, &strict).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "contract too large (162 bytes, up to 100 allowed)"
+        );
+
+        asset.contract["description"] = json!("x".repeat(10_000));
+        let err = verify_contract_size(&asset.contract, &ctx).unwrap_err();
+        assert!(matches!(
+            crate::errors::asset_error(&err),
+            Some(AssetError::InvalidField(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_contract_hash_hex() -> Result<()> {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let canonical = r#"{"entity":{"domain":"test.dev"},"issuer_pubkey":"037c7db0528e8b7b58e698ac104764f6852d74b5a7335bffcdad0ce799dd7742ec","name":"PPP coin","ticker":"PPP","version":0}"#;
+        let expected = "ac5a08996e50a12b38e2ad9e5e3ff2899db889b08422361d9fbed65d7b9c209b";
+
+        assert_eq!(serde_json::to_string(&asset.contract)?, canonical);
+        assert_eq!(canonical_contract_json(&asset.contract)?, canonical);
+        assert_eq!(contract_hash_hex(&asset.contract)?, expected);
+        assert_eq!(canonical_contract_hash_hex(canonical), expected);
+        assert_eq!(asset.contract_hash()?.to_hex(), expected);
+
+        // round-trips through ContractHash, which is what the asset id commits to
+        let contract_hash = ContractHash::from_hex(expected)?;
+        assert_eq!(contract_hash, contract_json_hash(&asset.contract)?);
+        assert_eq!(
+            AssetId::from_entropy(AssetId::generate_asset_entropy(
+                asset.issuance_prevout,
+                contract_hash
+            )),
+            asset.asset_id
+        );
+
+        // key order in the input does not matter, but whitespace in the canonical form does
+        let reordered: Value = serde_json::from_str(
+            r#"{"version":0,"ticker":"PPP","name":"PPP coin","issuer_pubkey":"037c7db0528e8b7b58e698ac104764f6852d74b5a7335bffcdad0ce799dd7742ec","entity":{"domain":"test.dev"}}"#,
+        )?;
+        assert_eq!(contract_hash_hex(&reordered)?, expected);
+        assert_ne!(
+            canonical_contract_hash_hex(&serde_json::to_string_pretty(&asset.contract)?),
+            expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_issuer_address() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        asset.fields.issuer_pubkey =
+            Vec::from_hex("031b84c5567b126440995d3ed5aaba0565d71e1834604819ff9c17f5e9d5dd078f")?;
+
+        let address = |network, kind| asset.issuer_address(network, kind);
+        assert_eq!(
+            address(Network::Liquid, AddressType::P2pkh)?,
+            "Q8MojEvSYmhrEkaGcVm5XjMWCGXULV47BU"
+        );
+        assert_eq!(
+            address(Network::Liquid, AddressType::P2wpkh)?,
+            "ex1q0xcqpzrky6eff2g52qdye53xkk9jxkvrane0fw"
+        );
+        assert_eq!(
+            address(Network::LiquidTestnet, AddressType::P2pkh)?,
+            "FgG93xfPdyyT4eeT6gmPM6dyyg7fT3DeNC"
+        );
+        assert_eq!(
+            address(Network::LiquidTestnet, AddressType::P2wpkh)?,
+            "tex1q0xcqpzrky6eff2g52qdye53xkk9jxkvr84tx49"
+        );
+        assert_eq!(
+            address(Network::ElementsRegtest, AddressType::P2pkh)?,
+            "2dkXAzZyivtBoouPgyB3or3oYE4PNuyvZTy"
+        );
+        assert_eq!(
+            address(Network::ElementsRegtest, AddressType::P2wpkh)?,
+            "ert1q0xcqpzrky6eff2g52qdye53xkk9jxkvr8pnhk5"
+        );
+
+        // even y keys
+        asset.fields.issuer_pubkey =
+            Vec::from_hex("024d4b6cd1361032ca9bd2aeb9d900aa4d45d9ead80ac9423374c451a7254d0766")?;
+        assert_eq!(
+            asset.issuer_address(Network::Liquid, AddressType::P2pkh)?,
+            "QJkw3GjCKRiMrkronUHaQSm9aM6UQmRkXg"
+        );
+        assert_eq!(
+            asset.issuer_address(Network::Liquid, AddressType::P2wpkh)?,
+            "ex1qa0qwuze2h85zw7nqpsj3ga0z9geyrgwptvypnr"
+        );
+
+        // not on the curve, or not compressed
+        asset.fields.issuer_pubkey = [&[0x02][..], &[0xff; 32][..]].concat();
+        assert!(asset
+            .issuer_address(Network::Liquid, AddressType::P2pkh)
+            .is_err());
+        asset.fields.issuer_pubkey = [&[0x04][..], &[0x01; 64][..]].concat();
+        assert!(asset
+            .issuer_address(Network::Liquid, AddressType::P2wpkh)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_asset_id() -> Result<()> {
+        let contract: Value = serde_json::from_str(
+            r#"{"entity":{"domain":"test.dev"},"issuer_pubkey":"037c7db0528e8b7b58e698ac104764f6852d74b5a7335bffcdad0ce799dd7742ec","name":"PPP coin","ticker":"PPP","version":0}"#,
+        )?;
+        let prevout = OutPoint::new(
+            "c1854811ffe022a023e42769a703d434a40cb3dc16407e1a47aa6279d6cd48b4".parse()?,
+            2,
+        );
+        assert_eq!(
+            compute_asset_id(prevout, &contract)?.to_hex(),
+            "b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05"
+        );
+
+        // matches what verify_asset_commitment() checks registered assets against
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        assert_eq!(
+            compute_asset_id(asset.issuance_prevout, &asset.contract)?,
+            asset.asset_id
+        );
+
+        // any change to the prevout or the contract results in a different asset id
+        let other_prevout = OutPoint::new(prevout.txid, 1);
+        assert_ne!(compute_asset_id(other_prevout, &contract)?, asset.asset_id);
+        let mut other_contract = contract.clone();
+        other_contract["ticker"] = "PPQ".into();
+        assert_ne!(compute_asset_id(prevout, &other_contract)?, asset.asset_id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_contract_json() -> Result<()> {
+        // nested keys are sorted at every level (by their utf-8 bytes) and array order is kept
+        let contract: Value = serde_json::from_str(
+            r#"{ "z": { "b": [ { "d": 1, "c": 0 } ], "a": 100 }, "é": 1,
+                 "a": "x\ny\u0001é", "m": [3, 2, 1], "c": true, "b": null }"#,
+        )?;
+        let canonical = r#"{"a":"x\ny\u0001é","b":null,"c":true,"m":[3,2,1],"z":{"a":100,"b":[{"c":0,"d":1}]},"é":1}"#;
+        assert_eq!(canonical_contract_json(&contract)?, canonical);
+        assert_eq!(
+            contract_hash_hex(&contract)?,
+            "f64d6aabf7a3d8fce523de1133fca544dfde2f1e35d0a0bb671c565d72f15c3a"
+        );
+        assert_eq!(
+            canonical_contract_hash_hex(canonical),
+            contract_hash_hex(&contract)?
+        );
+
+        // the canonical form is stable when re-parsed
+        let reparsed: Value = serde_json::from_str(canonical)?;
+        assert_eq!(canonical_contract_json(&reparsed)?, canonical);
+
+        // floats are rejected rather than rewritten, even when integral
+        for json in &[
+            r#"{"a":1.5}"#,
+            r#"{"a":[1e300]}"#,
+            r#"{"a":{"b":-0.1}}"#,
+            r#"{"a":1.0}"#,
+            r#"{"a":1e2}"#,
+            r#"{"a":-0}"#,
+        ] {
+            let contract: Value = serde_json::from_str(json)?;
+            assert!(canonical_contract_json(&contract).is_err(), "{}", json);
+            assert!(contract_json_hash(&contract).is_err(), "{}", json);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_contract_roundtrip() -> Result<()> {
+        // existing contracts serialize to the exact bytes they were issued with
+        for path in &["test/asset-b1405e.json", "test/asset-b1405e-desynced.json"] {
+            let asset = Asset::load(PathBuf::from(path))?;
+            assert_eq!(
+                canonical_contract_json(&asset.contract)?,
+                serde_json::to_string(&asset.contract)?,
+                "{}",
+                path
+            );
+        }
+        Asset::load(PathBuf::from("test/asset-b1405e.json"))?.verify_commitment()?;
+
+        // including contracts with integer fields of any size
+        let contract: Value = serde_json::from_str(
+            r#"{"precision":8,"version":0,"x":-9223372036854775808,"y":18446744073709551615}"#,
+        )?;
+        assert_eq!(
+            canonical_contract_json(&contract)?,
+            serde_json::to_string(&contract)?
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_contract_from_cbor() -> Result<()> {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let cbor = serde_cbor::to_vec(&asset.contract)?;
+
+        let contract = contract_from_cbor(&cbor)?;
+        assert_eq!(contract, asset.contract);
+        assert_eq!(contract_json_hash(&contract)?, asset.contract_hash()?);
+
+        assert!(contract_from_cbor(&serde_cbor::to_vec(&"foo")?).is_err());
+        Ok(())
+    }
+
     /*
     #[test]
     fn test2_verify_asset_sig() -> Result<()> {
@@ -323,4 +2249,181 @@ mod tests {
         )?;
         Ok(())
     }*/
+
+    #[test]
+    fn test_multisig_issuer() -> Result<()> {
+        use bitcoin::util::misc::signed_msg_hash;
+
+        let ec = Secp256k1::new();
+        let keys: Vec<secp256k1::SecretKey> = (1..=4u8)
+            .map(|i| secp256k1::SecretKey::from_slice(&[i; 32]).unwrap())
+            .collect();
+        let pubkeys: Vec<Vec<u8>> = keys
+            .iter()
+            .map(|key| {
+                secp256k1::PublicKey::from_secret_key(&ec, key)
+                    .serialize()
+                    .to_vec()
+            })
+            .collect();
+        let msg = "remove asset from registry";
+        let sign = |key: &secp256k1::SecretKey| {
+            let msg_hash = signed_msg_hash(msg);
+            let msg_secp = secp256k1::Message::from_slice(&msg_hash.into_inner()).unwrap();
+            ec.sign(&msg_secp, key).serialize_compact().to_vec()
+        };
+
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let mut fields = asset.fields.clone();
+        fields.issuer_pubkey = pubkeys[0].clone();
+        fields.issuer_pubkeys = Some(pubkeys[0..3].to_vec());
+        fields.issuer_threshold = Some(2);
+        fields.validate()?;
+
+        // 2-of-3, each key is only counted once
+        assert!(verify_issuer_sigs(&fields, &[sign(&keys[0])], msg).is_err());
+        assert!(verify_issuer_sigs(&fields, &[sign(&keys[0]), sign(&keys[0])], msg).is_err());
+        assert!(verify_issuer_sigs(&fields, &[sign(&keys[0]), sign(&keys[3])], msg).is_err());
+        verify_issuer_sigs(&fields, &[sign(&keys[0]), sign(&keys[2])], msg)?;
+
+        fields.issuer_threshold = Some(4);
+        assert!(fields.validate().is_err());
+        fields.issuer_threshold = None;
+        assert!(fields.validate().is_err());
+        fields.issuer_threshold = Some(2);
+        fields.issuer_pubkey = pubkeys[3].clone();
+        assert!(fields.validate().is_err());
+
+        // BIP-322 signatures (p2wpkh and p2tr) by the first key count towards the threshold
+        let bip322_p2wpkh = base64::decode("AkgwRQIhANykFm9ENKbF45qwIbYLMeY+JrCGjm1gDTNNkVSg852vAiAJLRjl35WYY6xnsIpxW0tWN3iMb3KXcYBvcnsMTG6AMAEhAxuExVZ7EmRAmV0+1aq6BWXXHhg0YEgZ/5wX9enV3QeP")?;
+        let bip322_p2tr = base64::decode("AUAU9Sv+CPjR6KedDmslHjgwCJPGRRxacKg+ycj84FpzWT7hieR39EegY2BY7aMPBDbov4Vs8qTeqf1tjHGJYBj8")?;
+        fields.issuer_pubkey = pubkeys[0].clone();
+        verify_issuer_sigs(&fields, &[bip322_p2wpkh.clone(), sign(&keys[1])], msg)?;
+        verify_issuer_sigs(&fields, &[sign(&keys[2]), bip322_p2tr.clone()], msg)?;
+        assert!(
+            verify_issuer_sigs(&fields, &[bip322_p2wpkh.clone(), bip322_p2tr.clone()], msg)
+                .is_err()
+        );
+        assert!(
+            verify_issuer_sigs(&fields, &[bip322_p2wpkh.clone(), sign(&keys[0])], msg).is_err()
+        );
+
+        let mut single = fields.clone();
+        single.issuer_pubkeys = None;
+        single.issuer_threshold = None;
+        verify_issuer_sigs(&single, &[bip322_p2wpkh], msg)?;
+        verify_issuer_sigs(&single, &[bip322_p2tr.clone()], msg)?;
+        assert!(verify_issuer_sigs(&single, &[bip322_p2tr], "remove other from registry").is_err());
+
+        // single key issuers are unaffected
+        let fields = asset.fields;
+        assert_eq!(
+            fields.issuer_pubkeys(),
+            (vec![&fields.issuer_pubkey[..]], 1)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deletion_msg() -> Result<()> {
+        use bitcoin::util::misc::signed_msg_hash;
+
+        let ec = Secp256k1::new();
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let sign = |msg: &str| {
+            let msg_hash = signed_msg_hash(msg);
+            let msg_secp = secp256k1::Message::from_slice(&msg_hash.into_inner()).unwrap();
+            ec.sign(&msg_secp, &key).serialize_compact().to_vec()
+        };
+
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        asset.fields.issuer_pubkey = secp256k1::PublicKey::from_secret_key(&ec, &key)
+            .serialize()
+            .to_vec();
+
+        let msg = deletion_sig_msg(&asset.asset_id, "test-registry", 1600000000).to_string();
+        assert_eq!(
+            msg,
+            format!(
+                "liquid-asset-delete:1:test-registry:{}:1600000000",
+                asset.asset_id.to_hex()
+            )
+        );
+        let sig = sign(&msg);
+        let legacy_sig = sign(&legacy_deletion_sig_msg(&asset.asset_id));
+        let v0_sig = sign(&format!(
+            "liquid-asset-delete:0:test-registry:{}",
+            asset.asset_id.to_hex()
+        ));
+
+        let reg = Some("test-registry");
+        assert_eq!(
+            asset.verify_deletion(&sig, reg, Some(1600000000), false)?,
+            Some(1600000000)
+        );
+        assert_eq!(
+            asset.verify_deletion(&sig, reg, Some(1600000000), true)?,
+            Some(1600000000)
+        );
+        // the signature only covers the signed registry id and sequence number
+        assert!(asset
+            .verify_deletion(&sig, Some("other-registry"), Some(1600000000), true)
+            .is_err());
+        assert!(asset
+            .verify_deletion(&sig, reg, Some(1600000001), true)
+            .is_err());
+        assert!(asset.verify_deletion(&sig, reg, None, true).is_err());
+        // registries without an id only accept the legacy message
+        assert!(asset.verify_deletion(&sig, None, None, true).is_err());
+        assert!(asset
+            .verify_deletion(&legacy_sig, None, None, false)
+            .is_err());
+        asset.verify_deletion(&legacy_sig, None, None, true)?;
+
+        // the legacy and version 0 messages are only accepted in compat mode, and don't sign for
+        // the sequence number
+        assert_eq!(
+            asset.verify_deletion(&legacy_sig, reg, Some(1600000000), true)?,
+            None
+        );
+        assert_eq!(asset.verify_deletion(&v0_sig, reg, None, true)?, None);
+        assert!(asset.verify_deletion(&v0_sig, None, None, true).is_err());
+        let err = asset
+            .verify_deletion(&legacy_sig, reg, Some(1600000000), false)
+            .unwrap_err();
+        match asset_error(&err) {
+            Some(AssetError::InvalidSignature(_)) => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert!(asset.verify_deletion(&v0_sig, reg, None, false).is_err());
+
+        // update authorizations are not interchangeable with deletion ones
+        let update_msg = update_sig_msg(&asset.asset_id, "test-registry", 1600000000).to_string();
+        assert_eq!(
+            update_msg,
+            format!(
+                "liquid-asset-update:1:test-registry:{}:1600000000",
+                asset.asset_id.to_hex()
+            )
+        );
+        let update_sig = sign(&update_msg);
+        assert_eq!(
+            asset.verify_update_sigs(&[update_sig.clone()], "test-registry", 1600000000)?,
+            1600000000
+        );
+        assert!(asset
+            .verify_update_sigs(&[sig.clone()], "test-registry", 1600000000)
+            .is_err());
+        assert!(asset
+            .verify_deletion(&update_sig, reg, Some(1600000000), true)
+            .is_err());
+
+        validate_registry_id("liquid.example_registry-1")?;
+        assert!(validate_registry_id("").is_err());
+        assert!(validate_registry_id("test:registry").is_err());
+        assert!(validate_registry_id(&"a".repeat(65)).is_err());
+
+        Ok(())
+    }
 }
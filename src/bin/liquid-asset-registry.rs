@@ -7,15 +7,25 @@ extern crate base64;
 #[macro_use]
 extern crate failure;
 
+use std::path::PathBuf;
+use std::time::Duration;
+
 use reqwest::{blocking::Client, StatusCode};
 use serde_json::Value;
 use structopt::StructOpt;
 
-use bitcoin_hashes::hex::ToHex;
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use elements::AssetId;
 
-use asset_registry::asset::{contract_json_hash, Asset, AssetRequest};
-use asset_registry::chain::ChainQuery;
+use asset_registry::asset::{
+    canonical_contract_json, contract_json_hash, normalize_contract, Asset, AssetRequest,
+};
+use asset_registry::chain::{rpc_auth, ChainQuery, CHAIN_TIMEOUT};
+use asset_registry::context::VerifyContext;
+use asset_registry::entity::{format_domain_proof, proof_page_path};
 use asset_registry::errors::{join_err, Result, ResultExt};
+use asset_registry::network::Network;
+use asset_registry::util::verify_domain_name;
 
 #[derive(StructOpt, Debug)]
 struct Cli {
@@ -30,6 +40,53 @@ struct Cli {
     cmd: Command,
 }
 
+// Verify against an elementsd node's json-rpc instead of esplora, and the chain request timeout
+#[derive(StructOpt, Debug)]
+struct RpcOpts {
+    #[structopt(
+        long = "elementsd-rpc-url",
+        help = "url of an elementsd json-rpc to query chain state from instead of esplora (requires -txindex=1)"
+    )]
+    rpc_url: Option<String>,
+
+    #[structopt(
+        long = "elementsd-rpc-auth",
+        conflicts_with = "elementsd-rpc-cookie",
+        help = "elementsd rpc credentials, as <user>:<password>"
+    )]
+    rpc_auth: Option<String>,
+
+    #[structopt(
+        long = "elementsd-rpc-cookie",
+        parse(from_os_str),
+        help = "path to the elementsd rpc .cookie file"
+    )]
+    rpc_cookie: Option<PathBuf>,
+
+    #[structopt(
+        long = "chain-timeout",
+        help = "timeout for requests to esplora or the elementsd rpc (in seconds, defaults to 30)"
+    )]
+    chain_timeout: Option<u64>,
+}
+
+impl RpcOpts {
+    fn chain(&self, network: Network) -> Result<Option<ChainQuery>> {
+        let rpc_url = match &self.rpc_url {
+            Some(rpc_url) => rpc_url.clone(),
+            None => return Ok(None),
+        };
+        let auth = rpc_auth(self.rpc_auth.as_deref(), self.rpc_cookie.as_deref())?;
+        let chain = ChainQuery::elements_rpc(rpc_url, auth, self.timeout());
+        Ok(Some(chain.with_network(network)))
+    }
+
+    fn timeout(&self) -> Duration {
+        self.chain_timeout
+            .map_or(CHAIN_TIMEOUT, Duration::from_secs)
+    }
+}
+
 #[derive(StructOpt, Debug)]
 enum Command {
     #[structopt(name = "verify-asset", about = "Verify asset associations")]
@@ -42,9 +99,61 @@ enum Command {
         )]
         esplora_url: String,
 
+        #[structopt(
+            long,
+            default_value = "liquid",
+            help = "network name (liquid, liquidtestnet or elementsregtest)"
+        )]
+        network: Network,
+
+        #[structopt(flatten)]
+        rpc: RpcOpts,
+
         jsons: Vec<String>,
     },
 
+    #[structopt(
+        name = "verify",
+        about = "Verify an asset file, printing the result of each verification step"
+    )]
+    Verify {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        #[structopt(
+            long = "esplora-url",
+            alias = "chain-url",
+            help = "url for querying chain state using the esplora api (skips the on-chain verification if neither it nor --elementsd-rpc-url are provided)"
+        )]
+        esplora_url: Option<String>,
+
+        #[structopt(
+            long,
+            default_value = "liquid",
+            help = "network name (liquid, liquidtestnet or elementsregtest)"
+        )]
+        network: Network,
+
+        #[structopt(flatten)]
+        rpc: RpcOpts,
+    },
+
+    #[structopt(
+        name = "proof",
+        about = "print the domain proof page contents and where to host them"
+    )]
+    Proof {
+        #[structopt(long = "asset-id", parse(try_from_str = AssetId::from_hex))]
+        asset_id: AssetId,
+        #[structopt(long)]
+        domain: String,
+        #[structopt(
+            long = "proof-path-prefix",
+            default_value = "/.well-known/liquid-asset-proof-"
+        )]
+        proof_path_prefix: String,
+    },
+
     #[structopt(name = "register-asset", about = "Send asset to registry")]
     RegisterAsset {
         #[structopt(
@@ -66,6 +175,8 @@ enum Command {
         json: String,
         #[structopt(short, long, help = "print contract hash (sha256)")]
         hash: bool,
+        #[structopt(long, help = "the contract is provided as hex-encoded cbor")]
+        cbor: bool,
     },
 }
 
@@ -75,15 +186,29 @@ fn main() -> Result<()> {
     debug!("cli args: {:?}", args);
 
     match args.cmd {
-        Command::VerifyAsset { esplora_url, jsons } => {
-            let chain = Some(ChainQuery::new(esplora_url));
+        Command::VerifyAsset {
+            esplora_url,
+            network,
+            rpc,
+            jsons,
+        } => {
+            let chain = match rpc.chain(network)? {
+                Some(chain) => Some(chain),
+                None => {
+                    Some(ChainQuery::with_timeout(esplora_url, rpc.timeout()).with_network(network))
+                }
+            };
+            let ctx = VerifyContext {
+                network,
+                ..Default::default()
+            };
             let mut failed = false;
 
             for json in jsons {
                 let asset: Asset = serde_json::from_str(&json).context("invalid asset json")?;
                 debug!("verifying asset: {:?}", asset);
 
-                match asset.verify(chain.as_ref()) {
+                match asset.verify_with(chain.as_ref(), &ctx) {
                     Ok(()) => println!("{},true", asset.id().to_hex()),
                     Err(err) => {
                         warn!("asset verification failed: {}", join_err(&err));
@@ -98,6 +223,61 @@ fn main() -> Result<()> {
             }
         }
 
+        Command::Verify {
+            path,
+            esplora_url,
+            network,
+            rpc,
+        } => {
+            let asset = Asset::load(path).context("failed loading asset")?;
+            ensure!(
+                esplora_url.is_none() || rpc.rpc_url.is_none(),
+                "only one of --esplora-url and --elementsd-rpc-url may be given"
+            );
+            let chain = match rpc.chain(network)? {
+                Some(chain) => Some(chain),
+                None => esplora_url
+                    .map(|url| ChainQuery::with_timeout(url, rpc.timeout()).with_network(network)),
+            };
+            let ctx = VerifyContext {
+                network,
+                ..Default::default()
+            };
+
+            println!("asset {}", asset.id().to_hex());
+            let mut failed = false;
+            for (step, result) in asset.verify_steps(chain.as_ref(), &ctx) {
+                match result {
+                    Some(Ok(())) => println!("  {:<18} ok", step),
+                    Some(Err(err)) => {
+                        println!("  {:<18} FAILED: {}", step, join_err(&err));
+                        failed = true;
+                    }
+                    None => println!("  {:<18} skipped", step),
+                }
+            }
+
+            if failed {
+                std::process::exit(1);
+            }
+        }
+
+        Command::Proof {
+            asset_id,
+            domain,
+            proof_path_prefix,
+        } => {
+            verify_domain_name(&domain).context("invalid domain name")?;
+            let asset_id = asset_id.to_hex();
+
+            eprintln!(
+                "Host the following contents at https://{}{}",
+                domain,
+                proof_page_path(&proof_path_prefix, &asset_id)
+            );
+            println!("{}", format_domain_proof(&domain, &asset_id));
+        }
+
         Command::RegisterAsset {
             registry_url,
             asset_req,
@@ -117,15 +297,20 @@ fn main() -> Result<()> {
             info!("registered succesfully: {:#?}", asset);
         }
 
-        Command::ContractJson { json, hash } => {
-            let contract: Value = serde_json::from_str(&json).context("invalid contract json")?;
+        Command::ContractJson { json, hash, cbor } => {
+            let mut contract: Value = if cbor {
+                contract_from_cbor_hex(&json)?
+            } else {
+                serde_json::from_str(&json).context("invalid contract json")?
+            };
+            // the registry only accepts NFC-normalized names, normalize before committing to it
+            normalize_contract(&mut contract);
 
             if hash {
                 let hash = contract_json_hash(&contract)?;
                 println!("{}", hash.to_hex());
             } else {
-                // deserializing and re-serializing gets us canonical encoding, with json keys sorted lexicographically
-                let contract_str = serde_json::to_string(&contract)?;
+                let contract_str = canonical_contract_json(&contract)?;
                 println!("{}", contract_str);
             }
         }
@@ -133,3 +318,14 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(feature = "cbor")]
+fn contract_from_cbor_hex(cbor_hex: &str) -> Result<Value> {
+    let cbor = hex::decode(cbor_hex.trim()).context("invalid cbor hex")?;
+    asset_registry::asset::contract_from_cbor(&cbor)
+}
+
+#[cfg(not(feature = "cbor"))]
+fn contract_from_cbor_hex(_cbor_hex: &str) -> Result<Value> {
+    bail!("cbor support requires the `cbor` feature")
+}
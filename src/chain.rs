@@ -1,20 +1,90 @@
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
 use reqwest::{blocking::Client as ReqClient, StatusCode};
 use serde_json::Value;
 
 use bitcoin::{BlockHash, Txid};
 use bitcoin_hashes::{hex::ToHex, Hash};
-use elements::{encode::deserialize, issuance::ContractHash, AssetId, Transaction};
+use elements::{
+    confidential, encode::deserialize, issuance::ContractHash, AssetId, OutPoint, Transaction,
+};
 
 use crate::asset::Asset;
-use crate::errors::{OptionExt, Result, ResultExt};
+use crate::context::VerifyContext;
+use crate::errors::{AssetError, OptionExt, Result, ResultExt};
+use crate::network::Network;
+use crate::util::TxInput;
+
+// max number of assets to request in a single batch
+const ASSETS_BATCH_SIZE: usize = 100;
+
+// default connect and overall timeout for requests to the backend
+pub const CHAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// how long assets missing from the backend are remembered as such, to avoid re-querying them
+const MISSING_ASSET_TTL: Duration = Duration::from_secs(10);
+
+#[cfg(feature = "async")]
+pub type ChainFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+// A source of chain state. Lookups of missing transactions return None rather than failing, while
+// failures of the backend itself should be classified with backend_error(), so that callers can
+// tell transient failures apart from deterministic ones.
+pub trait ChainBackend: fmt::Debug + Send + Sync {
+    fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>>;
+
+    // The block the transaction is confirmed in, or None if it is unconfirmed or does not exist
+    fn get_tx_status(&self, txid: &Txid) -> Result<Option<BlockId>>;
+
+    // The number of outputs of the transaction, or None if it does not exist
+    fn get_tx_output_count(&self, txid: &Txid) -> Result<Option<usize>>;
+
+    fn get_tip_height(&self) -> Result<u32>;
+
+    // Whether assets can be looked up by their id with fetch_assets(). Backends without an asset
+    // index can only verify assets against their issuance transaction.
+    fn has_asset_index(&self) -> bool {
+        false
+    }
+
+    // Lookup assets in the asset index, replying with esplora's asset entries (or None for missing
+    // assets) in the same order
+    fn fetch_assets(&self, _asset_ids: &[AssetId]) -> Result<Vec<Option<Value>>> {
+        bail!(
+            "asset lookups are not supported by the {} backend",
+            self.name()
+        )
+    }
+
+    // The backend name, for error messages
+    fn name(&self) -> &'static str;
+
+    #[cfg(feature = "async")]
+    fn get_tx_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<Transaction>>;
+
+    #[cfg(feature = "async")]
+    fn get_tx_status_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<BlockId>>;
+
+    #[cfg(feature = "async")]
+    fn get_tx_output_count_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<usize>>;
+}
 
 #[derive(Debug)]
 pub struct ChainQuery {
-    api_url: String,
-    rclient: ReqClient,
+    backend: Box<dyn ChainBackend>,
+    network: Network,
+    missing_assets: Mutex<HashMap<AssetId, Instant>>,
+    missing_asset_ttl: Duration,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BlockId {
     pub block_height: usize,
     pub block_hash: BlockHash,
@@ -23,25 +93,231 @@ pub struct BlockId {
 
 impl ChainQuery {
     pub fn new(api_url: String) -> Self {
+        Self::with_timeout(api_url, CHAIN_TIMEOUT)
+    }
+
+    // Requests to the backend fail with AssetError::ChainTimeout if it doesn't connect or respond
+    // within the timeout, so that a slow backend fails verification rather than stalling it
+    pub fn with_timeout(api_url: String, timeout: Duration) -> Self {
+        Self::with_backend(Esplora::new(api_url, timeout))
+    }
+
+    // Query an elementsd node's json-rpc instead of esplora (see ElementsRpc)
+    pub fn elements_rpc(
+        rpc_url: String,
+        auth: Option<(String, String)>,
+        timeout: Duration,
+    ) -> Self {
+        Self::with_backend(ElementsRpc::new(rpc_url, auth, timeout))
+    }
+
+    pub fn with_backend<B: ChainBackend + 'static>(backend: B) -> Self {
         ChainQuery {
-            api_url: api_url.trim_end_matches('/').into(),
-            rclient: ReqClient::new(),
+            backend: Box::new(backend),
+            network: Network::default(),
+            missing_assets: Mutex::new(HashMap::new()),
+            missing_asset_ttl: MISSING_ASSET_TTL,
         }
     }
 
+    // The network served by the backend, used to tell which network a lookup targeted.
+    // Should match the VerifyContext network.
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    // Whether assets can be looked up by their id (see get_asset())
+    pub fn has_asset_index(&self) -> bool {
+        self.backend.has_asset_index()
+    }
+
+    // How long assets that the backend reported as missing are answered as such without querying
+    // it again. Newly issued assets may take as long to be found once indexed. Zero disables it.
+    pub fn with_missing_asset_ttl(mut self, ttl: Duration) -> Self {
+        self.missing_asset_ttl = ttl;
+        self
+    }
+
     pub fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>> {
+        self.backend.get_tx(txid)
+    }
+
+    pub fn get_tx_status(&self, txid: &Txid) -> Result<Option<BlockId>> {
+        self.backend.get_tx_status(txid)
+    }
+
+    pub fn get_tip_height(&self) -> Result<u32> {
+        self.backend.get_tip_height()
+    }
+
+    // Get the number of outputs of the transaction, or None if it does not exist
+    pub fn get_tx_output_count(&self, txid: &Txid) -> Result<Option<usize>> {
+        self.backend.get_tx_output_count(txid)
+    }
+
+    pub fn get_asset(&self, asset_id: &AssetId) -> Result<Option<Value>> {
+        self.get_assets(&[*asset_id])?.pop().req()
+    }
+
+    // Lookup multiple assets, in batches if supported by the backend or one-by-one otherwise.
+    // Assets recently found to be missing are not queried again (see with_missing_asset_ttl()).
+    pub fn get_assets(&self, asset_ids: &[AssetId]) -> Result<Vec<Option<Value>>> {
+        let known_missing: Vec<bool> = {
+            let mut missing_assets = self.missing_assets.lock().unwrap();
+            let ttl = self.missing_asset_ttl;
+            missing_assets.retain(|_, since| since.elapsed() < ttl);
+            asset_ids
+                .iter()
+                .map(|id| missing_assets.contains_key(id))
+                .collect()
+        };
+        let lookup: Vec<AssetId> = asset_ids
+            .iter()
+            .zip(&known_missing)
+            .filter(|(_, missing)| !**missing)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut fetched = self.backend.fetch_assets(&lookup)?.into_iter();
+        if self.missing_asset_ttl > Duration::from_secs(0) {
+            let mut missing_assets = self.missing_assets.lock().unwrap();
+            for (id, asset) in lookup.iter().zip(fetched.as_slice()) {
+                if asset.is_none() {
+                    missing_assets.insert(*id, Instant::now());
+                }
+            }
+        }
+
+        Ok(known_missing
+            .into_iter()
+            .map(|missing| {
+                if missing {
+                    None
+                } else {
+                    fetched.next().unwrap()
+                }
+            })
+            .collect())
+    }
+
+    // Find the asset's issuance input and the prevout it spends. Looked up by the asset id when
+    // the backend has an asset index, or found among the inputs of the given issuance transaction
+    // otherwise. Returns None if the asset (or the transaction) does not exist.
+    pub fn get_issuance(
+        &self,
+        asset_id: &AssetId,
+        issuance_txid: Option<&Txid>,
+    ) -> Result<Option<(TxInput, OutPoint)>> {
+        if self.has_asset_index() {
+            let mut asset_data = match self.get_asset(asset_id)? {
+                Some(asset_data) => asset_data,
+                None => return Ok(None),
+            };
+            let issuance_txin = serde_json::from_value(asset_data["issuance_txin"].take())?;
+            let issuance_prevout = serde_json::from_value(asset_data["issuance_prevout"].take())?;
+            return Ok(Some((issuance_txin, issuance_prevout)));
+        }
+
+        let txid = issuance_txid.or_err(format!(
+            "the issuance txid is required with the {} backend, which has no asset index",
+            self.backend.name()
+        ))?;
+        Ok(self
+            .get_tx(txid)?
+            .and_then(|tx| find_issuance_input(&tx, asset_id)))
+    }
+}
+
+#[cfg(feature = "async")]
+impl ChainQuery {
+    pub async fn get_tx_async(&self, txid: &Txid) -> Result<Option<Transaction>> {
+        self.backend.get_tx_async(txid).await
+    }
+
+    pub async fn get_tx_output_count_async(&self, txid: &Txid) -> Result<Option<usize>> {
+        self.backend.get_tx_output_count_async(txid).await
+    }
+
+    pub async fn get_tx_status_async(&self, txid: &Txid) -> Result<Option<BlockId>> {
+        self.backend.get_tx_status_async(txid).await
+    }
+}
+
+// The esplora api, the default backend
+#[derive(Debug)]
+pub struct Esplora {
+    api_url: String,
+    rclient: ReqClient,
+    #[cfg(feature = "async")]
+    async_rclient: reqwest::Client,
+    batch_assets: bool,
+}
+
+impl Esplora {
+    pub fn new(api_url: String, timeout: Duration) -> Self {
+        Esplora {
+            api_url: api_url.trim_end_matches('/').into(),
+            rclient: http_client(timeout),
+            #[cfg(feature = "async")]
+            async_rclient: async_http_client(timeout),
+            batch_assets: false,
+        }
+    }
+
+    // Enable batched asset lookups, for backends that support `POST /assets` with a json array
+    // of asset ids, replying with an array of asset entries (or nulls) in the same order.
+    pub fn with_batch_assets(mut self, enabled: bool) -> Self {
+        self.batch_assets = enabled;
+        self
+    }
+
+    fn fetch_asset(&self, asset_id: &AssetId) -> Result<Option<Value>> {
+        let resp = self
+            .rclient
+            .get(&format!("{}/asset/{}", self.api_url, asset_id.to_hex()))
+            .send()
+            .map_err(|err| backend_error(err, "asset", self.name()))?;
+
+        Ok(if resp.status() == StatusCode::NOT_FOUND {
+            None
+        } else {
+            Some(
+                resp.error_for_status()
+                    .map_err(|err| backend_error(err, "asset", self.name()))?
+                    .json()
+                    .context("failed reading asset")?,
+            )
+        })
+    }
+}
+
+impl ChainBackend for Esplora {
+    fn name(&self) -> &'static str {
+        "esplora"
+    }
+
+    fn has_asset_index(&self) -> bool {
+        true
+    }
+
+    fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>> {
         let resp = self
             .rclient
             .get(&format!("{}/tx/{}/hex", self.api_url, txid.to_hex()))
             .send()
-            .context("failed fetching tx")?;
+            .map_err(|err| backend_error(err, "tx", self.name()))?;
 
         Ok(if resp.status() == StatusCode::NOT_FOUND {
             None
         } else {
             let hex = resp
                 .error_for_status()
-                .context("failed fetching tx")?
+                .map_err(|err| backend_error(err, "tx", self.name()))?
                 .text()
                 .context("failed reading tx")?;
 
@@ -49,14 +325,14 @@ impl ChainQuery {
         })
     }
 
-    pub fn get_tx_status(&self, txid: &Txid) -> Result<Option<BlockId>> {
+    fn get_tx_status(&self, txid: &Txid) -> Result<Option<BlockId>> {
         let status: Value = self
             .rclient
             .get(&format!("{}/tx/{}/status", self.api_url, txid.to_hex()))
             .send()
-            .context("failed fetching tx status")?
+            .map_err(|err| backend_error(err, "tx status", self.name()))?
             .error_for_status()
-            .context("failed fetching tx status")?
+            .map_err(|err| backend_error(err, "tx status", self.name()))?
             .json()?;
 
         Ok(if status["confirmed"].as_bool().unwrap_or(false) {
@@ -66,46 +342,558 @@ impl ChainQuery {
         })
     }
 
-    pub fn get_asset(&self, asset_id: &AssetId) -> Result<Option<Value>> {
+    fn get_tip_height(&self) -> Result<u32> {
+        Ok(self
+            .rclient
+            .get(&format!("{}/blocks/tip/height", self.api_url))
+            .send()
+            .map_err(|err| backend_error(err, "tip height", self.name()))?
+            .error_for_status()
+            .map_err(|err| backend_error(err, "tip height", self.name()))?
+            .text()
+            .context("failed reading tip height")?
+            .trim()
+            .parse()?)
+    }
+
+    fn get_tx_output_count(&self, txid: &Txid) -> Result<Option<usize>> {
         let resp = self
             .rclient
-            .get(&format!("{}/asset/{}", self.api_url, asset_id.to_hex()))
+            .get(&format!("{}/tx/{}", self.api_url, txid.to_hex()))
             .send()
-            .context("failed fetching tx")?;
+            .map_err(|err| backend_error(err, "tx", self.name()))?;
 
         Ok(if resp.status() == StatusCode::NOT_FOUND {
             None
         } else {
-            Some(
-                resp.error_for_status()
-                    .context("failed fetching asset")?
+            let tx: Value = resp
+                .error_for_status()
+                .map_err(|err| backend_error(err, "tx", self.name()))?
+                .json()
+                .context("failed reading tx")?;
+            Some(tx_output_count(&tx)?)
+        })
+    }
+
+    fn fetch_assets(&self, asset_ids: &[AssetId]) -> Result<Vec<Option<Value>>> {
+        if !self.batch_assets {
+            return asset_ids.iter().map(|id| self.fetch_asset(id)).collect();
+        }
+
+        let mut assets = Vec::with_capacity(asset_ids.len());
+        for chunk in asset_ids.chunks(ASSETS_BATCH_SIZE) {
+            let batch: Vec<Option<Value>> = self
+                .rclient
+                .post(&format!("{}/assets", self.api_url))
+                .json(&chunk.iter().map(|id| id.to_hex()).collect::<Vec<_>>())
+                .send()
+                .map_err(|err| backend_error(err, "assets", self.name()))?
+                .error_for_status()
+                .map_err(|err| backend_error(err, "assets", self.name()))?
+                .json()
+                .context("failed reading assets")?;
+            ensure!(batch.len() == chunk.len(), "unexpected number of assets");
+            assets.extend(batch);
+        }
+        Ok(assets)
+    }
+
+    #[cfg(feature = "async")]
+    fn get_tx_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<Transaction>> {
+        Box::pin(async move {
+            let resp = self
+                .async_rclient
+                .get(&format!("{}/tx/{}/hex", self.api_url, txid.to_hex()))
+                .send()
+                .await
+                .map_err(|err| backend_error(err, "tx", self.name()))?;
+
+            Ok(if resp.status() == StatusCode::NOT_FOUND {
+                None
+            } else {
+                let hex = resp
+                    .error_for_status()
+                    .map_err(|err| backend_error(err, "tx", self.name()))?
+                    .text()
+                    .await
+                    .context("failed reading tx")?;
+
+                Some(deserialize(&hex::decode(hex.trim())?)?)
+            })
+        })
+    }
+
+    #[cfg(feature = "async")]
+    fn get_tx_output_count_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<usize>> {
+        Box::pin(async move {
+            let resp = self
+                .async_rclient
+                .get(&format!("{}/tx/{}", self.api_url, txid.to_hex()))
+                .send()
+                .await
+                .map_err(|err| backend_error(err, "tx", self.name()))?;
+
+            Ok(if resp.status() == StatusCode::NOT_FOUND {
+                None
+            } else {
+                let tx: Value = resp
+                    .error_for_status()
+                    .map_err(|err| backend_error(err, "tx", self.name()))?
                     .json()
-                    .context("failed reading asset")?,
-            )
+                    .await
+                    .context("failed reading tx")?;
+                Some(tx_output_count(&tx)?)
+            })
+        })
+    }
+
+    #[cfg(feature = "async")]
+    fn get_tx_status_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<BlockId>> {
+        Box::pin(async move {
+            let status: Value = self
+                .async_rclient
+                .get(&format!("{}/tx/{}/status", self.api_url, txid.to_hex()))
+                .send()
+                .await
+                .map_err(|err| backend_error(err, "tx status", self.name()))?
+                .error_for_status()
+                .map_err(|err| backend_error(err, "tx status", self.name()))?
+                .json()
+                .await?;
+
+            Ok(if status["confirmed"].as_bool().unwrap_or(false) {
+                Some(serde_json::from_value(status)?)
+            } else {
+                None
+            })
+        })
+    }
+}
+
+// An elementsd node's json-rpc, authenticating with the rpc user and password (see
+// read_rpc_cookie() for cookie authentication). The node must run with `-txindex=1` for issuance
+// transactions to be found, and the rpc user must be allowed the `getrawtransaction`,
+// `getblockheader` and `getblockcount` methods. Has no asset index, so assets can only be
+// verified against their issuance transaction.
+#[derive(Debug)]
+pub struct ElementsRpc {
+    rpc_url: String,
+    auth: Option<(String, String)>,
+    rclient: ReqClient,
+    #[cfg(feature = "async")]
+    async_rclient: reqwest::Client,
+}
+
+impl ElementsRpc {
+    pub fn new(rpc_url: String, auth: Option<(String, String)>, timeout: Duration) -> Self {
+        ElementsRpc {
+            rpc_url: rpc_url.trim_end_matches('/').into(),
+            auth,
+            rclient: http_client(timeout),
+            #[cfg(feature = "async")]
+            async_rclient: async_http_client(timeout),
+        }
+    }
+
+    // Call an elementsd rpc method, returning None for missing transactions and blocks
+    fn call(&self, method: &str, params: Value) -> Result<Option<Value>> {
+        let mut req = self
+            .rclient
+            .post(&self.rpc_url)
+            .json(&rpc_request(method, params));
+        if let Some((user, password)) = &self.auth {
+            req = req.basic_auth(user, Some(password));
+        }
+        let resp = req
+            .send()
+            .map_err(|err| backend_error(err, method, self.name()))?;
+        let status = resp.status();
+        let body = resp
+            .text()
+            .map_err(|err| backend_error(err, method, self.name()))?;
+        rpc_reply(method, status, &body)
+    }
+
+    #[cfg(feature = "async")]
+    async fn call_async(&self, method: &str, params: Value) -> Result<Option<Value>> {
+        let mut req = self
+            .async_rclient
+            .post(&self.rpc_url)
+            .json(&rpc_request(method, params));
+        if let Some((user, password)) = &self.auth {
+            req = req.basic_auth(user, Some(password));
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|err| backend_error(err, method, self.name()))?;
+        let status = resp.status();
+        let body = resp
+            .text()
+            .await
+            .map_err(|err| backend_error(err, method, self.name()))?;
+        rpc_reply(method, status, &body)
+    }
+}
+
+impl ChainBackend for ElementsRpc {
+    fn name(&self) -> &'static str {
+        "elementsd"
+    }
+
+    fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>> {
+        let hex = self.call("getrawtransaction", json!([txid.to_hex(), false]))?;
+        hex.map(|hex| rpc_tx(&hex)).transpose()
+    }
+
+    fn get_tx_status(&self, txid: &Txid) -> Result<Option<BlockId>> {
+        let tx = self.call("getrawtransaction", json!([txid.to_hex(), true]))?;
+        match tx.as_ref().and_then(rpc_tx_blockhash) {
+            Some(blockhash) => {
+                let header = self
+                    .call("getblockheader", json!([blockhash, true]))?
+                    .or_err("block not found")?;
+                Ok(Some(rpc_block_id(&header)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_tip_height(&self) -> Result<u32> {
+        let height = self.call("getblockcount", json!([]))?;
+        Ok(serde_json::from_value(height.req()?)?)
+    }
+
+    fn get_tx_output_count(&self, txid: &Txid) -> Result<Option<usize>> {
+        let tx = self.call("getrawtransaction", json!([txid.to_hex(), true]))?;
+        tx.map(|tx| tx_output_count(&tx)).transpose()
+    }
+
+    #[cfg(feature = "async")]
+    fn get_tx_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<Transaction>> {
+        Box::pin(async move {
+            let hex = self
+                .call_async("getrawtransaction", json!([txid.to_hex(), false]))
+                .await?;
+            hex.map(|hex| rpc_tx(&hex)).transpose()
+        })
+    }
+
+    #[cfg(feature = "async")]
+    fn get_tx_output_count_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<usize>> {
+        Box::pin(async move {
+            let tx = self
+                .call_async("getrawtransaction", json!([txid.to_hex(), true]))
+                .await?;
+            tx.map(|tx| tx_output_count(&tx)).transpose()
+        })
+    }
+
+    #[cfg(feature = "async")]
+    fn get_tx_status_async<'a>(&'a self, txid: &'a Txid) -> ChainFuture<'a, Option<BlockId>> {
+        Box::pin(async move {
+            let tx = self
+                .call_async("getrawtransaction", json!([txid.to_hex(), true]))
+                .await?;
+            match tx.as_ref().and_then(rpc_tx_blockhash) {
+                Some(blockhash) => {
+                    let header = self
+                        .call_async("getblockheader", json!([blockhash, true]))
+                        .await?
+                        .or_err("block not found")?;
+                    Ok(Some(rpc_block_id(&header)?))
+                }
+                None => Ok(None),
+            }
         })
     }
 }
 
-pub fn verify_asset_issuance_tx(chain: &ChainQuery, asset: &Asset) -> Result<BlockId> {
+fn http_client(timeout: Duration) -> ReqClient {
+    ReqClient::builder()
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .build()
+        .expect("failed building http client")
+}
+
+#[cfg(feature = "async")]
+fn async_http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .build()
+        .expect("failed building http client")
+}
+
+// Classify failed requests to the backend, so that callers can tell transient failures (see
+// AssetError::is_transient()) apart from deterministic ones. Missing resources are not errors,
+// and are returned as None by the lookup methods instead.
+pub fn backend_error(err: reqwest::Error, what: &str, backend: &str) -> failure::Error {
+    let kind = if err.is_timeout() {
+        Some(AssetError::ChainTimeout)
+    } else if let Some(status) = err.status() {
+        if status.is_server_error() {
+            Some(AssetError::ChainServerError(status.as_u16()))
+        } else {
+            None
+        }
+    } else {
+        // connection refused, dns failures and other transport errors
+        Some(AssetError::ChainUnreachable)
+    };
+    let msg = format!("failed fetching {} from {}", what, backend);
+    let err = match kind {
+        Some(kind) => failure::Error::from(err).context(kind).into(),
+        None => failure::Error::from(err),
+    };
+    err.context(msg).into()
+}
+
+// elementsd replies to failed calls with an error status, but still with a json-rpc reply
+fn rpc_reply(method: &str, status: StatusCode, body: &str) -> Result<Option<Value>> {
+    let msg = || format!("failed calling {} on elementsd", method);
+    ensure!(
+        status != StatusCode::UNAUTHORIZED && status != StatusCode::FORBIDDEN,
+        "elementsd rpc authentication failed"
+    );
+    let mut reply: Value = match serde_json::from_str(body) {
+        Ok(reply) => reply,
+        Err(_) if status.is_server_error() => {
+            let err = AssetError::ChainServerError(status.as_u16());
+            return Err(failure::Error::from(err).context(msg()).into());
+        }
+        Err(err) => return Err(failure::Error::from(err).context(msg()).into()),
+    };
+    let error = reply["error"].take();
+    if error.is_null() {
+        return Ok(Some(reply["result"].take()));
+    }
+    // RPC_INVALID_ADDRESS_OR_KEY, returned for unknown transactions and blocks
+    if error["code"].as_i64() == Some(-5) {
+        return Ok(None);
+    }
+    bail!(
+        "{}: {}",
+        msg(),
+        error["message"].as_str().unwrap_or("unknown error")
+    )
+}
+
+// The input issuing the asset, if any, along with the prevout it spends. Reissuances of the asset
+// are skipped, as they don't commit to the contract.
+fn find_issuance_input(tx: &Transaction, asset_id: &AssetId) -> Option<(TxInput, OutPoint)> {
+    let txid = tx.txid();
+    tx.input
+        .iter()
+        .enumerate()
+        .find(|(_, txin)| {
+            let entropy = AssetId::generate_asset_entropy(
+                txin.previous_output,
+                ContractHash::from_inner(txin.asset_issuance.asset_entropy),
+            );
+            txin.has_issuance()
+                && txin.asset_issuance.asset_blinding_nonce == [0; 32]
+                && AssetId::from_entropy(entropy) == *asset_id
+        })
+        .map(|(vin, txin)| (TxInput { txid, vin }, txin.previous_output))
+}
+
+// Read the user and password from an elementsd `.cookie` file, for ChainQuery::elements_rpc()
+pub fn read_rpc_cookie(path: &std::path::Path) -> Result<(String, String)> {
+    let cookie = std::fs::read_to_string(path).context("failed reading rpc cookie file")?;
+    parse_rpc_auth(cookie.trim())
+}
+
+// Resolve the rpc credentials given either as `<user>:<password>` or as a path to a cookie file
+pub fn rpc_auth(
+    auth: Option<&str>,
+    cookie: Option<&std::path::Path>,
+) -> Result<Option<(String, String)>> {
+    match (auth, cookie) {
+        (Some(_), Some(_)) => bail!("only one of the rpc credentials and cookie may be given"),
+        (Some(auth), None) => Ok(Some(parse_rpc_auth(auth)?)),
+        (None, Some(path)) => Ok(Some(read_rpc_cookie(path)?)),
+        (None, None) => Ok(None),
+    }
+}
+
+// Parse rpc credentials given as `<user>:<password>`
+pub fn parse_rpc_auth(auth: &str) -> Result<(String, String)> {
+    let mut parts = auth.splitn(2, ':');
+    let user = parts.next().unwrap_or("");
+    let password = parts
+        .next()
+        .or_err("rpc credentials must be given as <user>:<password>")?;
+    Ok((user.to_string(), password.to_string()))
+}
+
+fn rpc_request(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "1.0", "id": "asset-registry", "method": method, "params": params })
+}
+
+fn rpc_tx(hex: &Value) -> Result<Transaction> {
+    let hex = hex.as_str().or_err("invalid getrawtransaction reply")?;
+    Ok(deserialize(&hex::decode(hex)?)?)
+}
+
+fn tx_output_count(tx: &Value) -> Result<usize> {
+    Ok(tx["vout"].as_array().or_err("invalid tx")?.len())
+}
+
+// The block the (verbose) transaction is confirmed in, if any. Mempool transactions have none.
+fn rpc_tx_blockhash(tx: &Value) -> Option<String> {
+    tx["blockhash"].as_str().map(String::from)
+}
+
+fn rpc_block_id(header: &Value) -> Result<BlockId> {
+    Ok(BlockId {
+        block_height: header["height"].as_u64().or_err("invalid block header")? as usize,
+        block_hash: serde_json::from_value(header["hash"].clone())?,
+        block_time: header["time"].as_u64().or_err("invalid block header")? as u32,
+    })
+}
+
+// Verify the asset's issuance against the chain's asset index data (as returned by get_asset()),
+// without fetching the issuance transaction itself
+pub fn verify_asset_issuance_data(asset: &Asset, asset_data: &Value) -> Result<()> {
+    let issuance_txin: TxInput = serde_json::from_value(asset_data["issuance_txin"].clone())?;
+    let issuance_prevout: OutPoint =
+        serde_json::from_value(asset_data["issuance_prevout"].clone())?;
+    let contract_hash: ContractHash = serde_json::from_value(asset_data["contract_hash"].clone())?;
+
+    // the asset index entry must be for the asset itself, not for the asset issuing it (as may
+    // be returned when looking up its reissuance token)
+    if let Some(asset_id) = asset_data["asset_id"].as_str() {
+        ensure!(asset_id == asset.asset_id.to_hex(), "asset id mismatch");
+    }
+
+    ensure!(
+        issuance_txin.txid == asset.issuance_txin.txid
+            && issuance_txin.vin == asset.issuance_txin.vin,
+        "issuance txin mismatch"
+    );
+    ensure!(
+        issuance_prevout == asset.issuance_prevout,
+        "issuance prevout mismatch"
+    );
+    ensure!(
+        contract_hash == asset.contract_hash()?,
+        "issuance contract hash mismatch"
+    );
+    ensure!(
+        asset_data["status"]["confirmed"].as_bool().unwrap_or(false),
+        "issuance transaction unconfirmed"
+    );
+
+    debug!(
+        "verified issuance data of asset {}, tx input {:?}",
+        asset.asset_id.to_hex(),
+        asset.issuance_txin,
+    );
+
+    Ok(())
+}
+
+pub fn verify_asset_issuance_tx(
+    chain: &ChainQuery,
+    asset: &Asset,
+    ctx: &VerifyContext,
+) -> Result<BlockId> {
     let tx = chain
         .get_tx(&asset.issuance_txin.txid)?
-        .or_err("issuance transaction not found")?;
+        .or_err(AssetError::IssuanceNotFound)?;
+    let blockid = chain
+        .get_tx_status(&asset.issuance_txin.txid)?
+        .or_err(AssetError::IssuanceUnconfirmed)?;
+
+    verify_issuance_tx(asset, &tx)?;
+    if ctx.single_unit_nfts {
+        verify_single_unit_issuance(asset, &tx)?;
+    }
+
+    let prevout_outputs = chain.get_tx_output_count(&asset.issuance_prevout.txid)?;
+    verify_prevout_exists(chain, asset, prevout_outputs)?;
+
+    Ok(blockid)
+}
+
+#[cfg(feature = "async")]
+pub async fn verify_asset_issuance_tx_async(
+    chain: &ChainQuery,
+    asset: &Asset,
+    ctx: &VerifyContext,
+) -> Result<BlockId> {
+    let tx = chain
+        .get_tx_async(&asset.issuance_txin.txid)
+        .await?
+        .or_err(AssetError::IssuanceNotFound)?;
+    let blockid = chain
+        .get_tx_status_async(&asset.issuance_txin.txid)
+        .await?
+        .or_err(AssetError::IssuanceUnconfirmed)?;
+
+    verify_issuance_tx(asset, &tx)?;
+    if ctx.single_unit_nfts {
+        verify_single_unit_issuance(asset, &tx)?;
+    }
+
+    let prevout_outputs = chain
+        .get_tx_output_count_async(&asset.issuance_prevout.txid)
+        .await?;
+    verify_prevout_exists(chain, asset, prevout_outputs)?;
+
+    Ok(blockid)
+}
+
+// The issuance prevout must exist on the chain's network. Asset ids are network-agnostic, so this
+// catches assets submitted to a registry serving a different network than the one they were
+// issued on.
+fn verify_prevout_exists(
+    chain: &ChainQuery,
+    asset: &Asset,
+    prevout_outputs: Option<usize>,
+) -> Result<()> {
+    let prevout = &asset.issuance_prevout;
+    let outputs = prevout_outputs.or_err(format!(
+        "issuance prevout tx {} not found on {}",
+        prevout.txid,
+        chain.network()
+    ))?;
+    ensure!(
+        (prevout.vout as usize) < outputs,
+        "issuance prevout {}:{} not found on {}",
+        prevout.txid,
+        prevout.vout,
+        chain.network()
+    );
+    Ok(())
+}
+
+fn verify_issuance_tx(asset: &Asset, tx: &Transaction) -> Result<()> {
     let txin = tx
         .input
         .get(asset.issuance_txin.vin)
         .or_err("issuance transaction missing input")?;
-    let blockid = chain
-        .get_tx_status(&asset.issuance_txin.txid)?
-        .or_err("issuance transaction unconfirmed")?;
 
     ensure!(
         tx.txid() == asset.issuance_txin.txid,
         "issuance txid mismatch"
     );
     ensure!(txin.has_issuance(), "input has no issuance");
+    // reissuances set the blinding nonce of the reissuance token being spent, while original
+    // issuances leave it zeroed. only the original issuance commits to the contract.
+    ensure!(
+        txin.asset_issuance.asset_blinding_nonce == [0; 32],
+        "input is a reissuance, the asset must be registered with its original issuance"
+    );
     ensure!(
         txin.previous_output == asset.issuance_prevout,
-        "issuance prevout mismatch"
+        "issuance prevout mismatch, `issuance_txin` spends {}:{} rather than {}:{}",
+        txin.previous_output.txid,
+        txin.previous_output.vout,
+        asset.issuance_prevout.txid,
+        asset.issuance_prevout.vout
     );
     ensure!(
         txin.asset_issuance.asset_entropy == asset.contract_hash()?.into_inner(),
@@ -129,18 +917,47 @@ pub fn verify_asset_issuance_tx(chain: &ChainQuery, asset: &Asset) -> Result<Blo
         asset.issuance_txin,
     );
 
-    Ok(blockid)
+    Ok(())
+}
+
+// Assets with a precision of 0 are treated as non-divisible collectibles, which must be issued
+// as a single unit. Called after verify_issuance_tx(), which checks the input exists.
+fn verify_single_unit_issuance(asset: &Asset, tx: &Transaction) -> Result<()> {
+    if asset.fields.precision != 0 {
+        return Ok(());
+    }
+    let issuance = &tx.input[asset.issuance_txin.vin].asset_issuance;
+    match issuance.amount {
+        confidential::Value::Explicit(1) => Ok(()),
+        confidential::Value::Explicit(amount) => bail!(
+            "assets with precision 0 must be issued with an amount of 1 (issued {})",
+            amount
+        ),
+        confidential::Value::Null => {
+            bail!("assets with precision 0 must be issued with an amount of 1 (issued 0)")
+        }
+        confidential::Value::Confidential(..) => {
+            bail!("assets with precision 0 must be issued with an explicit amount")
+        }
+    }
 }
 
 // needs to be run with --test-threads 1
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::asset::AssetRequest;
+    use crate::errors::asset_error;
+    use crate::util::wait_for_port;
+    use bitcoin_hashes::hex::FromHex;
     use rocket as r;
-    use rocket_contrib::json::JsonValue;
-    use std::{fs, str::FromStr};
+    use rocket::http::Status;
+    use rocket::response::status::Custom;
+    use rocket_contrib::json::{Json, JsonValue};
     use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Once;
+    use std::{fs, str::FromStr};
 
     static SPAWN_ONCE: Once = Once::new();
 
@@ -153,24 +970,118 @@ pub mod tests {
                 .unwrap();
             let rocket = r::custom(config).mount(
                 "/",
-                routes![tx_hex_handler, tx_status_handler, asset_handler],
+                routes![
+                    tx_hex_handler,
+                    tx_handler,
+                    tx_status_handler,
+                    asset_handler,
+                    assets_handler,
+                    rpc_handler
+                ],
             );
 
             std::thread::spawn(|| rocket.launch());
-        })
+        });
+        wait_for_port(58713);
     }
 
+    // txids starting with 0000 don't exist, ones starting with 5000 fail with a server error and
+    // ones starting with 7000 take 2 seconds to respond
     #[get("/tx/<txid>/hex")]
-    fn tx_hex_handler(txid: String) -> Result<String> {
+    fn tx_hex_handler(txid: String) -> Result<Option<String>> {
+        if txid.starts_with("0000") {
+            return Ok(None);
+        }
+        if txid.starts_with("5000") {
+            bail!("backend failure");
+        }
+        if txid.starts_with("7000") {
+            std::thread::sleep(Duration::from_secs(2));
+        }
         let path = format!("test/issuance-tx-{}.hex", &txid[..6]);
-        Ok(fs::read_to_string(path)?)
+        Ok(Some(fs::read_to_string(path)?))
     }
 
+    // any tx exists with 4 outputs, except for txids starting with 0000
+    #[get("/tx/<txid>")]
+    fn tx_handler(txid: String) -> Option<JsonValue> {
+        if txid.starts_with("0000") {
+            return None;
+        }
+        Some(JsonValue::from(json!({
+            "txid": txid,
+            "vout": [ {}, {}, {}, {} ],
+        })))
+    }
+
+    static MISSING_ASSET_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+    // asset ids starting with 0000 don't exist (with requests for ones starting with 0000ca
+    // counted, for the caching tests)
     #[get("/asset/<asset_id>")]
-    fn asset_handler(asset_id: String) -> Result<JsonValue> {
+    fn asset_handler(asset_id: String) -> Result<Option<JsonValue>> {
+        Ok(lookup_asset(&asset_id)?.map(JsonValue::from))
+    }
+
+    #[post("/assets", data = "<asset_ids>")]
+    fn assets_handler(asset_ids: Json<Vec<String>>) -> Result<JsonValue> {
+        let assets = asset_ids
+            .iter()
+            .map(|asset_id| lookup_asset(asset_id))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(JsonValue::from(json!(assets)))
+    }
+
+    fn lookup_asset(asset_id: &str) -> Result<Option<Value>> {
+        if asset_id.starts_with("0000") {
+            if asset_id.starts_with("0000ca") {
+                MISSING_ASSET_REQUESTS.fetch_add(1, Ordering::SeqCst);
+            }
+            return Ok(None);
+        }
         let path = format!("test/asset-{}.json", &asset_id[..6]);
         let jsonstr = fs::read_to_string(path)?;
-        Ok(JsonValue::from(serde_json::Value::from_str(&jsonstr)?))
+        Ok(Some(serde_json::Value::from_str(&jsonstr)?))
+    }
+
+    // an elementsd json-rpc, with the same transactions as the esplora api above
+    #[post("/rpc", data = "<req>")]
+    fn rpc_handler(req: Json<Value>) -> Custom<JsonValue> {
+        let params = &req["params"];
+        let txid = params[0].as_str().unwrap_or("");
+        let result = match req["method"].as_str().unwrap_or("") {
+            "getrawtransaction" if txid.starts_with("0000") => None,
+            "getrawtransaction" if params[1] == json!(true) => Some(json!({
+                "txid": txid,
+                "blockhash": "6ef1b8ac6cfacae9493e8d214d5ddd70322abe39bc0ab82727849b47bfb1fce6",
+                "vout": [ {}, {}, {}, {} ],
+            })),
+            "getrawtransaction" => {
+                let path = format!("test/issuance-tx-{}.hex", &txid[..6]);
+                fs::read_to_string(path).ok().map(|hex| json!(hex.trim()))
+            }
+            "getblockheader" => {
+                Some(json!({ "hash": params[0], "height": 999, "time": 1556733700 }))
+            }
+            "getblockcount" => Some(json!(1000)),
+            _ => {
+                let error = json!({ "code": -32601, "message": "Method not found" });
+                let reply = json!({ "result": null, "error": error, "id": req["id"] });
+                return Custom(Status::NotFound, JsonValue::from(reply));
+            }
+        };
+        match result {
+            Some(result) => {
+                let reply = json!({ "result": result, "error": null, "id": req["id"] });
+                Custom(Status::Ok, JsonValue::from(reply))
+            }
+            None => {
+                let error =
+                    json!({ "code": -5, "message": "No such mempool or blockchain transaction" });
+                let reply = json!({ "result": null, "error": error, "id": req["id"] });
+                Custom(Status::InternalServerError, JsonValue::from(reply))
+            }
+        }
     }
 
     #[get("/tx/<_txid>/status")]
@@ -195,7 +1106,234 @@ pub mod tests {
         let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
         let chain = ChainQuery::new("http://localhost:58713".to_string());
 
-        verify_asset_issuance_tx(&chain, &asset)?;
+        verify_asset_issuance_tx(&chain, &asset, &VerifyContext::default())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test2_reject_reissuance() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let hex = fs::read_to_string("test/issuance-tx-9b75a5.hex")?;
+        let mut tx: Transaction = deserialize(&hex::decode(hex.trim())?)?;
+        verify_issuance_tx(&asset, &tx)?;
+
+        tx.input[asset.issuance_txin.vin]
+            .asset_issuance
+            .asset_blinding_nonce = [1; 32];
+        asset.issuance_txin.txid = tx.txid();
+        let err = verify_issuance_tx(&asset, &tx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "input is a reissuance, the asset must be registered with its original issuance"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test3_verify_prevout() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let chain = ChainQuery::new("http://localhost:58713".to_string())
+            .with_network(Network::ElementsRegtest);
+        assert_eq!(chain.network(), Network::ElementsRegtest);
+
+        verify_prevout_exists(
+            &chain,
+            &asset,
+            chain.get_tx_output_count(&asset.issuance_prevout.txid)?,
+        )?;
+
+        let missing = Txid::from_hex(&format!(
+            "0000{}",
+            &asset.issuance_prevout.txid.to_hex()[4..]
+        ))?;
+        assert_eq!(chain.get_tx_output_count(&missing)?, None);
+        let err = verify_prevout_exists(&chain, &asset, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "issuance prevout tx {} not found on elementsregtest",
+                asset.issuance_prevout.txid
+            )
+        );
+
+        asset.issuance_prevout.vout = 4;
+        assert!(verify_prevout_exists(&chain, &asset, Some(4)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test4_single_unit_nfts() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let hex = fs::read_to_string("test/issuance-tx-9b75a5.hex")?;
+        let mut tx: Transaction = deserialize(&hex::decode(hex.trim())?)?;
+        let vin = asset.issuance_txin.vin;
+
+        // not enforced for divisible assets
+        asset.fields.precision = 8;
+        tx.input[vin].asset_issuance.amount = confidential::Value::Explicit(5000);
+        verify_single_unit_issuance(&asset, &tx)?;
+
+        asset.fields.precision = 0;
+        let err = verify_single_unit_issuance(&asset, &tx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "assets with precision 0 must be issued with an amount of 1 (issued 5000)"
+        );
+
+        tx.input[vin].asset_issuance.amount = confidential::Value::Explicit(1);
+        verify_single_unit_issuance(&asset, &tx)?;
+
+        tx.input[vin].asset_issuance.amount = confidential::Value::Null;
+        assert!(verify_single_unit_issuance(&asset, &tx).is_err());
+        tx.input[vin].asset_issuance.amount = confidential::Value::Confidential(8, [1; 32]);
+        assert!(verify_single_unit_issuance(&asset, &tx).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test5_get_assets() -> Result<()> {
+        let existing =
+            AssetId::from_hex("b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05")?;
+        let missing = AssetId::from_hex(&format!("0000ca{}", "01".repeat(29)))?;
+        let requests = || MISSING_ASSET_REQUESTS.load(Ordering::SeqCst);
+
+        // batched lookups preserve the order, with missing assets as nulls
+        let esplora = Esplora::new("http://localhost:58713".to_string(), CHAIN_TIMEOUT);
+        let chain = ChainQuery::with_backend(esplora.with_batch_assets(true));
+        let before = requests();
+        let assets = chain.get_assets(&[existing, missing, existing])?;
+        assert_eq!(assets.len(), 3);
+        assert_eq!(assets[0], assets[2]);
+        assert_eq!(
+            assets[0].as_ref().req()?["asset_id"],
+            json!(existing.to_hex())
+        );
+        assert_eq!(assets[1], None);
+        assert_eq!(requests(), before + 1);
+
+        // single lookups go through the same path, and known-missing assets aren't re-queried
+        assert!(chain.get_asset(&existing)?.is_some());
+        assert_eq!(chain.get_asset(&missing)?, None);
+        assert_eq!(chain.get_assets(&[missing, missing])?, vec![None, None]);
+        assert_eq!(requests(), before + 1);
+
+        // until the ttl expires
+        let chain = ChainQuery::new("http://localhost:58713".to_string())
+            .with_missing_asset_ttl(Duration::from_millis(50));
+        let before = requests();
+        assert_eq!(chain.get_asset(&missing)?, None);
+        assert_eq!(chain.get_asset(&missing)?, None);
+        assert_eq!(requests(), before + 1);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(chain.get_asset(&missing)?, None);
+        assert_eq!(requests(), before + 2);
+
+        // or always when disabled
+        let chain = ChainQuery::new("http://localhost:58713".to_string())
+            .with_missing_asset_ttl(Duration::from_secs(0));
+        let before = requests();
+        assert_eq!(chain.get_asset(&missing)?, None);
+        assert_eq!(chain.get_asset(&missing)?, None);
+        assert_eq!(requests(), before + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test6_backend_errors() -> Result<()> {
+        let txid = |prefix: &str| {
+            Txid::from_hex(&format!(
+                "{}a545ff42c403839b0be69c1047144dc3e778c0d937d85c71538f169eebb5",
+                prefix
+            ))
+        };
+        let chain = ChainQuery::with_timeout(
+            "http://localhost:58713".to_string(),
+            Duration::from_millis(500),
+        );
+
+        // missing transactions are not errors
+        assert!(chain.get_tx(&txid("0000")?)?.is_none());
+
+        let err = chain.get_tx(&txid("5000")?).unwrap_err();
+        assert_eq!(asset_error(&err), Some(&AssetError::ChainServerError(500)));
+        assert_eq!(err.to_string(), "failed fetching tx from esplora");
+
+        let err = chain.get_tx(&txid("7000")?).unwrap_err();
+        assert_eq!(asset_error(&err), Some(&AssetError::ChainTimeout));
+
+        let unreachable = ChainQuery::new("http://127.0.0.1:1".to_string());
+        let err = unreachable.get_tip_height().unwrap_err();
+        assert_eq!(asset_error(&err), Some(&AssetError::ChainUnreachable));
+        assert!(asset_error(&err).req()?.is_transient());
+
+        // reported as such through verification too
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let err = asset
+            .verify_capturing(Some(&unreachable), &VerifyContext::default())
+            .unwrap_err();
+        assert_eq!(asset_error(&err), Some(&AssetError::ChainUnreachable));
+        assert!(!AssetError::IssuanceNotFound.is_transient());
+        Ok(())
+    }
+
+    #[test]
+    fn test7_elements_rpc() -> Result<()> {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let auth = parse_rpc_auth("user:pass:word")?;
+        assert_eq!(auth, ("user".to_string(), "pass:word".to_string()));
+        assert!(parse_rpc_auth("user").is_err());
+
+        let chain = ChainQuery::elements_rpc(
+            "http://localhost:58713/rpc".to_string(),
+            Some(auth),
+            Duration::from_secs(10),
+        );
+        assert!(!chain.has_asset_index());
+        assert_eq!(chain.get_tip_height()?, 1000);
+
+        // verified against the issuance transaction, just like with esplora
+        let block = verify_asset_issuance_tx(&chain, &asset, &VerifyContext::default())?;
+        assert_eq!(block.block_height, 999);
+        assert_eq!(block.block_time, 1556733700);
+
+        let missing = Txid::from_hex(&format!("0000{}", &asset.issuance_txin.txid.to_hex()[4..]))?;
+        assert!(chain.get_tx(&missing)?.is_none());
+        assert_eq!(chain.get_tx_status(&missing)?, None);
+        assert_eq!(chain.get_tx_output_count(&missing)?, None);
+
+        // assets cannot be looked up by their id
+        let err = chain.get_asset(&asset.asset_id).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "asset lookups are not supported by the elementsd backend"
+        );
+
+        // but their issuance is found given the issuance txid
+        let (issuance_txin, issuance_prevout) = chain
+            .get_issuance(&asset.asset_id, Some(&asset.issuance_txin.txid))?
+            .req()?;
+        assert_eq!(issuance_txin.txid, asset.issuance_txin.txid);
+        assert_eq!(issuance_txin.vin, asset.issuance_txin.vin);
+        assert_eq!(issuance_prevout, asset.issuance_prevout);
+        assert!(chain
+            .get_issuance(&asset.asset_id, Some(&missing))?
+            .is_none());
+        let other_asset = AssetId::from_hex(&"01".repeat(32))?;
+        assert!(chain
+            .get_issuance(&other_asset, Some(&asset.issuance_txin.txid))?
+            .is_none());
+        assert!(chain.get_issuance(&asset.asset_id, None).is_err());
+
+        // which is enough to register assets by their id
+        let req = AssetRequest {
+            asset_id: asset.asset_id,
+            contract: asset.contract.clone(),
+            contract_raw: None,
+            issuance_txid: Some(asset.issuance_txin.txid),
+        };
+        let from_req = Asset::from_request(req, &chain)?;
+        assert_eq!(from_req.issuance_prevout, asset.issuance_prevout);
+        verify_asset_issuance_tx(&chain, &from_req, &VerifyContext::default())?;
         Ok(())
     }
 }
@@ -65,10 +65,27 @@ impl Client {
             .context("failed parsing asset from registry")?)
     }
 
-    pub fn delete(&self, asset_id: &AssetId, signature: &[u8]) -> Result<()> {
+    // Delete an asset, given the issuer's signature over the registry's deletion message with the
+    // `sequence` number (or over the legacy message, without one)
+    pub fn delete(
+        &self,
+        asset_id: &AssetId,
+        signature: &[u8],
+        sequence: Option<u64>,
+    ) -> Result<()> {
+        self.delete_with_sigs(asset_id, &[signature.to_vec()], sequence)
+    }
+
+    // Delete an asset of a multisig issuer, with signatures from the required number of keys
+    pub fn delete_with_sigs(
+        &self,
+        asset_id: &AssetId,
+        signatures: &[Vec<u8>],
+        sequence: Option<u64>,
+    ) -> Result<()> {
         self.rclient
             .delete(self.registry_url.join(&asset_id.to_hex())?)
-            .json(&json!({ "signature": base64::encode(signature) }))
+            .json(&deletion_body(signatures, sequence))
             .send()
             .context("failed sending deletion request to registry")?
             .error_for_status()
@@ -90,3 +107,32 @@ impl Client {
         Ok(())
     }
 }
+
+// Single signatures are sent as `signature`, which registries that predate multisig deletions
+// understand too
+fn deletion_body(signatures: &[Vec<u8>], sequence: Option<u64>) -> Value {
+    match signatures {
+        [signature] => json!({ "signature": base64::encode(signature), "sequence": sequence }),
+        _ => {
+            let signatures: Vec<String> = signatures.iter().map(base64::encode).collect();
+            json!({ "signatures": signatures, "sequence": sequence })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deletion_body() {
+        assert_eq!(
+            deletion_body(&[vec![1, 2]], Some(5)),
+            json!({ "signature": "AQI=", "sequence": 5 })
+        );
+        assert_eq!(
+            deletion_body(&[vec![1, 2], vec![3]], None),
+            json!({ "signatures": ["AQI=", "Aw=="], "sequence": null })
+        );
+    }
+}
@@ -0,0 +1,566 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+use std::{fmt, fs};
+
+use elements::AssetId;
+use reqwest::Certificate;
+
+use crate::asset::{Asset, NAME_MAX_BYTES, NAME_MAX_LEN, NAME_MIN_LEN};
+use crate::entity::{
+    CustomVerifier, DomainTxtVerifier, DomainVerifier, EntityVerifier, GitHubVerifier,
+    NostrVerifier, TwitterVerifier, DEFAULT_PROOF_PATH_PREFIX,
+};
+use crate::errors::{Result, ResultExt};
+use crate::network::Network;
+use crate::util::{domain_to_ascii, verify_domain_name};
+
+pub const DEFAULT_HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_CONTRACT_MAX_SIZE: usize = 8 * 1024;
+
+// Well-known symbols that operators may reserve, to prevent impersonating them
+pub const WELL_KNOWN_SYMBOLS: &[&str] = &[
+    "BTC",
+    "L-BTC",
+    "LBTC",
+    "XBT",
+    "Bitcoin",
+    "Liquid Bitcoin",
+    "USD",
+    "EUR",
+];
+
+// Operator-configurable verification policy.
+//
+// The defaults match the rules enforced by the protocol itself, so `VerifyContext::default()`
+// accepts exactly what `Asset::verify()` always accepted. Operators may tighten them for their
+// own registry.
+#[derive(Debug, Clone)]
+pub struct VerifyContext {
+    // The network whose policy asset (L-BTC) cannot be registered
+    pub network: Network,
+
+    // Additional asset ids that cannot be registered
+    pub reserved_assets: HashSet<AssetId>,
+
+    // Tickers and names that cannot be registered, matched case-insensitively (stored
+    // lowercased). Applies even when no registered asset uses them, unlike the uniqueness policy.
+    pub reserved_symbols: HashSet<String>,
+
+    // Reject assets whose entity domain is blocked, before fetching their proof
+    pub domain_blocklist: Option<DomainBlocklist>,
+
+    // Only accept assets whose entity is a domain on this list (in its punycode form, see
+    // load_domain_allowlist()), when set. `*.example.com` entries allow any subdomain of
+    // example.com, but not example.com itself. Assets with other entity types are rejected.
+    pub domain_allowlist: Option<HashSet<String>>,
+
+    // Allowed length range for asset names, in characters. Cannot be set wider than the
+    // protocol's own limits (NAME_MIN_LEN to NAME_MAX_LEN).
+    pub name_min_len: usize,
+    pub name_max_len: usize,
+
+    // Maximum utf-8 encoded size of asset names, in bytes. Cannot be set above NAME_MAX_BYTES.
+    pub name_max_bytes: usize,
+
+    // Maximum size of the contract's json serialization, in bytes. Checked before any other
+    // verification, to cheaply reject oversized submissions.
+    pub contract_max_size: usize,
+
+    // Minimum number of non-whitespace characters required in the `name` of assets that don't
+    // have a `ticker`, so that wallets have something meaningful to display. 0 disables the check.
+    pub tickerless_min_name_chars: usize,
+
+    // Compare domain proof pages case-insensitively, to tolerate CMSes that auto-capitalize text.
+    // This slightly weakens the proof, as the page no longer has to be exactly what the issuer
+    // was asked to publish.
+    pub proof_case_insensitive: bool,
+
+    // Require domain proofs to end with " at <unix timestamp>", no older than this. Protects
+    // against replaying proofs that the issuer has since taken down. The age is only checked at
+    // intake, as the proofs of stored assets keep aging.
+    pub proof_max_age: Option<Duration>,
+
+    // Whether newly submitted assets are being verified, rather than stored ones re-verified
+    // (see Registry::verify_all()). Checks that only apply to submissions, like the proof age,
+    // are skipped otherwise.
+    pub intake: bool,
+
+    // Also accept domain proofs hosted on the www. subdomain of an apex domain (or vice versa),
+    // tried when the registered domain has no proof page (404). The proof must still be for the
+    // registered domain. This slightly broadens what counts as control of the domain.
+    pub www_equivalence: bool,
+
+    // The path domain proof pages are hosted under, followed by the asset id. Registries for
+    // other asset namespaces may use their own, so that their proofs don't collide with mainnet
+    // ones on the same domain.
+    pub proof_path_prefix: String,
+
+    // Fall back to a `_liquid-asset-proof.<domain>` TXT record containing `asset_id=<id>` when
+    // the domain proof page cannot be verified. The proof page takes precedence when both exist.
+    pub dns_txt_fallback: bool,
+
+    // Treat assets with a `precision` of 0 as non-divisible collectibles (NFTs), requiring them to
+    // be issued on-chain with an explicit amount of exactly 1. Only checked against the issuance
+    // transaction.
+    pub single_unit_nfts: bool,
+
+    // SOCKS5 proxy address (like 127.0.0.1:9050) for fetching domain proofs of .onion domains,
+    // which cannot be verified without one. Other domains are always fetched directly.
+    pub tor_proxy: Option<String>,
+
+    // An additional root certificate trusted for fetching proofs and icons over https, like the
+    // private CA of an internal testnet (see load_root_cert()). The system roots remain trusted.
+    pub tls_root_cert: Option<Certificate>,
+
+    // Accept any certificate when fetching over https, leaving proofs unauthenticated. Only meant
+    // for testing against self-signed domains, a warning is logged when used.
+    pub danger_accept_invalid_certs: bool,
+
+    // Connect and overall timeout for fetching proofs, so that unresponsive hosts fail
+    // verification rather than stalling it
+    pub http_timeout: Duration,
+
+    // Retries for fetching domain proof pages that fail with a network error or a 5xx response.
+    // Missing pages and mismatching contents are never retried.
+    pub fetch_retry: RetryPolicy,
+
+    // Limit how often proof pages are fetched from each host, across all verifications sharing
+    // the limiter. Fetches over the limit fail with AssetError::FetchRateLimited.
+    pub fetch_limiter: Option<FetchLimiter>,
+
+    // External service to approve assets after all other checks pass (see oracle::verify_oracle)
+    pub oracle_url: Option<String>,
+
+    // Skip re-fetching the proofs of domain links that were verified successfully recently
+    pub link_cache: Option<LinkCache>,
+
+    // Twitter API bearer token, required for verifying `AssetEntity::Twitter` entities
+    pub twitter_bearer_token: Option<String>,
+
+    // Nostr relay urls (like wss://relay.example.com) queried for the proofs of
+    // `AssetEntity::Nostr` entities, which cannot be verified without any
+    pub nostr_relays: Vec<String>,
+
+    // Verifiers for each entity type, defaulting to the built-in ones, and for each kind of
+    // `AssetEntity::Custom` entities. Entities with no registered verifier are rejected.
+    pub entity_verifiers: EntityVerifiers,
+}
+
+impl Default for VerifyContext {
+    fn default() -> Self {
+        VerifyContext {
+            network: Network::default(),
+            reserved_assets: HashSet::new(),
+            reserved_symbols: HashSet::new(),
+            domain_allowlist: None,
+            domain_blocklist: None,
+            name_min_len: NAME_MIN_LEN,
+            name_max_len: NAME_MAX_LEN,
+            name_max_bytes: NAME_MAX_BYTES,
+            contract_max_size: DEFAULT_CONTRACT_MAX_SIZE,
+            tickerless_min_name_chars: 0,
+            proof_case_insensitive: false,
+            proof_max_age: None,
+            intake: true,
+            www_equivalence: false,
+            proof_path_prefix: DEFAULT_PROOF_PATH_PREFIX.to_string(),
+            dns_txt_fallback: false,
+            single_unit_nfts: false,
+            tor_proxy: None,
+            tls_root_cert: None,
+            danger_accept_invalid_certs: false,
+            http_timeout: DEFAULT_HTTP_TIMEOUT,
+            fetch_retry: RetryPolicy::default(),
+            fetch_limiter: None,
+            oracle_url: None,
+            twitter_bearer_token: None,
+            nostr_relays: vec![],
+            link_cache: None,
+            entity_verifiers: EntityVerifiers::default(),
+        }
+    }
+}
+
+// How many times to attempt transient operations, waiting `backoff` after the first failed
+// attempt and doubling the wait after each subsequent one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(attempts: u32, backoff: Duration) -> Self {
+        RetryPolicy { attempts, backoff }
+    }
+
+    // The wait after the given failed attempt (counting from 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff * 2u32.saturating_pow(attempt.saturating_sub(1))
+    }
+}
+
+// A single attempt, with no retries
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(1, Duration::from_secs(1))
+    }
+}
+
+// Load reserved tickers/names from a file with one per line, skipping empty lines and #comments.
+// Returned lowercased, for use as `VerifyContext::reserved_symbols`.
+pub fn load_reserved_symbols(path: &Path) -> Result<HashSet<String>> {
+    let contents = fs::read_to_string(path).context("failed reading reserved symbols")?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect())
+}
+
+// Load allowed domains from a file with one per line, skipping empty lines and #comments.
+// Returned in their punycode form, for use as `VerifyContext::domain_allowlist`.
+pub fn load_domain_allowlist(path: &Path) -> Result<HashSet<String>> {
+    let contents = fs::read_to_string(path).context("failed reading domain allowlist")?;
+    parse_domain_list(&contents)
+}
+
+// Domains (or `*.<suffix>` patterns) listed one per line, lowercased to their punycode form
+fn parse_domain_list(contents: &str) -> Result<HashSet<String>> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|entry| {
+            let entry = entry.to_lowercase();
+            let (wildcard, domain) = match entry.strip_prefix("*.") {
+                // suffixes may be a bare tld, validated as the parent of some subdomain
+                Some(suffix) => ("*.", suffix),
+                None => ("", &entry[..]),
+            };
+            let validated = match wildcard {
+                "" => verify_domain_name(domain),
+                _ => verify_domain_name(&format!("x.{}", domain)),
+            };
+            validated.context(format!("invalid domain `{}`", entry))?;
+            let ascii = domain_to_ascii(domain)?;
+            Ok(format!("{}{}", wildcard, ascii.trim_end_matches('.')))
+        })
+        .collect()
+}
+
+// Whether the domain is on the allowlist, directly or through a wildcard entry for any of
+// its parent domains
+pub fn domain_allowed(allowlist: &HashSet<String>, domain: &str) -> bool {
+    let domain = match domain_to_ascii(domain) {
+        Ok(domain) => domain.trim_end_matches('.').to_string(),
+        Err(_) => return false,
+    };
+    allowlist.contains(&domain)
+        || domain
+            .match_indices('.')
+            .any(|(i, _)| allowlist.contains(&format!("*{}", &domain[i..])))
+}
+
+// The policy lists loaded from files, which may be reloaded while running (see
+// Registry::reload_policy()). The domain blocklist reloads itself, see DomainBlocklist.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyFiles {
+    // `VerifyContext::reserved_symbols`, along with the WELL_KNOWN_SYMBOLS if enabled
+    pub reserved_symbols: Option<PathBuf>,
+    pub reserve_well_known_symbols: bool,
+    // `VerifyContext::domain_allowlist`
+    pub domain_allowlist: Option<PathBuf>,
+}
+
+impl PolicyFiles {
+    // Load the lists into the context. Fails without changing it if any of them is invalid.
+    pub fn apply(&self, ctx: &mut VerifyContext) -> Result<()> {
+        let mut reserved_symbols = match &self.reserved_symbols {
+            Some(path) => load_reserved_symbols(path)?,
+            None => HashSet::new(),
+        };
+        if self.reserve_well_known_symbols {
+            reserved_symbols.extend(WELL_KNOWN_SYMBOLS.iter().map(|s| s.to_lowercase()));
+        }
+        let domain_allowlist = match &self.domain_allowlist {
+            Some(path) => Some(load_domain_allowlist(path)?),
+            None => None,
+        };
+
+        ctx.reserved_symbols = reserved_symbols;
+        ctx.domain_allowlist = domain_allowlist;
+        Ok(())
+    }
+
+    // The modification time and size of each file, to tell when they need reloading
+    pub fn stamps(&self) -> Vec<Option<(SystemTime, u64)>> {
+        self.reserved_symbols
+            .iter()
+            .chain(&self.domain_allowlist)
+            .map(|path| file_stamp(path).ok())
+            .collect()
+    }
+}
+
+// Load a PEM root certificate, for use as `VerifyContext::tls_root_cert`
+pub fn load_root_cert(path: &Path) -> Result<Certificate> {
+    let pem = fs::read(path).context("failed reading root certificate")?;
+    Ok(Certificate::from_pem(&pem).context("invalid root certificate")?)
+}
+
+lazy_static! {
+    // Shared by all the default verifiers, to tell whether domains are still verified by it
+    static ref BUILTIN_DOMAIN_VERIFIER: Arc<dyn EntityVerifier> = Arc::new(DomainVerifier);
+}
+
+#[derive(Clone)]
+pub struct EntityVerifiers(HashMap<String, Arc<dyn EntityVerifier>>);
+
+impl EntityVerifiers {
+    // No verifiers at all, not even the built-in ones
+    pub fn empty() -> Self {
+        EntityVerifiers(HashMap::new())
+    }
+
+    // Register the verifier for the entity type, replacing the existing one (if any)
+    pub fn register<V>(&mut self, kind: &str, verifier: V)
+    where
+        V: EntityVerifier + 'static,
+    {
+        self.0.insert(kind.to_string(), Arc::new(verifier));
+    }
+
+    // Register the function verifying `AssetEntity::Custom` entities of the given `kind`, given
+    // the entity's `value`
+    pub fn register_custom<F>(&mut self, kind: &str, verifier: F)
+    where
+        F: Fn(&Asset, &str) -> Result<()> + Send + Sync + 'static,
+    {
+        self.register(&format!("custom:{}", kind), CustomVerifier(verifier));
+    }
+
+    pub fn remove(&mut self, kind: &str) {
+        self.0.remove(kind);
+    }
+
+    // Looked up by AssetEntity::verifier_kind()
+    pub fn get(&self, kind: &str) -> Option<&Arc<dyn EntityVerifier>> {
+        self.0.get(kind)
+    }
+
+    // Whether domains are verified by the built-in verifier, which verify_asset_link_async()
+    // runs using non-blocking requests
+    pub(crate) fn has_builtin_domain(&self) -> bool {
+        self.0.get("domain").map_or(false, |verifier| {
+            // compared as thin pointers, as vtables may be duplicated across codegen units
+            Arc::as_ptr(verifier) as *const ()
+                == Arc::as_ptr(&*BUILTIN_DOMAIN_VERIFIER) as *const ()
+        })
+    }
+}
+
+impl Default for EntityVerifiers {
+    fn default() -> Self {
+        let mut verifiers = EntityVerifiers::empty();
+        verifiers
+            .0
+            .insert("domain".to_string(), BUILTIN_DOMAIN_VERIFIER.clone());
+        verifiers.register("domain_txt", DomainTxtVerifier);
+        verifiers.register("twitter", TwitterVerifier);
+        verifiers.register("github", GitHubVerifier);
+        verifiers.register("nostr", NostrVerifier);
+        verifiers
+    }
+}
+
+impl fmt::Debug for EntityVerifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+// Records when domain links were last verified successfully, keyed by asset id and domain.
+// Clones share the same records, so a single cache may be injected into multiple contexts.
+#[derive(Debug, Clone)]
+pub struct LinkCache {
+    ttl: Duration,
+    verified: Arc<Mutex<HashMap<(AssetId, String), Instant>>>,
+}
+
+impl LinkCache {
+    pub fn new(ttl: Duration) -> Self {
+        LinkCache {
+            ttl,
+            verified: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // Whether the link was verified successfully within the ttl
+    pub fn is_fresh(&self, asset_id: &AssetId, domain: &str) -> bool {
+        let verified = self.verified.lock().unwrap();
+        verified
+            .get(&(*asset_id, domain.to_string()))
+            .map_or(false, |verified_at| verified_at.elapsed() < self.ttl)
+    }
+
+    pub fn record(&self, asset_id: &AssetId, domain: &str) {
+        let mut verified = self.verified.lock().unwrap();
+        // drop expired records while we're at it
+        let ttl = self.ttl;
+        verified.retain(|_, verified_at| verified_at.elapsed() < ttl);
+        verified.insert((*asset_id, domain.to_string()), Instant::now());
+    }
+
+    // Forget the links of the given asset, so that the next verification fetches them again
+    pub fn invalidate(&self, asset_id: &AssetId) {
+        let mut verified = self.verified.lock().unwrap();
+        verified.retain(|(cached_id, _), _| cached_id != asset_id);
+    }
+
+    pub fn clear(&self) {
+        self.verified.lock().unwrap().clear();
+    }
+}
+
+// Domains known for abuse, rejected as entities before their proof is fetched. Loaded from a file
+// with one per line (like load_domain_allowlist()), where each entry blocks the domain along with
+// all of its subdomains and `*.<suffix>` entries block any domain under the suffix (like `*.xyz`).
+// The file is reloaded once its modification time or size changes, so that it can be updated
+// without a restart. Clones share the loaded list, so a single blocklist may be injected into multiple
+// contexts.
+#[derive(Debug, Clone)]
+pub struct DomainBlocklist {
+    path: PathBuf,
+    loaded: Arc<Mutex<LoadedBlocklist>>,
+}
+
+#[derive(Debug)]
+struct LoadedBlocklist {
+    // the file's modification time and size when loaded
+    stamp: Option<(SystemTime, u64)>,
+    domains: HashSet<String>,
+}
+
+impl DomainBlocklist {
+    // Fails if the file cannot be loaded initially. Later reloads that fail keep the previously
+    // loaded list.
+    pub fn load(path: &Path) -> Result<Self> {
+        let (stamp, domains) = read_blocklist(path)?;
+        Ok(DomainBlocklist {
+            path: path.to_path_buf(),
+            loaded: Arc::new(Mutex::new(LoadedBlocklist { stamp, domains })),
+        })
+    }
+
+    pub fn is_blocked(&self, domain: &str) -> bool {
+        let domain = match domain_to_ascii(domain) {
+            Ok(domain) => domain.trim_end_matches('.').to_string(),
+            // not a domain we'd fetch from anyway
+            Err(_) => return false,
+        };
+        let loaded = self.refresh();
+        loaded.domains.contains(&domain)
+            || domain.match_indices('.').any(|(i, _)| {
+                let parent = &domain[i + 1..];
+                loaded.domains.contains(parent) || loaded.domains.contains(&format!("*.{}", parent))
+            })
+    }
+
+    // The loaded list, reloaded first if the file was modified since
+    fn refresh(&self) -> MutexGuard<LoadedBlocklist> {
+        let mut loaded = self.loaded.lock().unwrap();
+        let stamp = file_stamp(&self.path).ok();
+        if stamp.is_some() && stamp != loaded.stamp {
+            match read_blocklist(&self.path) {
+                Ok((stamp, domains)) => {
+                    info!(
+                        "reloaded domain blocklist with {} entries from {}",
+                        domains.len(),
+                        self.path.display()
+                    );
+                    *loaded = LoadedBlocklist { stamp, domains };
+                }
+                Err(err) => {
+                    warn!(
+                        "failed reloading domain blocklist, keeping the previous one: {}",
+                        err
+                    );
+                    // don't retry until it's modified again
+                    loaded.stamp = stamp;
+                }
+            }
+        }
+        loaded
+    }
+}
+
+fn file_stamp(path: &Path) -> Result<(SystemTime, u64)> {
+    let meta = fs::metadata(path)?;
+    Ok((meta.modified()?, meta.len()))
+}
+
+fn read_blocklist(path: &Path) -> Result<(Option<(SystemTime, u64)>, HashSet<String>)> {
+    let stamp = file_stamp(path).ok();
+    let contents = fs::read_to_string(path).context("failed reading domain blocklist")?;
+    Ok((stamp, parse_domain_list(&contents)?))
+}
+
+// Token buckets of proof page fetches, keyed by host. Each host may be fetched `per_minute` times
+// in a burst, after which its tokens are refilled gradually over a minute. Clones share the same
+// buckets, so a single limiter may be injected into multiple contexts.
+#[derive(Debug, Clone)]
+pub struct FetchLimiter {
+    per_minute: u32,
+    buckets: Arc<Mutex<HashMap<String, FetchBucket>>>,
+}
+
+#[derive(Debug)]
+struct FetchBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl FetchLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        FetchLimiter {
+            per_minute: per_minute.max(1),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // Take a token for fetching from the host, or return how long to wait until one is available
+    pub fn acquire(&self, host: &str) -> StdResult<(), Duration> {
+        let capacity = f64::from(self.per_minute);
+        let rate = capacity / 60.0;
+        let now = Instant::now();
+        let refilled = |bucket: &FetchBucket| {
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            (bucket.tokens + elapsed * rate).min(capacity)
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        // drop buckets that were refilled in full while we're at it
+        buckets.retain(|_, bucket| refilled(bucket) < capacity);
+
+        let bucket = buckets
+            .entry(host.to_lowercase())
+            .or_insert_with(|| FetchBucket {
+                tokens: capacity,
+                updated: now,
+            });
+        bucket.tokens = refilled(bucket);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+}
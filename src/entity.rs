@@ -1,83 +1,1242 @@
+use std::collections::HashMap;
 use std::fmt;
+use std::net::{Ipv4Addr, TcpStream, ToSocketAddrs};
+use std::result::Result as StdResult;
+use std::sync::Once;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use bitcoin_hashes::hex::ToHex;
+use bitcoin_hashes::{hex::ToHex, sha256, Hash};
 use failure::ResultExt;
-use reqwest::blocking::get as reqwest_get;
+use reqwest::{
+    blocking::{Client as ReqClient, ClientBuilder},
+    header::{HOST, USER_AGENT},
+    Proxy, StatusCode, Url,
+};
+use secp256k1::Secp256k1;
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::Deserialize;
+use serde_json::Value;
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::Resolver;
+use tungstenite::{client::AutoStream, stream::Stream, Message, WebSocket};
 
 use crate::asset::Asset;
-use crate::errors::Result;
-use crate::util::verify_domain_name;
+use crate::context::VerifyContext;
+use crate::errors::{AssetError, OptionExt, Result};
+use crate::util::{
+    decode_npub, domain_to_ascii, domain_to_unicode, verify_bitcoin_msg, verify_domain_name,
+    verify_github_user, verify_schnorr_sig, verify_twitter_handle,
+};
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub const PROOF_FILE_PREFIX: &str = "liquid-asset-proof-";
+
+// Where domain proof pages are hosted, followed by the asset id. Deployments running a parallel
+// registry (like for a testnet) may configure a different one, see VerifyContext::proof_path_prefix
+pub const DEFAULT_PROOF_PATH_PREFIX: &str = "/.well-known/liquid-asset-proof-";
+
+// tolerance for proof timestamps that are slightly in the future
+const PROOF_MAX_CLOCK_SKEW: u64 = 300;
+
+lazy_static! {
+    static ref EC: Secp256k1<secp256k1::VerifyOnly> = Secp256k1::verification_only();
+}
+
+// Serialized as `{"<entity type>": <payload>}`. Each type is verified by the `EntityVerifier`
+// registered for it in the `VerifyContext`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
 pub enum AssetEntity {
     #[serde(rename = "domain")]
     DomainName(String),
+
+    // A domain name verified through a DNS TXT record only, for setups that can't serve the
+    // proof page (see verify_domain_txt)
+    #[serde(rename = "domain_txt")]
+    DomainTxt(String),
+
+    // A twitter handle (without the @), verified through its profile bio or pinned tweet
+    #[serde(rename = "twitter")]
+    Twitter(String),
+
+    // A github user, verified through a public gist with a `liquid-asset-proof-<asset_id>` file
+    #[serde(rename = "github")]
+    GitHub(String),
+
+    // A nostr public key (as a bech32 npub), verified through a signed note published to the
+    // configured relays
+    #[serde(rename = "nostr")]
+    Nostr(String),
+
+    // An operator-defined entity type, verified by the `CustomVerifier` registered for its `kind`
+    // in the `VerifyContext` (see EntityVerifiers::register_custom)
+    #[serde(rename = "custom")]
+    Custom { kind: String, value: String },
+}
+
+impl fmt::Display for AssetEntity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // the same entity as DomainName, only verified differently
+            AssetEntity::DomainName(domain) | AssetEntity::DomainTxt(domain) => {
+                write!(f, "domain:{}", domain)
+            }
+            AssetEntity::Twitter(handle) => write!(f, "twitter:{}", handle),
+            AssetEntity::GitHub(user) => write!(f, "github:{}", user),
+            AssetEntity::Nostr(npub) => write!(f, "nostr:{}", npub),
+            AssetEntity::Custom { kind, value } => write!(f, "custom:{}:{}", kind, value),
+        }
+    }
+}
+
+impl AssetEntity {
+    // The entity type, as used for its serialization tag
+    pub fn kind(&self) -> &str {
+        match self {
+            AssetEntity::DomainName(_) => "domain",
+            AssetEntity::DomainTxt(_) => "domain_txt",
+            AssetEntity::Twitter(_) => "twitter",
+            AssetEntity::GitHub(_) => "github",
+            AssetEntity::Nostr(_) => "nostr",
+            AssetEntity::Custom { .. } => "custom",
+        }
+    }
+
+    // The key of the entity's verifier in `VerifyContext::entity_verifiers`. Custom entities are
+    // verified per `kind`, registered as `custom:<kind>`.
+    pub fn verifier_kind(&self) -> String {
+        match self {
+            AssetEntity::Custom { kind, .. } => format!("custom:{}", kind),
+            _ => self.kind().to_string(),
+        }
+    }
+
+    // The domain, handle or user name identifying the entity
+    pub fn value(&self) -> &str {
+        match self {
+            AssetEntity::DomainName(value)
+            | AssetEntity::DomainTxt(value)
+            | AssetEntity::Twitter(value)
+            | AssetEntity::GitHub(value)
+            | AssetEntity::Nostr(value)
+            | AssetEntity::Custom { value, .. } => value,
+        }
+    }
+
+    // Whether both refer to the same entity, regardless of how it is verified (a DomainName and a
+    // DomainTxt for the same domain are the same entity)
+    pub fn same_entity(&self, other: &AssetEntity) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl<'de> Deserialize<'de> for AssetEntity {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct CustomEntity {
+            kind: String,
+            value: String,
+        }
+
+        fn payload<T: DeserializeOwned, E: de::Error>(payload: Value) -> StdResult<T, E> {
+            serde_json::from_value(payload).map_err(E::custom)
+        }
+
+        let entity = HashMap::<String, Value>::deserialize(deserializer)?;
+        if entity.len() != 1 {
+            return Err(de::Error::custom("entity must have exactly one type"));
+        }
+        let (kind, value) = entity.into_iter().next().unwrap();
+
+        Ok(match kind.as_str() {
+            "domain" => AssetEntity::DomainName(payload(value)?),
+            "domain_txt" => AssetEntity::DomainTxt(payload(value)?),
+            "twitter" => AssetEntity::Twitter(payload(value)?),
+            "github" => AssetEntity::GitHub(payload(value)?),
+            "nostr" => AssetEntity::Nostr(payload(value)?),
+            "custom" => {
+                let custom: CustomEntity = payload(value)?;
+                AssetEntity::Custom {
+                    kind: custom.kind,
+                    value: custom.value,
+                }
+            }
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "unsupported entity type `{}`",
+                    kind
+                )))
+            }
+        })
+    }
+}
+
+// Verifies the link between an asset and its entity. Implementations are registered in the
+// `VerifyContext` for the entity type they verify, replacing the built-in ones below.
+pub trait EntityVerifier: Send + Sync {
+    fn verify(&self, asset: &Asset, ctx: &VerifyContext) -> Result<()>;
+
+    // Like verify(), but also returning the fetched proof as evidence of the proof-of-control,
+    // for verifiers that have one
+    fn verify_capturing(&self, asset: &Asset, ctx: &VerifyContext) -> Result<Option<LinkProof>> {
+        self.verify(asset, ctx).map(|_| None)
+    }
+}
+
+pub struct DomainVerifier;
+pub struct DomainTxtVerifier;
+pub struct TwitterVerifier;
+pub struct GitHubVerifier;
+pub struct NostrVerifier;
+
+// Verifies the custom entities of one `kind` using an operator-provided function, given the
+// entity's `value`. Registered with EntityVerifiers::register_custom().
+pub struct CustomVerifier<F>(pub F);
+
+impl EntityVerifier for DomainVerifier {
+    fn verify(&self, asset: &Asset, ctx: &VerifyContext) -> Result<()> {
+        self.verify_capturing(asset, ctx).map(|_| ())
+    }
+    fn verify_capturing(&self, asset: &Asset, ctx: &VerifyContext) -> Result<Option<LinkProof>> {
+        verify_domain_link(asset, asset.entity().value(), ctx).map(Some)
+    }
+}
+
+impl EntityVerifier for DomainTxtVerifier {
+    fn verify(&self, asset: &Asset, ctx: &VerifyContext) -> Result<()> {
+        self.verify_capturing(asset, ctx).map(|_| ())
+    }
+    fn verify_capturing(&self, asset: &Asset, _ctx: &VerifyContext) -> Result<Option<LinkProof>> {
+        verify_domain_txt(asset, asset.entity().value()).map(Some)
+    }
+}
+
+impl EntityVerifier for TwitterVerifier {
+    fn verify(&self, asset: &Asset, ctx: &VerifyContext) -> Result<()> {
+        verify_twitter_link(asset, asset.entity().value(), ctx)
+    }
+}
+
+impl EntityVerifier for GitHubVerifier {
+    fn verify(&self, asset: &Asset, ctx: &VerifyContext) -> Result<()> {
+        self.verify_capturing(asset, ctx).map(|_| ())
+    }
+    fn verify_capturing(&self, asset: &Asset, ctx: &VerifyContext) -> Result<Option<LinkProof>> {
+        verify_github_link(asset, asset.entity().value(), ctx).map(Some)
+    }
+}
+
+impl EntityVerifier for NostrVerifier {
+    fn verify(&self, asset: &Asset, ctx: &VerifyContext) -> Result<()> {
+        verify_nostr_link(asset, asset.entity().value(), ctx)
+    }
+}
+
+impl<F> EntityVerifier for CustomVerifier<F>
+where
+    F: Fn(&Asset, &str) -> Result<()> + Send + Sync,
+{
+    fn verify(&self, asset: &Asset, _ctx: &VerifyContext) -> Result<()> {
+        let (kind, value) = match asset.entity() {
+            AssetEntity::Custom { kind, value } => (kind, value),
+            _ => bail!("not a custom entity"),
+        };
+
+        (self.0)(asset, value).context(format!("custom {} entity verification failed", kind))?;
+
+        debug!(
+            "verified custom {} entity link {} for {}",
+            kind,
+            value,
+            asset.id().to_hex()
+        );
+
+        Ok(())
+    }
+}
+
+// The proof page served for a verified domain link, as evidence of the proof-of-control
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LinkProof {
+    pub url: String,
+    pub body: String,
+    // unix timestamp in seconds
+    pub fetched_at: u64,
+}
+
+pub fn verify_asset_link(asset: &Asset, ctx: &VerifyContext) -> Result<()> {
+    verify_asset_link_capturing(asset, ctx).map(|_| ())
+}
+
+// Like verify_asset_link(), but also returns the fetched proof for domain entities
+// Domain links that were verified recently according to the context's link cache are not
+// fetched again, in which case no proof is returned.
+pub fn verify_asset_link_capturing(
+    asset: &Asset,
+    ctx: &VerifyContext,
+) -> Result<Option<LinkProof>> {
+    check_domain_blocklist(asset, ctx)?;
+    if is_link_cached(asset, ctx) {
+        return Ok(None);
+    }
+
+    let verifier = ctx
+        .entity_verifiers
+        .get(&asset.entity().verifier_kind())
+        .or_err(match asset.entity() {
+            AssetEntity::Custom { kind, .. } => {
+                format!("unsupported custom entity kind `{}`", kind)
+            }
+            entity => format!("unsupported entity type `{}`", entity.kind()),
+        })?;
+    let proof = verifier.verify_capturing(asset, ctx)?;
+
+    cache_link(asset, ctx);
+    Ok(proof)
+}
+
+// Checked before anything is fetched, including for cached links (the domain may have been
+// blocked since)
+fn check_domain_blocklist(asset: &Asset, ctx: &VerifyContext) -> Result<()> {
+    if let (Some(blocklist), Some(domain)) = (&ctx.domain_blocklist, link_domain(asset)) {
+        if blocklist.is_blocked(domain) {
+            warn!(
+                "rejected asset {} of blocked domain {}",
+                asset.id().to_hex(),
+                domain
+            );
+            return Err(AssetError::DomainNotPermitted.into());
+        }
+    }
+    Ok(())
+}
+
+// The domain of domain links, which are the ones subject to the link cache and blocklist
+fn link_domain(asset: &Asset) -> Option<&str> {
+    match asset.entity() {
+        AssetEntity::DomainName(domain) | AssetEntity::DomainTxt(domain) => Some(domain),
+        _ => None,
+    }
+}
+
+fn is_link_cached(asset: &Asset, ctx: &VerifyContext) -> bool {
+    match (&ctx.link_cache, link_domain(asset)) {
+        (Some(cache), Some(domain)) if cache.is_fresh(asset.id(), domain) => {
+            debug!(
+                "domain link {} for {} was verified recently, skipping",
+                domain,
+                asset.id().to_hex()
+            );
+            true
+        }
+        _ => false,
+    }
+}
+
+fn cache_link(asset: &Asset, ctx: &VerifyContext) {
+    if let (Some(cache), Some(domain)) = (&ctx.link_cache, link_domain(asset)) {
+        cache.record(asset.id(), domain);
+    }
+}
+
+fn verify_domain_link(asset: &Asset, domain: &str, ctx: &VerifyContext) -> Result<LinkProof> {
+    enter_span!("verify_domain_link", asset_id = %asset.asset_id, domain);
+    let (mut attempts, hosts) = DomainProofAttempts::new(asset, domain, ctx)?;
+    for host in &hosts {
+        attempts.start(host, ctx);
+        match verify_domain_proof_page(host, asset, &attempts.expected_body, ctx) {
+            Ok(proof) => {
+                attempts.verified(host);
+                return Ok(proof);
+            }
+            Err(err) => {
+                if !attempts.failed(host, err) {
+                    break;
+                }
+            }
+        }
+    }
+
+    // the proof page takes precedence, the txt record is only checked if it fails
+    if ctx.dns_txt_fallback {
+        match verify_domain_txt(asset, domain) {
+            Ok(proof) => return Ok(proof),
+            Err(err) => debug!("dns txt proof for {} failed: {:?}", domain, err),
+        }
+    }
+
+    Err(attempts.into_error())
+}
+
+// The proof page fetches for verifying a domain link, shared by the blocking and async
+// verification. The hosts are tried in order (see domain_proof_hosts()), moving on to the next
+// one only when the page is missing.
+struct DomainProofAttempts<'a> {
+    domain: &'a str,
+    asset_id: String,
+    expected_body: String,
+    // the public urls of the proof pages attempted so far, and why they failed
+    tried: Vec<String>,
+    errors: Vec<failure::Error>,
+}
+
+impl<'a> DomainProofAttempts<'a> {
+    // Along with the hosts to try
+    fn new(asset: &Asset, domain: &'a str, ctx: &VerifyContext) -> Result<(Self, Vec<String>)> {
+        verify_domain_name(domain).context("invalid domain name")?;
+
+        let asset_id = asset.id().to_hex();
+        let attempts = DomainProofAttempts {
+            domain,
+            expected_body: format_domain_proof(domain, &asset_id),
+            asset_id,
+            tried: vec![],
+            errors: vec![],
+        };
+        Ok((attempts, domain_proof_hosts(domain, ctx)))
+    }
+
+    fn start(&mut self, host: &str, ctx: &VerifyContext) {
+        self.tried
+            .push(proof_page_public_url(host, &self.asset_id, ctx));
+    }
+
+    fn verified(&self, host: &str) {
+        debug!(
+            "verified domain link {} for {} via {}",
+            domain_to_unicode(self.domain),
+            self.asset_id,
+            host
+        );
+    }
+
+    // Returns whether to try the next host, which is only done when the page is missing
+    fn failed(&mut self, host: &str, err: failure::Error) -> bool {
+        debug!("domain proof on {} failed: {:?}", host, err);
+        let missing = is_missing_page(&err);
+        self.errors.push(err);
+        missing
+    }
+
+    fn into_error(self) -> failure::Error {
+        domain_link_error(self.errors, &self.tried)
+    }
+}
+
+// The authorization message that proves the link, for each entity type. Shared with the proof
+// generation and the tests, as any mismatch silently fails verification.
+pub fn format_authorization(subject: &str, asset_id: &str) -> String {
+    format!(
+        "Authorize linking {} to the Liquid asset {}",
+        subject, asset_id
+    )
+}
+
+// The contents of the proof page expected by verify_domain_link()
+pub fn format_domain_proof(domain: &str, asset_id: &str) -> String {
+    format_authorization(&format!("the domain name {}", domain), asset_id)
+}
+
+pub fn format_twitter_proof(handle: &str, asset_id: &str) -> String {
+    format_authorization(&format!("@{}", handle), asset_id)
+}
+
+pub fn format_github_proof(user: &str, asset_id: &str) -> String {
+    format_authorization(&format!("the github user {}", user), asset_id)
+}
+
+pub fn format_nostr_proof(npub: &str, asset_id: &str) -> String {
+    format_authorization(npub, asset_id)
+}
+
+// The proof is always for the registered domain, but may optionally be hosted on its
+// www./apex counterpart. Internationalized domains are requested in their Punycode form.
+fn domain_proof_hosts(domain: &str, ctx: &VerifyContext) -> Vec<String> {
+    let domain = domain_to_ascii(domain).unwrap_or_else(|_| domain.to_string());
+    let mut hosts = vec![domain.clone()];
+    if ctx.www_equivalence {
+        hosts.extend(www_counterpart(&domain));
+    }
+    hosts
+}
+
+// Report the error for the registered domain itself, along with the urls attempted when the
+// counterpart was tried too
+fn domain_link_error(mut errors: Vec<failure::Error>, tried: &[String]) -> failure::Error {
+    let err = errors.remove(0);
+    if tried.len() > 1 {
+        let msg = format!("{} (tried {})", err, tried.join(", "));
+        err.context(msg).into()
+    } else {
+        err
+    }
+}
+
+// Whether the proof page fetch failed with a 404
+fn is_missing_page(err: &failure::Error) -> bool {
+    err.iter_chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .map_or(false, |err| err.status() == Some(StatusCode::NOT_FOUND))
+    })
+}
+
+fn verify_domain_proof_page(
+    host: &str,
+    asset: &Asset,
+    expected_body: &str,
+    ctx: &VerifyContext,
+) -> Result<LinkProof> {
+    let page_url = proof_page_fetch_url(host, asset, ctx);
+    let client = proof_page_client(host, ctx)?;
+    let fetch = || {
+        client
+            .get(&page_url)
+            // explicitly set for the hard-coded page used in testing and development modes
+            .header(HOST, host)
+            .send()?
+            .error_for_status()
+    };
+
+    let mut attempt = 1;
+    let resp = loop {
+        limit_fetch(host, ctx)?;
+        match fetch() {
+            Ok(resp) => break resp,
+            Err(err) if should_retry(&err, attempt, &page_url, ctx) => {
+                thread::sleep(ctx.fetch_retry.delay(attempt));
+                attempt += 1;
+            }
+            Err(err) => return Err(proof_page_error(err, &page_url, attempt)),
+        }
+    };
+    let body = resp.text().context("invalid page contents")?;
+
+    verified_proof_page(page_url, body, expected_body, asset, ctx)
+}
+
+// The url the host's proof page is fetched from, logged as it is being verified
+fn proof_page_fetch_url(host: &str, asset: &Asset, ctx: &VerifyContext) -> String {
+    let asset_id = &asset.id().to_hex();
+    let page_url = proof_page_url(host, asset_id, ctx);
+
+    debug!(
+        "verifying domain proof on {} for {}: GET {}",
+        host, asset_id, page_url
+    );
+    page_url
+}
+
+// Whether to retry the failed proof page fetch, after waiting the retry policy's delay
+fn should_retry(err: &reqwest::Error, attempt: u32, page_url: &str, ctx: &VerifyContext) -> bool {
+    if attempt < ctx.fetch_retry.attempts && is_transient(err) {
+        debug!("fetching {} failed, retrying: {:?}", page_url, err);
+        true
+    } else {
+        false
+    }
+}
+
+// Check the fetched proof page contents, returning it as the link's proof
+fn verified_proof_page(
+    page_url: String,
+    body: String,
+    expected_body: &str,
+    asset: &Asset,
+    ctx: &VerifyContext,
+) -> Result<LinkProof> {
+    check_proof_page(&body, expected_body, &asset.fields.issuer_pubkey, ctx)?;
+
+    Ok(LinkProof {
+        url: page_url,
+        body,
+        fetched_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    })
+}
+
+// Fail rather than wait when the host was fetched from too often, so that submissions can't be
+// used to flood third-party domains with requests
+fn limit_fetch(host: &str, ctx: &VerifyContext) -> Result<()> {
+    if let Some(limiter) = &ctx.fetch_limiter {
+        if let Err(retry_after) = limiter.acquire(host) {
+            let retry_after = retry_after.as_secs() + 1;
+            return Err(AssetError::FetchRateLimited(host.to_string(), retry_after).into());
+        }
+    }
+    Ok(())
+}
+
+// The name of the proof page on domains, and of the proof file in github gists
+pub fn proof_filename(asset_id: &str) -> String {
+    format!("{}{}", PROOF_FILE_PREFIX, asset_id)
+}
+
+// The path where the proof page must be hosted, under the given prefix (DEFAULT_PROOF_PATH_PREFIX
+// unless configured otherwise)
+pub fn proof_page_path(prefix: &str, asset_id: &str) -> String {
+    format!("{}{}", prefix, asset_id)
+}
+
+fn proof_page_url(host: &str, asset_id: &str, ctx: &VerifyContext) -> String {
+    if cfg!(any(test, feature = "dev")) {
+        // use a hard-coded verification page in testing and development modes
+        let path = proof_page_path(&ctx.proof_path_prefix, asset_id);
+        format!("http://127.0.0.1:58712{}", path)
+    } else {
+        proof_page_public_url(host, asset_id, ctx)
+    }
+}
+
+// The url the proof page is expected at, as reported to issuers
+fn proof_page_public_url(host: &str, asset_id: &str, ctx: &VerifyContext) -> String {
+    let path = proof_page_path(&ctx.proof_path_prefix, asset_id);
+    // require tls for non-onion hosts, assume http for onion ones
+    let protocol = if host.ends_with(".onion") {
+        "http"
+    } else {
+        "https"
+    };
+
+    format!("{}://{}{}", protocol, host, path)
+}
+
+// Verify the link using non-blocking requests. Domain proof pages are fetched asynchronously when
+// verified by the built-in `DomainVerifier`, while other verifiers (including ones replacing it)
+// and the dns txt fallback are run on the blocking thread pool.
+#[cfg(feature = "async")]
+pub async fn verify_asset_link_async(
+    asset: &Asset,
+    ctx: &VerifyContext,
+) -> Result<Option<LinkProof>> {
+    match asset.entity() {
+        AssetEntity::DomainName(domain) if ctx.entity_verifiers.has_builtin_domain() => {
+            check_domain_blocklist(asset, ctx)?;
+            if is_link_cached(asset, ctx) {
+                return Ok(None);
+            }
+            let proof = verify_domain_link_async(asset, domain, ctx).await?;
+            cache_link(asset, ctx);
+            Ok(Some(proof))
+        }
+        _ => {
+            let (asset, ctx) = (asset.clone(), ctx.clone());
+            tokio::task::spawn_blocking(move || verify_asset_link_capturing(&asset, &ctx)).await?
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+async fn verify_domain_link_async(
+    asset: &Asset,
+    domain: &str,
+    ctx: &VerifyContext,
+) -> Result<LinkProof> {
+    let (mut attempts, hosts) = DomainProofAttempts::new(asset, domain, ctx)?;
+    for host in &hosts {
+        attempts.start(host, ctx);
+        match verify_domain_proof_page_async(host, asset, &attempts.expected_body, ctx).await {
+            Ok(proof) => {
+                attempts.verified(host);
+                return Ok(proof);
+            }
+            Err(err) => {
+                if !attempts.failed(host, err) {
+                    break;
+                }
+            }
+        }
+    }
+
+    if ctx.dns_txt_fallback {
+        let (asset, domain_) = (asset.clone(), domain.to_string());
+        match tokio::task::spawn_blocking(move || verify_domain_txt(&asset, &domain_)).await? {
+            Ok(proof) => return Ok(proof),
+            Err(err) => debug!("dns txt proof for {} failed: {:?}", domain, err),
+        }
+    }
+
+    Err(attempts.into_error())
+}
+
+#[cfg(feature = "async")]
+async fn verify_domain_proof_page_async(
+    host: &str,
+    asset: &Asset,
+    expected_body: &str,
+    ctx: &VerifyContext,
+) -> Result<LinkProof> {
+    let page_url = proof_page_fetch_url(host, asset, ctx);
+    let client = proof_page_client_async(host, ctx)?;
+
+    let mut attempt = 1;
+    let resp = loop {
+        limit_fetch(host, ctx)?;
+        match fetch_proof_page_async(&client, &page_url, host).await {
+            Ok(resp) => break resp,
+            Err(err) if should_retry(&err, attempt, &page_url, ctx) => {
+                tokio::time::delay_for(ctx.fetch_retry.delay(attempt)).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(proof_page_error(err, &page_url, attempt)),
+        }
+    };
+    let body = resp.text().await.context("invalid page contents")?;
+
+    verified_proof_page(page_url, body, expected_body, asset, ctx)
+}
+
+#[cfg(feature = "async")]
+async fn fetch_proof_page_async(
+    client: &reqwest::Client,
+    page_url: &str,
+    host: &str,
+) -> StdResult<reqwest::Response, reqwest::Error> {
+    client
+        .get(page_url)
+        .header(HOST, host)
+        .send()
+        .await?
+        .error_for_status()
+}
+
+// Verify a `_liquid-asset-proof.<domain>` TXT record containing `asset_id=<asset_id>`
+fn verify_domain_txt(asset: &Asset, domain: &str) -> Result<LinkProof> {
+    verify_domain_name(domain).context("invalid domain name")?;
+
+    let asset_id = asset.id().to_hex();
+    let record_name = format!("_liquid-asset-proof.{}", domain_to_ascii(domain)?);
+    let expected_record = format!("asset_id={}", asset_id);
+
+    let resolver = if cfg!(any(test, feature = "dev")) {
+        // use a local mock resolver in testing and development modes
+        let nameservers =
+            NameServerConfigGroup::from_ips_clear(&[Ipv4Addr::LOCALHOST.into()], 58716);
+        let config = ResolverConfig::from_parts(None, vec![], nameservers);
+        Resolver::new(config, ResolverOpts::default())
+    } else {
+        Resolver::from_system_conf()
+    }
+    .context("failed initializing dns resolver")?;
+
+    debug!("verifying dns txt proof {} for {}", record_name, asset_id);
+
+    let records = resolver
+        .txt_lookup(record_name.as_str())
+        .context(format!("failed resolving {}", record_name))?;
+
+    // records may be split into multiple strings, which are concatenated
+    let body = records
+        .iter()
+        .map(|txt| {
+            txt.txt_data()
+                .iter()
+                .map(|data| String::from_utf8_lossy(data))
+                .collect::<String>()
+        })
+        .find(|record| record.trim() == expected_record)
+        .or_err("dns txt record mismatch")?;
+
+    debug!("verified dns txt link {} for {}", domain, asset_id);
+
+    Ok(LinkProof {
+        url: format!("dns:{}", record_name),
+        body,
+        fetched_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    })
+}
+
+#[derive(Deserialize)]
+struct TwitterUserResponse {
+    data: TwitterUser,
+    #[serde(default)]
+    includes: TwitterIncludes,
+}
+
+#[derive(Deserialize)]
+struct TwitterUser {
+    username: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Deserialize, Default)]
+struct TwitterIncludes {
+    #[serde(default)]
+    tweets: Vec<Tweet>,
+}
+
+#[derive(Deserialize)]
+struct Tweet {
+    text: String,
+}
+
+fn verify_twitter_link(asset: &Asset, handle: &str, ctx: &VerifyContext) -> Result<()> {
+    verify_twitter_handle(handle).context("invalid twitter handle")?;
+
+    let asset_id = asset.id().to_hex();
+
+    let expected_text = format_twitter_proof(handle, &asset_id);
+
+    let api_url = if cfg!(any(test, feature = "dev")) {
+        // use a mock api in testing and development modes
+        "http://127.0.0.1:58712"
+    } else {
+        "https://api.twitter.com"
+    };
+    let url = format!(
+        "{}/2/users/by/username/{}?user.fields=description,pinned_tweet_id&expansions=pinned_tweet_id&tweet.fields=text",
+        api_url, handle
+    );
+
+    let mut req = http_client(ctx)?.build()?.get(&url);
+    if let Some(token) = &ctx.twitter_bearer_token {
+        req = req.bearer_auth(token);
+    } else if !cfg!(any(test, feature = "dev")) {
+        bail!("twitter verification is not configured");
+    }
+
+    debug!("verifying twitter proof of @{} for {}", handle, asset_id);
+
+    let user: TwitterUserResponse = req
+        .send()
+        .map_err(|err| fetch_error(err, "twitter profile"))?
+        .error_for_status()?
+        .json()
+        .context("invalid twitter api response")?;
+
+    ensure!(
+        user.data.username.to_lowercase() == handle,
+        "twitter profile mismatch"
+    );
+
+    // the proof may be in the profile bio or in the pinned tweet
+    let contains_proof = |text: &str| {
+        if ctx.proof_case_insensitive {
+            text.to_lowercase().contains(&expected_text.to_lowercase())
+        } else {
+            text.contains(&expected_text)
+        }
+    };
+    ensure!(
+        contains_proof(&user.data.description)
+            || user
+                .includes
+                .tweets
+                .iter()
+                .any(|tweet| contains_proof(&tweet.text)),
+        "twitter proof not found in profile bio or pinned tweet"
+    );
+
+    debug!("verified twitter link @{} for {}", handle, asset_id);
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Gist {
+    files: HashMap<String, GistFile>,
+}
+
+#[derive(Deserialize)]
+struct GistFile {
+    raw_url: String,
+}
+
+fn verify_github_link(asset: &Asset, user: &str, ctx: &VerifyContext) -> Result<LinkProof> {
+    verify_github_user(user).context("invalid github user")?;
+
+    let asset_id = asset.id().to_hex();
+    let filename = proof_filename(&asset_id);
+
+    let expected_body = format_github_proof(user, &asset_id);
+
+    let (api_url, raw_url_prefix) = if cfg!(any(test, feature = "dev")) {
+        // use a mock api in testing and development modes
+        (
+            "http://127.0.0.1:58712/github-api".to_string(),
+            format!("http://127.0.0.1:58712/gist/{}/", user),
+        )
+    } else {
+        (
+            "https://api.github.com".to_string(),
+            format!("https://gist.githubusercontent.com/{}/", user),
+        )
+    };
+
+    debug!("verifying github proof of {} for {}", user, asset_id);
+
+    // the github api rejects requests without a user agent
+    let client = http_client(ctx)?.build()?;
+    let gists: Vec<Gist> = client
+        .get(&format!("{}/users/{}/gists?per_page=100", api_url, user))
+        .header(USER_AGENT, "liquid-asset-registry")
+        .send()
+        .map_err(|err| fetch_error(err, "github gists"))?
+        .error_for_status()?
+        .json()
+        .context("invalid github api response")?;
+
+    let page_url = gists
+        .into_iter()
+        .find_map(|mut gist| gist.files.remove(&filename))
+        .or_err(format!("no gist with a {} file found", filename))?
+        .raw_url;
+
+    // the gist must be owned by the user itself
+    ensure!(
+        page_url.starts_with(&raw_url_prefix),
+        "unexpected gist url {}",
+        page_url
+    );
+
+    // redirects are followed by reqwest, as served by gist.githubusercontent.com for some urls
+    let body = client
+        .get(&page_url)
+        .header(USER_AGENT, "liquid-asset-registry")
+        .send()
+        .map_err(|err| fetch_error(err, &page_url))?
+        .error_for_status()?
+        .text()
+        .context("invalid gist contents")?;
+
+    check_proof_body(&body, &expected_body, ctx)?;
+
+    debug!("verified github link {} for {}", user, asset_id);
+
+    Ok(LinkProof {
+        url: page_url,
+        body,
+        fetched_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    })
+}
+
+#[derive(Deserialize)]
+struct NostrEvent {
+    id: String,
+    pubkey: String,
+    created_at: u64,
+    kind: u64,
+    tags: Value,
+    content: String,
+    sig: String,
+}
+
+// Maximum number of recent notes requested from each relay
+const NOSTR_EVENTS_LIMIT: usize = 100;
+
+const NOSTR_SUBSCRIPTION_ID: &str = "liquid-asset-proof";
+
+fn verify_nostr_link(asset: &Asset, npub: &str, ctx: &VerifyContext) -> Result<()> {
+    let pubkey = decode_npub(npub).context("invalid nostr npub")?;
+
+    let asset_id = asset.id().to_hex();
+
+    let expected_text = format_nostr_proof(npub, &asset_id);
+
+    let relays = if cfg!(any(test, feature = "dev")) {
+        // use a mock relay in testing and development modes
+        vec!["ws://127.0.0.1:58717".to_string()]
+    } else {
+        ctx.nostr_relays.clone()
+    };
+    ensure!(!relays.is_empty(), "nostr verification is not configured");
+
+    debug!("verifying nostr proof of {} for {}", npub, asset_id);
+
+    // the proof only has to be found on one of the relays
+    for relay in &relays {
+        match find_nostr_proof(relay, &pubkey, &expected_text, ctx) {
+            Ok(true) => {
+                debug!("verified nostr link {} for {} on {}", npub, asset_id, relay);
+                return Ok(());
+            }
+            Ok(false) => debug!("nostr proof not found on {}", relay),
+            Err(err) => debug!("failed querying nostr relay {}: {}", relay, err),
+        }
+    }
+
+    bail!("nostr proof not found (tried {})", relays.join(", "))
+}
+
+// Query the relay for the recent notes of the pubkey, looking for a validly signed one that
+// contains the expected text
+fn find_nostr_proof(
+    relay: &str,
+    pubkey: &[u8; 32],
+    expected_text: &str,
+    ctx: &VerifyContext,
+) -> Result<bool> {
+    let mut socket = connect_relay(relay, ctx.http_timeout).context("failed connecting")?;
+
+    let req = json!([
+        "REQ",
+        NOSTR_SUBSCRIPTION_ID,
+        { "authors": [ pubkey.to_hex() ], "kinds": [ 1 ], "limit": NOSTR_EVENTS_LIMIT }
+    ]);
+    socket.write_message(Message::Text(req.to_string()))?;
+
+    let contains_proof = |text: &str| {
+        if ctx.proof_case_insensitive {
+            text.to_lowercase().contains(&expected_text.to_lowercase())
+        } else {
+            text.contains(expected_text)
+        }
+    };
+
+    let mut found = false;
+    loop {
+        let msg = match socket.read_message()? {
+            Message::Text(msg) => msg,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let msg: Vec<Value> = serde_json::from_str(&msg).context("invalid relay message")?;
+        match msg.get(0).and_then(Value::as_str) {
+            Some("EVENT") => {
+                let event = msg
+                    .get(2)
+                    .cloned()
+                    .map(serde_json::from_value::<NostrEvent>);
+                let event = match event {
+                    Some(Ok(event)) => event,
+                    _ => {
+                        debug!("invalid event from nostr relay {}", relay);
+                        continue;
+                    }
+                };
+                if !contains_proof(&event.content) {
+                    continue;
+                }
+                // relays are untrusted, forged events are skipped
+                match verify_nostr_event(&event, pubkey) {
+                    Ok(()) => {
+                        found = true;
+                        break;
+                    }
+                    Err(err) => warn!("invalid nostr event {} from {}: {}", event.id, relay, err),
+                }
+            }
+            Some("EOSE") | Some("CLOSED") => break,
+            Some("NOTICE") => debug!("notice from nostr relay {}: {:?}", relay, msg.get(1)),
+            _ => (),
+        }
+    }
+
+    socket.close(None).ok();
+    Ok(found)
 }
 
-impl fmt::Display for AssetEntity {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            AssetEntity::DomainName(domain) => write!(f, "domain:{}", domain),
+// Open a websocket to the relay, with the timeout applying to the tcp connection as well (which
+// tungstenite::connect() would wait on indefinitely) and to every read and write after it
+fn connect_relay(relay: &str, timeout: Duration) -> Result<WebSocket<AutoStream>> {
+    let url = Url::parse(relay).context("invalid relay url")?;
+    let host = url.host_str().or_err("missing relay host")?;
+    let port = url.port_or_known_default().or_err("missing relay port")?;
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .or_err("relay host not found")?;
+
+    let tcp = TcpStream::connect_timeout(&addr, timeout)?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_write_timeout(Some(timeout))?;
+
+    let stream = match url.scheme() {
+        "ws" => Stream::Plain(tcp),
+        "wss" => Stream::Tls(
+            native_tls::TlsConnector::new()?
+                .connect(host, tcp)
+                .map_err(|err| format_err!("tls handshake failed: {}", err))?,
+        ),
+        scheme => bail!("unsupported relay scheme {}", scheme),
+    };
+    let (socket, _) = tungstenite::client(url.as_str(), stream)
+        .map_err(|err| format_err!("websocket handshake failed: {}", err))?;
+    Ok(socket)
+}
+
+// Verify that the event is by the pubkey and that its id and signature are valid (NIP-01)
+fn verify_nostr_event(event: &NostrEvent, pubkey: &[u8; 32]) -> Result<()> {
+    ensure!(event.pubkey == pubkey.to_hex(), "event pubkey mismatch");
+
+    let serialized = json!([
+        0,
+        event.pubkey,
+        event.created_at,
+        event.kind,
+        event.tags,
+        event.content
+    ])
+    .to_string();
+    let id = sha256::Hash::hash(serialized.as_bytes()).into_inner();
+    ensure!(event.id == id.to_hex(), "event id mismatch");
+
+    let sig = hex::decode(&event.sig).context("invalid event signature")?;
+    verify_schnorr_sig(pubkey, &id, &sig)
+}
+
+// Apply the context's timeout and TLS options to a blocking or async reqwest client builder,
+// which have the same methods but no shared trait
+macro_rules! configure_http_client {
+    ($client:expr, $ctx:expr) => {{
+        let ctx: &VerifyContext = $ctx;
+        let mut client = $client
+            .connect_timeout(ctx.http_timeout)
+            .timeout(ctx.http_timeout);
+        if let Some(cert) = &ctx.tls_root_cert {
+            client = client.add_root_certificate(cert.clone());
+        }
+        if ctx.danger_accept_invalid_certs {
+            warn_invalid_certs();
+            client = client.danger_accept_invalid_certs(true);
         }
+        client
+    }};
+}
+
+fn proof_page_client(host: &str, ctx: &VerifyContext) -> Result<ReqClient> {
+    let mut client = http_client(ctx)?;
+    if let Some(proxy) = proof_page_proxy(host, ctx)? {
+        client = client.proxy(proxy);
     }
+    Ok(client.build()?)
 }
 
-pub fn verify_asset_link(asset: &Asset) -> Result<()> {
-    match asset.entity() {
-        AssetEntity::DomainName(domain) => verify_domain_link(asset, domain),
+#[cfg(feature = "async")]
+fn proof_page_client_async(host: &str, ctx: &VerifyContext) -> Result<reqwest::Client> {
+    let mut client = configure_http_client!(reqwest::Client::builder(), ctx);
+    if let Some(proxy) = proof_page_proxy(host, ctx)? {
+        client = client.proxy(proxy);
     }
+    Ok(client.build()?)
 }
 
-fn verify_domain_link(asset: &Asset, domain: &str) -> Result<()> {
-    verify_domain_name(domain).context("invalid domain name")?;
+// Onion hosts are accessed through the configured tor proxy, others directly (as is the
+// hard-coded page used in testing and development modes)
+fn proof_page_proxy(host: &str, ctx: &VerifyContext) -> Result<Option<Proxy>> {
+    if !host.ends_with(".onion") || cfg!(any(test, feature = "dev")) {
+        return Ok(None);
+    }
+    let tor_proxy = ctx
+        .tor_proxy
+        .as_ref()
+        .or_err("a tor proxy is required for verifying onion domains")?;
 
-    // TODO tor proxy for accessing onion
+    // socks5h, to have the proxy resolve the onion address
+    Ok(Some(Proxy::all(&format!("socks5h://{}", tor_proxy))?))
+}
 
-    let asset_id = asset.id().to_hex();
+pub(crate) fn http_client(ctx: &VerifyContext) -> Result<ClientBuilder> {
+    Ok(configure_http_client!(ReqClient::builder(), ctx))
+}
 
-    let expected_body = format!(
-        "Authorize linking the domain name {} to the Liquid asset {}",
-        domain, asset_id
-    );
+fn warn_invalid_certs() {
+    static WARNED: Once = Once::new();
+    WARNED.call_once(|| {
+        warn!("DANGER: accepting invalid TLS certificates, proofs fetched over https are NOT authenticated!")
+    });
+}
 
-    let page_url = if cfg!(any(test, feature = "dev")) {
-        // use a hard-coded verification page in testing and development modes
-        format!(
-            "http://127.0.0.1:58712/.well-known/liquid-asset-proof-{}",
-            asset_id
-        )
+// Timeouts are reported separately, to tell unresponsive hosts apart from other failures
+pub(crate) fn fetch_error(err: reqwest::Error, what: &str) -> failure::Error {
+    if err.is_timeout() {
+        failure::Error::from(err)
+            .context(format!("timed out fetching {}", what))
+            .into()
     } else {
-        // require tls for non-onion hosts, assume http for onion ones
-        let protocol = if domain.ends_with(".onion") {
-            "http"
-        } else {
-            "https"
-        };
+        failure::Error::from(err)
+            .context(format!("failed fetching {}", what))
+            .into()
+    }
+}
 
-        format!(
-            "{}://{}/.well-known/liquid-asset-proof-{}",
-            protocol, domain, asset_id
-        )
+// Network-level failures and server errors may succeed when retried, while other failures (like
+// a missing page) are deterministic
+fn is_transient(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status.is_server_error(),
+        None => err.is_timeout() || err.is_request(),
+    }
+}
+
+fn proof_page_error(err: reqwest::Error, page_url: &str, attempts: u32) -> failure::Error {
+    let err = if err.is_status() {
+        err.into()
+    } else {
+        fetch_error(err, page_url)
     };
+    if attempts > 1 {
+        err.context(format!("gave up after {} attempts", attempts))
+            .into()
+    } else {
+        err
+    }
+}
 
-    debug!(
-        "verifying domain name {} for {}: GET {}",
-        domain, asset_id, page_url
-    );
+// The www. subdomain of an apex domain, or the apex domain of a www. subdomain
+fn www_counterpart(domain: &str) -> Option<String> {
+    let counterpart = match domain.strip_prefix("www.") {
+        Some(apex) => apex.to_string(),
+        None => format!("www.{}", domain),
+    };
+    verify_domain_name(&counterpart).ok().map(|_| counterpart)
+}
 
-    let body = reqwest_get(&page_url)
-        .context(format!("failed fetching {}", page_url))?
-        .error_for_status()?
-        .text()
-        .context("invalid page contents")?;
+// Domain proof pages may have a second line with the issuer's base64-encoded signature over the
+// authorization, making the proof self-authenticating. The first line alone is checked against the
+// expected contents, and the signature is verified when present.
+fn check_proof_page(
+    body: &str,
+    expected_body: &str,
+    issuer_pubkey: &[u8],
+    ctx: &VerifyContext,
+) -> Result<()> {
+    let body = body.trim_end();
+    let (first_line, signature) = match body.find('\n') {
+        Some(pos) => (&body[..pos], Some(body[pos + 1..].trim())),
+        None => (body, None),
+    };
+    check_proof_body(first_line, expected_body, ctx)?;
 
-    ensure!(
-        body.trim_end() == expected_body,
-        "verification page contents mismatch"
-    );
+    if let Some(signature) = signature {
+        let signature = base64::decode(signature).context("invalid proof signature base64")?;
+        verify_bitcoin_msg(&EC, issuer_pubkey, &signature, expected_body)
+            .context("invalid proof signature")?;
+    }
+    Ok(())
+}
+
+fn check_proof_body(body: &str, expected_body: &str, ctx: &VerifyContext) -> Result<()> {
+    let (body, expected_body) = if ctx.proof_case_insensitive {
+        (body.trim_end().to_lowercase(), expected_body.to_lowercase())
+    } else {
+        (body.trim_end().to_string(), expected_body.to_string())
+    };
+
+    match ctx.proof_max_age {
+        None => ensure!(body == expected_body, "verification page contents mismatch"),
 
-    debug!("verified domain link {} for {}", domain, asset_id);
+        // require the body to be suffixed with " at <unix timestamp>"
+        Some(max_age) => {
+            let timestamp: u64 = body
+                .strip_prefix(&expected_body)
+                .and_then(|rest| rest.strip_prefix(" at "))
+                .and_then(|timestamp| timestamp.parse().ok())
+                .or_err("verification page contents mismatch")?;
 
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            ensure!(
+                timestamp <= now + PROOF_MAX_CLOCK_SKEW,
+                "proof timestamp is in the future"
+            );
+            ensure!(
+                !ctx.intake || now.saturating_sub(timestamp) <= max_age.as_secs(),
+                "proof timestamp is stale"
+            );
+        }
+    }
     Ok(())
 }
 
@@ -85,12 +1244,26 @@ fn verify_domain_link(asset: &Asset, domain: &str) -> Result<()> {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::util::BoolOpt;
+    use crate::context::{load_root_cert, DomainBlocklist, FetchLimiter, LinkCache, RetryPolicy};
+    use crate::errors::asset_error;
+    use crate::util::wait_for_port;
     use rocket as r;
-    use std::path::PathBuf;
+    use rocket::http::Status;
+    use rocket::request::{self, FromRequest, Request};
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream, UdpSocket};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Once;
+    use std::time::Duration;
 
     static SPAWN_ONCE: Once = Once::new();
+    static DNS_SPAWN_ONCE: Once = Once::new();
+    static NOSTR_SPAWN_ONCE: Once = Once::new();
+    static TLS_SPAWN_ONCE: Once = Once::new();
+
+    pub const TEST_NPUB: &str = "npub1a5dpktresju538f4gcelv3wj385z2pr8had7snvvx7yv0ttuhuzq8af424";
 
     // a server that identifies as "test.dev" and verifies any requested asset id
     pub fn spawn_mock_verifier_server() {
@@ -99,34 +1272,842 @@ pub mod tests {
                 .port(58712)
                 .finalize()
                 .unwrap();
-            let rocket = r::custom(config).mount("/", routes![verify_handler]);
+            let rocket = r::custom(config).mount(
+                "/",
+                routes![
+                    verify_handler,
+                    twitter_handler,
+                    github_gists_handler,
+                    gist_raw_handler,
+                    gist_content_handler,
+                    icon_handler,
+                    icon_redirect_handler
+                ],
+            );
 
             std::thread::spawn(|| rocket.launch());
-        })
+        });
+        wait_for_port(58712);
+    }
+
+    // a tls server for localhost with a certificate issued by test/private-ca.pem, replying to
+    // every request with an empty page
+    fn spawn_private_ca_server() {
+        TLS_SPAWN_ONCE.call_once(|| {
+            let der = fs::read("test/private-ca-localhost.p12").unwrap();
+            let identity = native_tls::Identity::from_pkcs12(&der, "test").unwrap();
+            let acceptor = native_tls::TlsAcceptor::new(identity).unwrap();
+            let listener = TcpListener::bind("127.0.0.1:58718").unwrap();
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // fails for clients that don't trust the certificate
+                    if let Ok(mut stream) = acceptor.accept(stream) {
+                        // read up to the end of the request headers
+                        let mut reader = BufReader::new(&mut stream);
+                        let mut line = String::new();
+                        while reader.read_line(&mut line).map_or(false, |n| n > 2) {
+                            line.clear();
+                        }
+                        let reply =
+                            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+                        stream.write_all(reply.as_bytes()).ok();
+                    }
+                }
+            });
+        });
+        wait_for_port(58718);
     }
 
+    // a dns server with a TXT proof for the b1405e asset on txt.dev
+    pub fn spawn_mock_dns_server() {
+        DNS_SPAWN_ONCE.call_once(|| {
+            let socket = UdpSocket::bind("127.0.0.1:58716").unwrap();
+            std::thread::spawn(move || {
+                let mut buf = [0u8; 512];
+                loop {
+                    let (len, src) = socket.recv_from(&mut buf).unwrap();
+                    if let Some(resp) = mock_dns_response(&buf[..len]) {
+                        socket.send_to(&resp, src).unwrap();
+                    }
+                }
+            });
+        });
+    }
+
+    // a nostr relay serving the events in test/nostr-events.ndjson: a note, a forged proof for
+    // the b1405e asset and the valid proof, all by TEST_NPUB
+    pub fn spawn_mock_nostr_relay() {
+        NOSTR_SPAWN_ONCE.call_once(|| {
+            let listener = TcpListener::bind("127.0.0.1:58717").unwrap();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = stream.unwrap();
+                    std::thread::spawn(move || serve_nostr_client(stream));
+                }
+            });
+        });
+        wait_for_port(58717);
+    }
+
+    fn serve_nostr_client(stream: TcpStream) {
+        let events = std::fs::read_to_string("test/nostr-events.ndjson").unwrap();
+        let mut socket = tungstenite::accept(stream).unwrap();
+
+        let req = match socket.read_message().unwrap() {
+            Message::Text(req) => req,
+            _ => return,
+        };
+        let req: Value = serde_json::from_str(&req).unwrap();
+        let sub_id = &req[1];
+        let authors = &req[2]["authors"];
+
+        for event in events.lines() {
+            let event: Value = serde_json::from_str(event).unwrap();
+            if authors.as_array().unwrap().contains(&event["pubkey"]) {
+                let msg = json!(["EVENT", sub_id, event]);
+                socket
+                    .write_message(Message::Text(msg.to_string()))
+                    .unwrap();
+            }
+        }
+        let eose = json!(["EOSE", sub_id]);
+        socket
+            .write_message(Message::Text(eose.to_string()))
+            .unwrap();
+        // wait for the client to close the connection
+        while socket.read_message().is_ok() {}
+    }
+
+    fn mock_dns_response(query: &[u8]) -> Option<Vec<u8>> {
+        // read the queried name, starting after the 12 bytes header
+        let mut labels = vec![];
+        let mut pos = 12;
+        loop {
+            let len = *query.get(pos)? as usize;
+            pos += 1;
+            if len == 0 {
+                break;
+            }
+            labels.push(String::from_utf8_lossy(query.get(pos..pos + len)?).to_lowercase());
+            pos += len;
+        }
+        let question = query.get(12..pos + 4)?;
+
+        let txt = match labels.join(".").as_str() {
+            "_liquid-asset-proof.txt.dev" => {
+                Some("asset_id=b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05")
+            }
+            _ => None,
+        };
+
+        // id, flags (NOERROR or NXDOMAIN), one question and one or no answers
+        let mut resp = query[0..2].to_vec();
+        resp.extend(&[0x81, if txt.is_some() { 0x80 } else { 0x83 }]);
+        resp.extend(&[0, 1, 0, txt.is_some() as u8, 0, 0, 0, 0]);
+        resp.extend(question);
+        if let Some(txt) = txt {
+            // pointer to the question name, TXT, IN, ttl, and the rdata
+            resp.extend(&[0xc0, 12, 0, 16, 0, 1, 0, 0, 0, 60]);
+            resp.extend(&(txt.len() as u16 + 1).to_be_bytes());
+            resp.push(txt.len() as u8);
+            resp.extend(txt.as_bytes());
+        }
+        Some(resp)
+    }
+
+    struct Host(String);
+
+    impl<'a, 'r> FromRequest<'a, 'r> for Host {
+        type Error = ();
+        fn from_request(req: &'a Request<'r>) -> request::Outcome<Self, ()> {
+            let host = req.headers().get_one("Host").unwrap_or_default();
+            r::Outcome::Success(Host(host.to_string()))
+        }
+    }
+
+    static FLAKY_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+    // hosts the proofs for test.dev on test.dev, for wwwonly.dev on www.wwwonly.dev, for café.dev
+    // on its punycode host and for www.apexonly.dev on apexonly.dev. slow.dev takes 3 seconds to respond,
+    // flaky.dev only succeeds on every third request and fails with a 503 otherwise. broken.dev
+    // always fails with a 500 (with the proof on www.broken.dev). testnet.dev hosts its proofs
+    // under a testnet-specific prefix.
     #[get("/.well-known/<page>")]
-    fn verify_handler(page: String) -> Option<String> {
-        page.starts_with("liquid-asset-proof-")
-            .as_option()
-            .map(|_| {
-                format!(
-                    "Authorize linking the domain name test.dev to the Liquid asset {}",
-                    &page[19..]
-                )
+    fn verify_handler(page: String, host: Host) -> Option<Result<String, Status>> {
+        let domain = match host.0.as_str() {
+            "test.dev" => "test.dev",
+            "slow.dev" => {
+                std::thread::sleep(Duration::from_secs(3));
+                "slow.dev"
+            }
+            "flaky.dev" => {
+                if FLAKY_REQUESTS.fetch_add(1, Ordering::SeqCst) % 3 != 2 {
+                    return Some(Err(Status::ServiceUnavailable));
+                }
+                "flaky.dev"
+            }
+            "www.wwwonly.dev" => "wwwonly.dev",
+            "xn--caf-dma.dev" => "café.dev",
+            "apexonly.dev" => "www.apexonly.dev",
+            "broken.dev" => return Some(Err(Status::InternalServerError)),
+            "www.broken.dev" => "broken.dev",
+            "testnet.dev" => {
+                return page
+                    .strip_prefix("testnet-liquid-asset-proof-")
+                    .map(|asset_id| Ok(format_domain_proof("testnet.dev", asset_id)))
+            }
+            _ => return None,
+        };
+        page.strip_prefix(PROOF_FILE_PREFIX)
+            .map(|asset_id| Ok(format_domain_proof(domain, asset_id)))
+    }
+
+    // icons for the icon verification tests
+    #[get("/icons/<name>")]
+    fn icon_handler(name: String) -> Option<Vec<u8>> {
+        use crate::icon::tests::{test_png, TEST_SVG};
+        match name.as_str() {
+            "icon.png" => Some(test_png(false)),
+            "icon.svg" => Some(TEST_SVG.as_bytes().to_vec()),
+            "animated.png" => Some(test_png(true)),
+            "huge.png" => {
+                let mut png = test_png(false);
+                png.resize(crate::icon::ICON_MAX_SIZE + 1, 0);
+                Some(png)
+            }
+            _ => None,
+        }
+    }
+
+    // redirects to the icons over plain http (as served in testing)
+    #[get("/icon-redirect/<name>")]
+    fn icon_redirect_handler(name: String) -> r::response::Redirect {
+        r::response::Redirect::to(uri!(icon_handler: name))
+    }
+
+    // a twitter api where @testdev pins a proof for the b1405e asset, and @nobody has none
+    #[get("/2/users/by/username/<handle>")]
+    fn twitter_handler(handle: String) -> Option<String> {
+        let pinned_text = match handle.as_str() {
+            "testdev" => format_twitter_proof(
+                "testdev",
+                "b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05",
+            ),
+            "nobody" => "just setting up my twttr".to_string(),
+            _ => return None,
+        };
+        Some(
+            json!({
+                "data": { "id": "1", "username": handle, "description": "", "pinned_tweet_id": "2" },
+                "includes": { "tweets": [ { "id": "2", "text": pinned_text } ] },
             })
+            .to_string(),
+        )
+    }
+
+    // a github api where the testdev user has a gist with a proof for the b1405e asset
+    #[get("/github-api/users/<user>/gists")]
+    fn github_gists_handler(user: String) -> Option<String> {
+        let asset_id = "b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05";
+        let filename = proof_filename(asset_id);
+        let gists = match user.as_str() {
+            "testdev" => json!([
+                { "files": { "notes.txt": { "raw_url": "http://127.0.0.1:58712/gist/testdev/a1/raw/notes.txt" } } },
+                { "files": { &filename: { "raw_url": format!("http://127.0.0.1:58712/gist/testdev/b2/raw/{}", filename) } } },
+            ]),
+            // links to a gist of another user
+            "imposter" => json!([
+                { "files": { &filename: { "raw_url": format!("http://127.0.0.1:58712/gist/testdev/b2/raw/{}", filename) } } },
+            ]),
+            _ => return None,
+        };
+        Some(gists.to_string())
+    }
+
+    // redirects like gist raw urls do
+    #[get("/gist/<user>/<_gist_id>/raw/<file>")]
+    fn gist_raw_handler(user: String, _gist_id: String, file: String) -> r::response::Redirect {
+        r::response::Redirect::to(uri!(gist_content_handler: user, file))
+    }
+
+    #[get("/gist-content/<user>/<file>")]
+    fn gist_content_handler(user: String, file: String) -> Option<String> {
+        file.strip_prefix(PROOF_FILE_PREFIX)
+            .map(|asset_id| format_github_proof(&user, asset_id))
     }
 
     #[test]
     fn test0_init() {
         stderrlog::new().verbosity(3).init().ok();
         spawn_mock_verifier_server();
+        spawn_mock_dns_server();
     }
 
     #[test]
     fn test1_verify_domain_link() {
         let asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
         // expects https://test.dev/ to forward requests to a local web server
-        verify_domain_link(&asset, "test.dev").expect("failed verifying domain name");
+        verify_domain_link(&asset, "test.dev", &VerifyContext::default())
+            .expect("failed verifying domain name");
+
+        // the served proof is returned as-is
+        let proof = verify_asset_link_capturing(&asset, &VerifyContext::default())
+            .unwrap()
+            .expect("missing domain proof");
+        assert_eq!(
+            proof.body,
+            format_domain_proof("test.dev", &asset.id().to_hex())
+        );
+    }
+
+    #[test]
+    fn test3_verify_idn_domain() {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let ctx = VerifyContext::default();
+
+        // requested on the punycode host, with the proof for the domain as registered
+        verify_domain_link(&asset, "café.dev", &ctx).expect("failed verifying idn domain");
+        assert!(verify_domain_link(&asset, "xn--caf-dma.dev", &ctx).is_err());
+    }
+
+    #[test]
+    fn test3_verify_with_retries() {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let retrying = VerifyContext {
+            fetch_retry: RetryPolicy::new(3, Duration::from_millis(10)),
+            ..Default::default()
+        };
+        FLAKY_REQUESTS.store(0, Ordering::SeqCst);
+
+        // fails on the first 503 without retries
+        let err = verify_domain_link(&asset, "flaky.dev", &VerifyContext::default()).unwrap_err();
+        assert!(err.to_string().contains("503"), "{}", err);
+
+        // succeeds on the third attempt
+        verify_domain_link(&asset, "flaky.dev", &retrying).expect("failed verifying with retries");
+        assert_eq!(FLAKY_REQUESTS.load(Ordering::SeqCst), 3);
+
+        // missing pages are not retried
+        let slow_retrying = VerifyContext {
+            fetch_retry: RetryPolicy::new(3, Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let started = std::time::Instant::now();
+        let err = verify_domain_link(&asset, "unknown.dev", &slow_retrying).unwrap_err();
+        assert!(err.to_string().contains("404"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // and mismatches are reported as such
+        let mismatching = VerifyContext {
+            proof_max_age: Some(Duration::from_secs(60)),
+            ..retrying
+        };
+        let err = verify_domain_link(&asset, "test.dev", &mismatching).unwrap_err();
+        assert_eq!(err.to_string(), "verification page contents mismatch");
+    }
+
+    #[test]
+    fn test3_verify_www_equivalence() {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let strict = VerifyContext::default();
+        let lenient = VerifyContext {
+            www_equivalence: true,
+            ..Default::default()
+        };
+
+        // apex domain with the proof hosted on www.
+        assert!(verify_domain_link(&asset, "wwwonly.dev", &strict).is_err());
+        verify_domain_link(&asset, "wwwonly.dev", &lenient).expect("www. should be accepted");
+
+        // www. domain with the proof hosted on the apex
+        assert!(verify_domain_link(&asset, "www.apexonly.dev", &strict).is_err());
+        verify_domain_link(&asset, "www.apexonly.dev", &lenient).expect("apex should be accepted");
+
+        // the proof must still be for the registered domain
+        assert!(verify_domain_link(&asset, "www.test.dev", &lenient).is_err());
+
+        // the error lists the urls that were attempted
+        let err = verify_domain_link(&asset, "missing.dev", &lenient).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("404"), "{}", msg);
+        assert!(
+            msg.contains(&format!(
+                "tried https://missing.dev/.well-known/liquid-asset-proof-{0}, https://www.missing.dev/.well-known/liquid-asset-proof-{0}",
+                asset.id().to_hex()
+            )),
+            "{}",
+            msg
+        );
+
+        // the counterpart is only tried when the page is missing, not on other failures (it
+        // would've verified otherwise)
+        let err = verify_domain_link(&asset, "broken.dev", &lenient).unwrap_err();
+        assert!(err.to_string().contains("500"), "{}", err);
+        assert!(!err.to_string().contains("tried"), "{}", err);
+
+        assert_eq!(www_counterpart("foo.com"), Some("www.foo.com".to_string()));
+        assert_eq!(www_counterpart("www.foo.com"), Some("foo.com".to_string()));
+        assert_eq!(www_counterpart("www.com"), None);
+    }
+
+    #[test]
+    fn test3_verify_proof_path_prefix() {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let testnet = VerifyContext {
+            proof_path_prefix: "/.well-known/testnet-liquid-asset-proof-".to_string(),
+            ..Default::default()
+        };
+
+        assert!(verify_domain_link(&asset, "testnet.dev", &VerifyContext::default()).is_err());
+        verify_domain_link(&asset, "testnet.dev", &testnet)
+            .expect("failed verifying with a custom prefix");
+        // mainnet proofs are not accepted under the custom prefix
+        assert!(verify_domain_link(&asset, "test.dev", &testnet).is_err());
+
+        assert_eq!(
+            proof_page_path(DEFAULT_PROOF_PATH_PREFIX, "b1405e"),
+            "/.well-known/liquid-asset-proof-b1405e"
+        );
+    }
+
+    #[test]
+    fn test4_verify_twitter_link() {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let ctx = VerifyContext::default();
+
+        asset.fields.entity = AssetEntity::Twitter("testdev".to_string());
+        verify_asset_link(&asset, &ctx).expect("failed verifying twitter link");
+        assert_eq!(asset.fields.entity.to_string(), "twitter:testdev");
+
+        asset.fields.entity = AssetEntity::Twitter("nobody".to_string());
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+
+        asset.fields.entity = AssetEntity::Twitter("unknown".to_string());
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+    }
+
+    #[test]
+    fn test4_verify_nostr_link() {
+        spawn_mock_nostr_relay();
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let ctx = VerifyContext::default();
+
+        asset.fields.entity = AssetEntity::Nostr(TEST_NPUB.to_string());
+        verify_asset_link(&asset, &ctx).expect("failed verifying nostr link");
+        assert_eq!(
+            asset.fields.entity.to_string(),
+            format!("nostr:{}", TEST_NPUB)
+        );
+
+        // a valid npub with no events on the relay
+        asset.fields.entity = AssetEntity::Nostr(
+            "npub10xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqpkge6d".to_string(),
+        );
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+
+        asset.fields.entity = AssetEntity::Nostr("npub1invalid".to_string());
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+
+        // the relay only has a (forged and a valid) proof for the b1405e asset
+        let mut other = Asset::load(PathBuf::from("test/asset-38dac0.json")).unwrap();
+        other.fields.entity = AssetEntity::Nostr(TEST_NPUB.to_string());
+        assert!(verify_asset_link(&other, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_verify_nostr_event() -> Result<()> {
+        let pubkey = decode_npub(TEST_NPUB)?;
+        let events = std::fs::read_to_string("test/nostr-events.ndjson")?;
+        let lines: Vec<&str> = events.lines().collect();
+        let events = lines
+            .iter()
+            .map(|line| serde_json::from_str(line))
+            .collect::<StdResult<Vec<NostrEvent>, _>>()?;
+
+        verify_nostr_event(&events[0], &pubkey)?;
+        let err = verify_nostr_event(&events[1], &pubkey).unwrap_err();
+        assert_eq!(err.to_string(), "signature verification failed");
+        verify_nostr_event(&events[2], &pubkey)?;
+
+        let mut tampered = serde_json::from_str::<NostrEvent>(lines[2])?;
+        tampered.content.push('!');
+        let err = verify_nostr_event(&tampered, &pubkey).unwrap_err();
+        assert_eq!(err.to_string(), "event id mismatch");
+
+        let other = decode_npub("npub10xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqpkge6d")?;
+        let err = verify_nostr_event(&events[2], &other).unwrap_err();
+        assert_eq!(err.to_string(), "event pubkey mismatch");
+        Ok(())
+    }
+
+    #[test]
+    fn test5_verify_github_link() {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let ctx = VerifyContext::default();
+
+        asset.fields.entity = AssetEntity::GitHub("testdev".to_string());
+        let proof = verify_asset_link_capturing(&asset, &ctx)
+            .expect("failed verifying github link")
+            .expect("missing github proof");
+        assert!(proof.url.contains("/gist/testdev/"));
+        assert_eq!(asset.fields.entity.to_string(), "github:testdev");
+
+        // gists of other users are not accepted
+        asset.fields.entity = AssetEntity::GitHub("imposter".to_string());
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+
+        asset.fields.entity = AssetEntity::GitHub("unknown".to_string());
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+    }
+
+    #[test]
+    fn test6_verify_domain_txt() {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let strict = VerifyContext::default();
+        let fallback = VerifyContext {
+            dns_txt_fallback: true,
+            ..Default::default()
+        };
+
+        asset.fields.entity = AssetEntity::DomainTxt("txt.dev".to_string());
+        let proof = verify_asset_link_capturing(&asset, &strict)
+            .expect("failed verifying dns txt link")
+            .expect("missing dns txt proof");
+        assert_eq!(proof.url, "dns:_liquid-asset-proof.txt.dev");
+        assert_eq!(asset.fields.entity.to_string(), "domain:txt.dev");
+
+        // txt.dev has no proof page, the txt record is only used as a fallback when enabled
+        asset.fields.entity = AssetEntity::DomainName("txt.dev".to_string());
+        assert!(verify_asset_link(&asset, &strict).is_err());
+        verify_asset_link(&asset, &fallback).expect("txt fallback should be accepted");
+
+        // test.dev has a proof page but no txt record
+        asset.fields.entity = AssetEntity::DomainTxt("test.dev".to_string());
+        assert!(verify_asset_link(&asset, &strict).is_err());
+    }
+
+    #[test]
+    fn test7_proof_fetch_timeout() {
+        let asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let ctx = VerifyContext {
+            http_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+
+        let err = verify_domain_link(&asset, "slow.dev", &ctx).unwrap_err();
+        assert!(err.to_string().starts_with("timed out fetching"));
+
+        let ctx = VerifyContext {
+            http_timeout: Duration::from_secs(5),
+            ..Default::default()
+        };
+        verify_domain_link(&asset, "slow.dev", &ctx).expect("failed verifying slow domain");
+    }
+
+    #[test]
+    fn test8_link_cache() {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let ctx = VerifyContext {
+            link_cache: Some(LinkCache::new(Duration::from_secs(3600))),
+            ..Default::default()
+        };
+        let cache = ctx.link_cache.as_ref().unwrap();
+
+        // verified links are recorded, and not fetched again while fresh
+        assert!(verify_asset_link_capturing(&asset, &ctx).unwrap().is_some());
+        assert!(cache.is_fresh(asset.id(), "test.dev"));
+        assert!(verify_asset_link_capturing(&asset, &ctx).unwrap().is_none());
+
+        cache.invalidate(asset.id());
+        assert!(!cache.is_fresh(asset.id(), "test.dev"));
+        assert!(verify_asset_link_capturing(&asset, &ctx).unwrap().is_some());
+
+        // failures are not cached, and cached records are per domain
+        asset.fields.entity = AssetEntity::DomainName("wwwonly.dev".to_string());
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+        assert!(!cache.is_fresh(asset.id(), "wwwonly.dev"));
+
+        // expired records are fetched again
+        let ctx = VerifyContext {
+            link_cache: Some(LinkCache::new(Duration::from_secs(0))),
+            ..Default::default()
+        };
+        asset.fields.entity = AssetEntity::DomainName("test.dev".to_string());
+        verify_asset_link(&asset, &ctx).unwrap();
+        assert!(!ctx
+            .link_cache
+            .as_ref()
+            .unwrap()
+            .is_fresh(asset.id(), "test.dev"));
+    }
+
+    #[test]
+    fn test8_fetch_limiter() {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        let ctx = VerifyContext {
+            fetch_limiter: Some(FetchLimiter::new(2)),
+            ..Default::default()
+        };
+
+        verify_asset_link(&asset, &ctx).unwrap();
+        verify_asset_link(&asset, &ctx).unwrap();
+        let err = verify_asset_link(&asset, &ctx).unwrap_err();
+        assert_eq!(
+            asset_error(&err),
+            Some(&AssetError::FetchRateLimited("test.dev".to_string(), 30))
+        );
+
+        // limits are per host and shared by clones
+        asset.fields.entity = AssetEntity::DomainName("café.dev".to_string());
+        verify_asset_link(&asset, &ctx.clone()).unwrap();
+        let limiter = ctx.fetch_limiter.as_ref().unwrap();
+        assert!(limiter.acquire("xn--caf-dma.dev").is_ok());
+        assert!(limiter.acquire("xn--caf-dma.dev").is_err());
+    }
+
+    #[test]
+    fn test8_domain_blocklist() -> Result<()> {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        let path = std::env::temp_dir().join(format!("domain-blocklist-{}", std::process::id()));
+        fs::write(
+            &path,
+            "# scams
+Scam.com
+*.xyz
+",
+        )?;
+
+        let blocklist = DomainBlocklist::load(&path)?;
+        assert!(blocklist.is_blocked("scam.com"));
+        assert!(blocklist.is_blocked("SCAM.com."));
+        assert!(blocklist.is_blocked("free.coins.scam.com"));
+        assert!(blocklist.is_blocked("anything.xyz"));
+        assert!(!blocklist.is_blocked("notscam.com"));
+        assert!(!blocklist.is_blocked("test.dev"));
+
+        let ctx = VerifyContext {
+            domain_blocklist: Some(blocklist.clone()),
+            link_cache: Some(LinkCache::new(Duration::from_secs(3600))),
+            ..Default::default()
+        };
+        verify_asset_link(&asset, &ctx)?;
+
+        // rejected before fetching, the proof would not be found
+        asset.fields.entity = AssetEntity::DomainName("coins.scam.com".to_string());
+        let err = verify_asset_link(&asset, &ctx).unwrap_err();
+        assert_eq!(asset_error(&err), Some(&AssetError::DomainNotPermitted));
+
+        // modifications are picked up without reloading, including for cached links
+        fs::write(
+            &path,
+            "scam.com
+*.xyz
+test.dev
+",
+        )?;
+        asset.fields.entity = AssetEntity::DomainName("test.dev".to_string());
+        let err = verify_asset_link(&asset, &ctx).unwrap_err();
+        assert_eq!(asset_error(&err), Some(&AssetError::DomainNotPermitted));
+
+        // invalid modifications keep the previous list
+        fs::write(&path, "not a domain\n")?;
+        assert!(blocklist.is_blocked("test.dev"));
+        assert!(blocklist.is_blocked("scam.com"));
+
+        fs::remove_file(&path)?;
+        assert!(DomainBlocklist::load(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test3_verify_tls_options() -> Result<()> {
+        spawn_private_ca_server();
+        let fetch = |ctx: &VerifyContext| -> Result<()> {
+            http_client(ctx)?
+                .build()?
+                .get("https://localhost:58718/")
+                .send()?
+                .error_for_status()?;
+            Ok(())
+        };
+
+        // not trusted by the system roots
+        assert!(fetch(&VerifyContext::default()).is_err());
+
+        let private_ca = VerifyContext {
+            tls_root_cert: Some(load_root_cert(Path::new("test/private-ca.pem"))?),
+            ..Default::default()
+        };
+        fetch(&private_ca)?;
+
+        let err = load_root_cert(Path::new("test/asset-b1405e.json")).unwrap_err();
+        assert_eq!(err.to_string(), "invalid root certificate");
+
+        let insecure = VerifyContext {
+            danger_accept_invalid_certs: true,
+            ..Default::default()
+        };
+        fetch(&insecure)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_body_case() {
+        let expected = &format_domain_proof("test.dev", "b1405e");
+        let title_cased = "Authorize Linking The Domain Name Test.dev To The Liquid Asset B1405e";
+
+        let ctx = VerifyContext::default();
+        assert!(check_proof_body(expected, expected, &ctx).is_ok());
+        assert!(check_proof_body(title_cased, expected, &ctx).is_err());
+
+        let ctx = VerifyContext {
+            proof_case_insensitive: true,
+            ..Default::default()
+        };
+        assert!(check_proof_body(title_cased, expected, &ctx).is_ok());
+        assert!(check_proof_body("Authorize Linking", expected, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_proof_page_signature() {
+        use bitcoin::util::misc::signed_msg_hash;
+
+        let expected = &format_domain_proof("test.dev", "b1405e");
+        let ec = Secp256k1::signing_only();
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&ec, &key).serialize();
+        let other_pubkey = secp256k1::PublicKey::from_secret_key(
+            &ec,
+            &secp256k1::SecretKey::from_slice(&[2; 32]).unwrap(),
+        )
+        .serialize();
+        let sign = |msg: &str| {
+            let msg = secp256k1::Message::from_slice(&signed_msg_hash(msg).into_inner()).unwrap();
+            base64::encode(&ec.sign(&msg, &key).serialize_compact()[..])
+        };
+        let ctx = VerifyContext::default();
+
+        // unsigned proofs are still accepted
+        assert!(check_proof_page(&format!("{}\n", expected), expected, &pubkey, &ctx).is_ok());
+
+        let signed = format!("{}\n{}\n", expected, sign(expected));
+        assert!(check_proof_page(&signed, expected, &pubkey, &ctx).is_ok());
+        assert!(check_proof_page(&signed, expected, &other_pubkey, &ctx).is_err());
+
+        // the signature must be over the authorization itself, and the first line must match
+        let signed_other = format!("{}\n{}", expected, sign("something else"));
+        assert!(check_proof_page(&signed_other, expected, &pubkey, &ctx).is_err());
+        let wrong_line = format!("{}.\n{}", expected, sign(expected));
+        assert!(check_proof_page(&wrong_line, expected, &pubkey, &ctx).is_err());
+        let garbage = format!("{}\nnot a signature", expected);
+        let err = check_proof_page(&garbage, expected, &pubkey, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "invalid proof signature base64");
+    }
+
+    #[test]
+    fn test_proof_body_freshness() {
+        let expected = &format_domain_proof("test.dev", "b1405e");
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let ctx = VerifyContext {
+            proof_max_age: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        };
+
+        let fresh = format!("{} at {}", expected, now - 60);
+        assert!(check_proof_body(&fresh, expected, &ctx).is_ok());
+
+        let stale = format!("{} at {}", expected, now - 7200);
+        let err = check_proof_body(&stale, expected, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "proof timestamp is stale");
+
+        // stored assets are re-verified regardless of their proof's age
+        let reverifying = VerifyContext {
+            intake: false,
+            ..ctx.clone()
+        };
+        assert!(check_proof_body(&stale, expected, &reverifying).is_ok());
+
+        let future = format!("{} at {}", expected, now + 3600);
+        let err = check_proof_body(&future, expected, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "proof timestamp is in the future");
+
+        // the timestamp is required when enabled, and rejected otherwise
+        assert!(check_proof_body(expected, expected, &ctx).is_err());
+        assert!(check_proof_body(&fresh, expected, &VerifyContext::default()).is_err());
+    }
+
+    #[test]
+    fn test2_verify_custom_link() {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        asset.fields.entity = AssetEntity::Custom {
+            kind: "employee-id".to_string(),
+            value: "E1234".to_string(),
+        };
+
+        let mut ctx = VerifyContext::default();
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+
+        ctx.entity_verifiers
+            .register_custom("employee-id", |_asset, value| {
+                ensure!(value == "E1234", "unknown employee");
+                Ok(())
+            });
+        verify_asset_link(&asset, &ctx).expect("failed verifying custom entity");
+
+        asset.fields.entity = AssetEntity::Custom {
+            kind: "employee-id".to_string(),
+            value: "E9999".to_string(),
+        };
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+    }
+
+    #[test]
+    fn test2_entity_verifiers() -> Result<()> {
+        let entity: AssetEntity = serde_json::from_value(json!({ "twitter": "testdev" }))?;
+        assert_eq!(entity, AssetEntity::Twitter("testdev".to_string()));
+        assert_eq!(
+            serde_json::to_value(&entity)?,
+            json!({ "twitter": "testdev" })
+        );
+        let entity: AssetEntity =
+            serde_json::from_value(json!({ "custom": { "kind": "a", "value": "b" } }))?;
+        assert_eq!(entity.kind(), "custom");
+
+        let err =
+            serde_json::from_value::<AssetEntity>(json!({ "keybase": "testdev" })).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported entity type `keybase`");
+        assert!(serde_json::from_value::<AssetEntity>(json!({})).is_err());
+
+        struct AllowList(Vec<&'static str>);
+        impl EntityVerifier for AllowList {
+            fn verify(&self, asset: &Asset, _ctx: &VerifyContext) -> Result<()> {
+                ensure!(self.0.contains(&asset.entity().value()), "not allowed");
+                Ok(())
+            }
+        }
+
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json"))?;
+        asset.fields.entity = AssetEntity::GitHub("someone".to_string());
+        let mut ctx = VerifyContext::default();
+        ctx.entity_verifiers
+            .register("github", AllowList(vec!["someone"]));
+        verify_asset_link(&asset, &ctx)?;
+
+        asset.fields.entity = AssetEntity::GitHub("else".to_string());
+        assert!(verify_asset_link(&asset, &ctx).is_err());
+
+        ctx.entity_verifiers.remove("github");
+        let err = verify_asset_link(&asset, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported entity type `github`");
+
+        // replacing the domain verifier applies to the async verification too
+        assert!(ctx.entity_verifiers.has_builtin_domain());
+        ctx.entity_verifiers
+            .register("domain", AllowList(vec!["test.dev"]));
+        assert!(!ctx.entity_verifiers.has_builtin_domain());
+        Ok(())
     }
 }
@@ -1,6 +1,7 @@
 use std::result::Result as StdResult;
 
 use core::fmt::Display;
+use failure::Fail;
 pub use failure::{Context, Error, ResultExt};
 
 pub type Result<T> = StdResult<T, Error>;
@@ -32,3 +33,149 @@ pub fn join_err(err: &Error) -> String {
         .collect::<Vec<String>>()
         .join(": ")
 }
+
+// Kinds of failures that callers may want to tell apart, like to map them to http status codes.
+// They are usually wrapped with additional context (or wrap the underlying error as context
+// themselves), use asset_error() to find them.
+#[derive(Debug, Fail, PartialEq, Eq)]
+pub enum AssetError {
+    #[fail(display = "{}", _0)]
+    InvalidName(String),
+
+    #[fail(display = "invalid `ticker`")]
+    InvalidTicker,
+
+    // Any other invalid asset field
+    #[fail(display = "{}", _0)]
+    InvalidField(String),
+
+    // The policy asset and assets reserved by the operator
+    #[fail(display = "{}", _0)]
+    Reserved(String),
+
+    #[fail(display = "{}", _0)]
+    CommitmentMismatch(String),
+
+    #[fail(display = "issuance transaction not found")]
+    IssuanceNotFound,
+
+    #[fail(display = "issuance transaction unconfirmed")]
+    IssuanceUnconfirmed,
+
+    #[fail(display = "failed verifying on-chain issuance")]
+    IssuanceFailed,
+
+    // The chain backend could not be reached, did not respond in time (see
+    // ChainQuery::with_timeout()) or failed with a server error. Unlike the issuance not being
+    // found, these may succeed when retried.
+    #[fail(display = "chain backend unreachable")]
+    ChainUnreachable,
+
+    #[fail(display = "chain backend timed out")]
+    ChainTimeout,
+
+    #[fail(display = "chain backend failed with status {}", _0)]
+    ChainServerError(u16),
+
+    // The entity is not on the operator's allowlist, see VerifyContext::domain_allowlist
+    #[fail(display = "domain not permitted")]
+    DomainNotPermitted,
+
+    #[fail(display = "failed verifying linked entity")]
+    EntityLinkFailed,
+
+    #[fail(display = "failed verifying icon")]
+    InvalidIcon,
+
+    #[fail(display = "failed oracle approval")]
+    OracleRejected,
+
+    #[fail(display = "{}", _0)]
+    InvalidSignature(String),
+
+    // Admin operations that were not authorized
+    #[fail(display = "{}", _0)]
+    Unauthorized(String),
+
+    #[fail(display = "too many admin operations, try again later")]
+    RateLimited,
+
+    // Proof pages were fetched from the host too often, see VerifyContext::fetch_limiter
+    #[fail(
+        display = "too many proof fetches from {}, try again in {} seconds",
+        _0, _1
+    )]
+    FetchRateLimited(String, u64),
+
+    #[fail(display = "asset does not exists")]
+    NotFound,
+
+    #[fail(display = "updates are not allowed")]
+    AlreadyExists,
+
+    // Another asset is already registered with the same entity/ticker, name or ticker
+    #[fail(display = "{}", _0)]
+    Conflict(String),
+
+    // Changes and verification attempted on a read-only registry
+    #[fail(display = "registry is read-only")]
+    ReadOnly,
+}
+
+impl AssetError {
+    // Failures that may succeed when retried, as opposed to ones that are deterministic
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AssetError::ChainUnreachable
+            | AssetError::ChainTimeout
+            | AssetError::ChainServerError(_)
+            | AssetError::RateLimited
+            | AssetError::FetchRateLimited(..) => true,
+            _ => false,
+        }
+    }
+}
+
+// The most specific AssetError in the error's chain of causes, if any
+pub fn asset_error(err: &Error) -> Option<&AssetError> {
+    let causes: Vec<&dyn Fail> = err.iter_chain().collect();
+    causes.into_iter().rev().find_map(|cause| {
+        cause.downcast_ref::<AssetError>().or_else(|| {
+            cause
+                .downcast_ref::<Context<AssetError>>()
+                .map(Context::get_context)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_error() {
+        let err: Error = AssetError::NotFound.into();
+        assert_eq!(asset_error(&err), Some(&AssetError::NotFound));
+
+        // found through the context it is wrapped with, or that it wraps
+        let err: Error = Err::<(), _>(AssetError::InvalidTicker)
+            .context("intake: invalid asset fields")
+            .unwrap_err()
+            .into();
+        assert_eq!(asset_error(&err), Some(&AssetError::InvalidTicker));
+        assert_eq!(
+            join_err(&err),
+            "intake: invalid asset fields: invalid `ticker`"
+        );
+
+        let err: Error = None::<()>
+            .or_err(AssetError::IssuanceNotFound)
+            .context(AssetError::IssuanceFailed)
+            .unwrap_err()
+            .into();
+        assert_eq!(asset_error(&err), Some(&AssetError::IssuanceNotFound));
+
+        let err = format_err!("something else");
+        assert!(asset_error(&err).is_none());
+    }
+}
@@ -0,0 +1,324 @@
+use std::io::Read;
+
+use failure::ResultExt;
+use regex::Regex;
+use reqwest::{header::HOST, redirect, Url};
+
+use crate::context::VerifyContext;
+use crate::entity::{fetch_error, http_client};
+use crate::errors::{OptionExt, Result};
+
+// Maximum size of icon images, in bytes
+pub const ICON_MAX_SIZE: usize = 100 * 1024;
+
+// Stored icons are downscaled to fit within this size, in pixels
+#[cfg(feature = "icons")]
+pub const ICON_MAX_DIMENSION: u32 = 256;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Maximum number of redirects followed when fetching icons
+const ICON_MAX_REDIRECTS: usize = 5;
+
+lazy_static! {
+    // SVG elements that animate the image, run scripts or embed other documents, with any
+    // namespace prefix (matched against the lowercased svg)
+    static ref SVG_DISALLOWED_ELEMENTS: Regex = Regex::new(
+        r"<\s*([a-z0-9_.-]+:)?(script|foreignobject|animate[a-z]*|set|iframe|embed|object|handler|listener|discard)[\s/>]"
+    )
+    .unwrap();
+    // constructs that run scripts, animate or load external resources outside of elements:
+    // event handler attributes, javascript: urls, css animations and imports, and doctypes
+    // (which may declare entities that hide any of the above)
+    static ref SVG_DISALLOWED_CONSTRUCTS: Regex = Regex::new(
+        r"\son[a-z]+\s*=|javascript\s*:|@keyframes|@import|<!doctype|<!entity"
+    )
+    .unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconFormat {
+    Png,
+    Svg,
+}
+
+impl IconFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            IconFormat::Png => "png",
+            IconFormat::Svg => "svg",
+        }
+    }
+}
+
+pub const ICON_EXTENSIONS: &[&str] = &["png", "svg"];
+
+// Verify that the icon url committed in the contract serves an acceptable image: a static PNG
+// or SVG, no larger than ICON_MAX_SIZE. Returns the fetched icon along with its format, so that
+// it can be stored without fetching it again.
+pub fn verify_icon(url: &str, ctx: &VerifyContext) -> Result<(Vec<u8>, IconFormat)> {
+    let icon = fetch_icon(url, ctx)?;
+    let format = check_icon(&icon)?;
+
+    debug!(
+        "verified {:?} icon at {} ({} bytes)",
+        format,
+        url,
+        icon.len()
+    );
+    Ok((icon, format))
+}
+
+// Fetch the icon over https, without reading more than ICON_MAX_SIZE. Redirects are only followed
+// to https urls.
+pub fn fetch_icon(url: &str, ctx: &VerifyContext) -> Result<Vec<u8>> {
+    let url = Url::parse(url).context("invalid icon url")?;
+    ensure!(url.scheme() == "https", "icon url must be https");
+    let host = url.host_str().or_err("icon url missing host")?;
+    let fetch_url = icon_fetch_url(&url);
+
+    debug!("fetching icon from {}: GET {}", host, fetch_url);
+
+    let resp = http_client(ctx)?
+        .redirect(redirect::Policy::custom(|attempt| {
+            if attempt.url().scheme() != "https" {
+                attempt.error("icon redirected to a non-https url")
+            } else if attempt.previous().len() > ICON_MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()?
+        .get(&fetch_url)
+        // explicitly set for the hard-coded server used in testing and development modes
+        .header(HOST, host)
+        .send()
+        .map_err(|err| fetch_error(err, &fetch_url))?
+        .error_for_status()?;
+
+    let mut icon = vec![];
+    resp.take(ICON_MAX_SIZE as u64 + 1)
+        .read_to_end(&mut icon)
+        .context("failed reading icon")?;
+    ensure!(
+        icon.len() <= ICON_MAX_SIZE,
+        "icon too large (maximum {} bytes)",
+        ICON_MAX_SIZE
+    );
+
+    Ok(icon)
+}
+
+fn icon_fetch_url(url: &Url) -> String {
+    if cfg!(any(test, feature = "dev")) {
+        // use a hard-coded server in testing and development modes
+        format!("http://127.0.0.1:58712{}", url.path())
+    } else {
+        url.to_string()
+    }
+}
+
+// Normalize a fetched icon for storage. PNGs are re-encoded, downscaled to fit within
+// ICON_MAX_DIMENSION if larger. SVGs cannot be rasterized here and are not stored, as serving
+// them verbatim from the registry's origin would be unsafe.
+#[cfg(feature = "icons")]
+pub fn normalize_icon(icon: &[u8]) -> Result<(Vec<u8>, IconFormat)> {
+    use image::GenericImageView;
+
+    let format = check_icon(icon)?;
+    ensure!(
+        format == IconFormat::Png,
+        "only png icons can be stored, svg icons are not supported"
+    );
+
+    let mut image = image::load_from_memory_with_format(icon, image::ImageFormat::Png)
+        .context("invalid png icon")?;
+    if image.width() > ICON_MAX_DIMENSION || image.height() > ICON_MAX_DIMENSION {
+        image = image.thumbnail(ICON_MAX_DIMENSION, ICON_MAX_DIMENSION);
+    }
+
+    let mut normalized = vec![];
+    image
+        .write_to(&mut normalized, image::ImageOutputFormat::Png)
+        .context("failed encoding icon")?;
+    Ok((normalized, format))
+}
+
+#[cfg(not(feature = "icons"))]
+pub fn normalize_icon(_icon: &[u8]) -> Result<(Vec<u8>, IconFormat)> {
+    bail!("storing icons requires the `icons` feature")
+}
+
+// Identify the image format by its contents (the served content-type is not relied upon),
+// rejecting animated images
+pub fn check_icon(icon: &[u8]) -> Result<IconFormat> {
+    if icon.starts_with(PNG_SIGNATURE) {
+        check_png(icon)?;
+        return Ok(IconFormat::Png);
+    }
+
+    let text = std::str::from_utf8(icon).context("unsupported icon format")?;
+    let text = text.to_lowercase();
+    ensure!(text.contains("<svg"), "unsupported icon format");
+    for re in &[&*SVG_DISALLOWED_ELEMENTS, &*SVG_DISALLOWED_CONSTRUCTS] {
+        if let Some(found) = re.find(&text) {
+            bail!(
+                "svg icons must be static (found `{}`)",
+                found.as_str().trim()
+            );
+        }
+    }
+    Ok(IconFormat::Svg)
+}
+
+// Walk the PNG chunks, rejecting animated (APNG) images
+fn check_png(png: &[u8]) -> Result<()> {
+    let mut pos = PNG_SIGNATURE.len();
+    loop {
+        let header = png.get(pos..pos + 8).or_err("truncated png")?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type = &header[4..8];
+
+        ensure!(chunk_type != b"acTL", "animated png icons are not allowed");
+        if chunk_type == b"IEND" {
+            return Ok(());
+        }
+
+        // chunk data and crc
+        pos = pos
+            .checked_add(8 + 4)
+            .and_then(|pos| pos.checked_add(len))
+            .filter(|&pos| pos <= png.len())
+            .or_err("truncated png")?;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::entity::tests::spawn_mock_verifier_server;
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for byte in bytes {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = (crc >> 1) ^ (0xedb8_8320 & (!(crc & 1)).wrapping_add(1));
+            }
+        }
+        !crc
+    }
+
+    fn png_chunk(chunk_type: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend(chunk_type);
+        chunk.extend(data);
+        chunk.extend(&crc32(&chunk[4..]).to_be_bytes());
+        chunk
+    }
+
+    fn build_png(animated: bool, idat: &[u8]) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(png_chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]));
+        if animated {
+            png.extend(png_chunk(b"acTL", &[0, 0, 0, 2, 0, 0, 0, 0]));
+        }
+        png.extend(png_chunk(b"IDAT", idat));
+        png.extend(png_chunk(b"IEND", &[]));
+        png
+    }
+
+    // A decodable 1x1 transparent png (the zlib-compressed scanline as the image data)
+    pub fn test_png(animated: bool) -> Vec<u8> {
+        build_png(animated, &[120, 156, 99, 96, 0, 2, 0, 0, 5, 0, 1])
+    }
+
+    pub const TEST_SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1 1"><rect width="1" height="1"/></svg>"#;
+
+    #[test]
+    fn test_check_icon() {
+        assert_eq!(check_icon(&test_png(false)).unwrap(), IconFormat::Png);
+        assert!(check_icon(&test_png(true)).is_err());
+        assert!(check_icon(&test_png(false)[..20]).is_err());
+
+        assert_eq!(check_icon(TEST_SVG.as_bytes()).unwrap(), IconFormat::Svg);
+        let animated = TEST_SVG.replace("/>", "><animate attributeName=\"width\"/></rect>");
+        assert!(check_icon(animated.as_bytes()).is_err());
+        let scripted = TEST_SVG.replace("<rect", "<script>alert(1)</script><rect");
+        assert!(check_icon(scripted.as_bytes()).is_err());
+        for bypass in &[
+            "<svg:script>alert(1)</svg:script>",
+            "<animateTransform attributeName=\"transform\"/>",
+            "<rect onload=\"alert(1)\"/>",
+            "<rect\nonclick = \"alert(1)\"/>",
+            "<a href=\"javascript:alert(1)\"><rect/></a>",
+            "<style>rect { animation: spin 1s } @keyframes spin {}</style>",
+            "<iframe src=\"https://example.com\"/>",
+        ] {
+            let svg = TEST_SVG.replace("<rect", &format!("{}<rect", bypass));
+            assert!(check_icon(svg.as_bytes()).is_err(), "{}", bypass);
+        }
+        let with_entity = format!(
+            "<!DOCTYPE svg [<!ENTITY s \"&#60;script\">]>{}",
+            TEST_SVG.replace("<rect", "&s;<rect")
+        );
+        assert!(check_icon(with_entity.as_bytes()).is_err());
+        // attributes and elements that merely start like the disallowed ones are fine
+        let benign = TEST_SVG.replace("<rect", "<settings/><rect stroke-linejoin=\"round\"");
+        assert_eq!(check_icon(benign.as_bytes()).unwrap(), IconFormat::Svg);
+
+        // chunk lengths that would overflow the position are rejected
+        let mut overflowing = PNG_SIGNATURE.to_vec();
+        overflowing.extend(png_chunk(b"IHDR", &[]));
+        overflowing[8..12].copy_from_slice(&u32::max_value().to_be_bytes());
+        assert!(check_icon(&overflowing).is_err());
+
+        assert!(check_icon(b"GIF89a").is_err());
+    }
+
+    #[cfg(feature = "icons")]
+    #[test]
+    fn test_normalize_icon() {
+        use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+
+        let mut large = vec![];
+        DynamicImage::new_rgba8(512, 256)
+            .write_to(&mut large, ImageOutputFormat::Png)
+            .unwrap();
+        let (normalized, format) = normalize_icon(&large).unwrap();
+        assert_eq!(format, IconFormat::Png);
+        let normalized = image::load_from_memory(&normalized).unwrap();
+        assert_eq!(normalized.dimensions(), (256, 128));
+
+        let (_, format) = normalize_icon(&test_png(false)).unwrap();
+        assert_eq!(format, IconFormat::Png);
+
+        // svgs are not stored
+        assert!(normalize_icon(TEST_SVG.as_bytes()).is_err());
+
+        // not decodable, despite the png signature
+        assert!(normalize_icon(&build_png(false, &[0; 8])).is_err());
+    }
+
+    #[test]
+    fn test_verify_icon() {
+        spawn_mock_verifier_server();
+        let ctx = VerifyContext::default();
+
+        let (icon, format) = verify_icon("https://test.dev/icons/icon.png", &ctx).unwrap();
+        assert_eq!((icon, format), (test_png(false), IconFormat::Png));
+        assert_eq!(
+            verify_icon("https://test.dev/icons/icon.svg", &ctx)
+                .unwrap()
+                .1,
+            IconFormat::Svg
+        );
+        // redirects to non-https urls are not followed
+        assert!(verify_icon("https://test.dev/icon-redirect/icon.png", &ctx).is_err());
+        assert!(verify_icon("https://test.dev/icons/animated.png", &ctx).is_err());
+        assert!(verify_icon("https://test.dev/icons/huge.png", &ctx).is_err());
+        assert!(verify_icon("https://test.dev/icons/missing.png", &ctx).is_err());
+        assert!(verify_icon("http://test.dev/icons/icon.png", &ctx).is_err());
+    }
+}
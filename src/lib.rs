@@ -4,6 +4,7 @@ extern crate base64;
 extern crate bitcoin;
 extern crate elements;
 extern crate secp256k1;
+extern crate secp256k1_schnorr;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
@@ -15,12 +16,29 @@ extern crate lazy_static;
 extern crate failure;
 #[macro_use]
 extern crate log;
+extern crate libc;
+extern crate native_tls;
 extern crate regex;
+extern crate trust_dns_resolver;
+extern crate tungstenite;
+extern crate unicode_normalization;
 
+#[cfg(feature = "compression")]
+extern crate flate2;
 #[cfg(feature = "server")]
 extern crate hyper;
+#[cfg(feature = "icons")]
+extern crate image;
+#[cfg(feature = "cbor")]
+extern crate serde_cbor;
 #[cfg(feature = "cli")]
 extern crate structopt;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "trace")]
+extern crate tracing;
+#[cfg(all(feature = "trace", feature = "server"))]
+extern crate tracing_subscriber;
 
 #[cfg(test)]
 #[macro_use]
@@ -28,13 +46,21 @@ extern crate rocket;
 #[cfg(test)]
 extern crate rocket_contrib;
 
+#[macro_use]
+mod trace;
+
 pub mod asset;
 pub mod chain;
 #[cfg(feature = "client")]
 pub mod client;
+pub mod context;
 pub mod entity;
 pub mod errors;
+pub mod icon;
+pub mod network;
+pub mod oracle;
 pub mod registry;
+pub mod schema;
 #[cfg(feature = "server")]
 pub mod server;
 pub mod util;
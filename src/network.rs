@@ -0,0 +1,82 @@
+use std::{fmt, str::FromStr};
+
+use bitcoin_hashes::hex::FromHex;
+use elements::{AddressParams, AssetId};
+use serde::{Serialize, Serializer};
+
+use crate::errors::{Error, Result};
+
+// Not provided by rust-elements, unlike the LIQUID and ELEMENTS (regtest) ones
+static LIQUID_TESTNET_PARAMS: AddressParams = AddressParams {
+    p2pkh_prefix: 36,
+    p2sh_prefix: 19,
+    blinded_prefix: 23,
+    bech_hrp: "tex",
+    blech_hrp: "tlq",
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Liquid,
+    LiquidTestnet,
+    ElementsRegtest,
+}
+
+impl Network {
+    // The native asset used to pay fees (L-BTC)
+    pub fn policy_asset(self) -> AssetId {
+        let hex = match self {
+            Network::Liquid => "6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d",
+            Network::LiquidTestnet => {
+                "144c654344aa716d6f3abcc1ca90e5641e4e2a7f633bc09fe3baf64585819a49"
+            }
+            Network::ElementsRegtest => {
+                "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225"
+            }
+        };
+        AssetId::from_hex(hex).unwrap()
+    }
+
+    pub fn address_params(self) -> &'static AddressParams {
+        match self {
+            Network::Liquid => &AddressParams::LIQUID,
+            Network::LiquidTestnet => &LIQUID_TESTNET_PARAMS,
+            Network::ElementsRegtest => &AddressParams::ELEMENTS,
+        }
+    }
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network::Liquid
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Network::Liquid => "liquid",
+            Network::LiquidTestnet => "liquidtestnet",
+            Network::ElementsRegtest => "elementsregtest",
+        })
+    }
+}
+
+impl Serialize for Network {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl FromStr for Network {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "liquid" => Network::Liquid,
+            "liquidtestnet" => Network::LiquidTestnet,
+            "elementsregtest" => Network::ElementsRegtest,
+            _ => bail!("unknown network {}", s),
+        })
+    }
+}
@@ -0,0 +1,118 @@
+use bitcoin_hashes::hex::ToHex;
+use failure::ResultExt;
+use reqwest::blocking::Client as ReqClient;
+
+use crate::asset::Asset;
+use crate::errors::Result;
+
+#[derive(Deserialize)]
+struct OracleVerdict {
+    approved: bool,
+    reason: Option<String>,
+}
+
+// Ask an operator-configured external service to approve the asset. The oracle receives a POST
+// request with the asset id and fields, and replies with `{"approved":bool,"reason":string}`.
+pub fn verify_oracle(asset: &Asset, oracle_url: &str) -> Result<()> {
+    debug!("requesting oracle approval for {}", asset.id().to_hex());
+
+    let verdict: OracleVerdict = ReqClient::new()
+        .post(oracle_url)
+        .json(&oracle_request(asset))
+        .send()
+        .context("failed sending oracle request")?
+        .error_for_status()
+        .context("oracle request failed")?
+        .json()
+        .context("invalid oracle response")?;
+
+    check_verdict(asset, verdict)
+}
+
+#[cfg(feature = "async")]
+pub async fn verify_oracle_async(asset: &Asset, oracle_url: &str) -> Result<()> {
+    debug!("requesting oracle approval for {}", asset.id().to_hex());
+
+    let verdict: OracleVerdict = reqwest::Client::new()
+        .post(oracle_url)
+        .json(&oracle_request(asset))
+        .send()
+        .await
+        .context("failed sending oracle request")?
+        .error_for_status()
+        .context("oracle request failed")?
+        .json()
+        .await
+        .context("invalid oracle response")?;
+
+    check_verdict(asset, verdict)
+}
+
+fn oracle_request(asset: &Asset) -> serde_json::Value {
+    json!({ "asset_id": asset.id(), "fields": asset.fields })
+}
+
+fn check_verdict(asset: &Asset, verdict: OracleVerdict) -> Result<()> {
+    ensure!(
+        verdict.approved,
+        "rejected by oracle: {}",
+        verdict.reason.as_deref().unwrap_or("no reason given")
+    );
+
+    debug!("oracle approved {}", asset.id().to_hex());
+
+    Ok(())
+}
+
+// needs to be run with --test-threads 1
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::util::wait_for_port;
+    use rocket as r;
+    use rocket_contrib::json::{Json, JsonValue};
+    use serde_json::Value;
+    use std::path::PathBuf;
+    use std::sync::Once;
+
+    static SPAWN_ONCE: Once = Once::new();
+
+    // an oracle that approves any asset not named "Evil coin"
+    pub fn spawn_mock_oracle_server() {
+        SPAWN_ONCE.call_once(|| {
+            let config = r::config::Config::build(r::config::Environment::Development)
+                .port(58714)
+                .finalize()
+                .unwrap();
+            let rocket = r::custom(config).mount("/", routes![oracle_handler]);
+
+            std::thread::spawn(|| rocket.launch());
+        });
+        wait_for_port(58714);
+    }
+
+    #[post("/", data = "<req>")]
+    fn oracle_handler(req: Json<Value>) -> JsonValue {
+        if req["fields"]["name"] == "Evil coin" {
+            JsonValue::from(json!({ "approved": false, "reason": "evil" }))
+        } else {
+            JsonValue::from(json!({ "approved": true }))
+        }
+    }
+
+    #[test]
+    fn test0_init() {
+        stderrlog::new().verbosity(3).init().ok();
+        spawn_mock_oracle_server();
+    }
+
+    #[test]
+    fn test1_verify_oracle() {
+        let mut asset = Asset::load(PathBuf::from("test/asset-b1405e.json")).unwrap();
+        verify_oracle(&asset, "http://localhost:58714/").expect("oracle should approve");
+
+        asset.fields.name = "Evil coin".to_string();
+        let err = verify_oracle(&asset, "http://localhost:58714/").unwrap_err();
+        assert_eq!(err.to_string(), "rejected by oracle: evil");
+    }
+}
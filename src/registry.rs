@@ -1,205 +1,4507 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::{fs, path, process::Command};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Output, Stdio};
+use std::str::FromStr;
+use std::sync::{mpsc, Arc, Condvar, Mutex, RwLock, TryLockError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{fmt, fs, path, thread};
 
-use bitcoin_hashes::hex::ToHex;
+use bitcoin_hashes::{hex::FromHex, hex::ToHex, sha256, Hash};
 use elements::AssetId;
+use secp256k1::Secp256k1;
+use serde_json::Value;
 
-use crate::asset::Asset;
-use crate::chain::ChainQuery;
-use crate::entity::AssetEntity;
-use crate::errors::{OptionExt, Result, ResultExt};
+use crate::asset::{
+    canonical_contract_json, deletion_sig_msg, legacy_deletion_sig_msg, update_sig_msg,
+    validate_registry_id, Asset, AssetFields, ElementsAssetEntry, Verified,
+};
+use crate::chain::{verify_asset_issuance_data, verify_asset_issuance_tx, ChainQuery};
+use crate::context::{PolicyFiles, VerifyContext};
+use crate::entity::{AssetEntity, LinkProof};
+use crate::errors::{asset_error, join_err, AssetError, Error, OptionExt, Result, ResultExt};
+use crate::icon::{normalize_icon, IconFormat, ICON_EXTENSIONS};
+use crate::network::Network;
+use crate::util::{
+    gzip, is_compressed, read_json_file, similarity, verify_bitcoin_msg, verify_pubkey,
+    COMPRESSED_EXTENSION,
+};
 
-// length of asset id prefix to use for sub-directory partitioning
-// (in number of hex characters, not bytes)
-const DIR_PARTITION_LEN: usize = 2;
+// minimal similarity score for find_similar() matches
+const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+// append-only log of registry updates, one json entry per line
+const CHANGELOG_FILE: &str = "_changelog.ndjson";
+
+// the changelog is read backwards in blocks of this size (see RevLines)
+const CHANGELOG_BLOCK_SIZE: u64 = 64 * 1024;
+
+// summary of all stored assets keyed by asset id, when enabled
+const INDEX_FILE: &str = "_index.json";
+
+// extension of the checksum files stored alongside asset files, when enabled
+const CHECKSUM_EXTENSION: &str = "sha256";
+
+// directory for the domain proofs kept as evidence, when enabled
+const PROOFS_DIR: &str = "_proofs";
+
+// the last sequence number accepted for each asset's deletion, retained after the asset is deleted
+const SEQUENCES_FILE: &str = "_sequences.json";
+
+// error for assets whose entity/ticker combination is already taken
+const NS_CONFLICT: &str = "another asset is already registered with this entity/ticker";
+
+// the timestamp of the last accepted admin authorization for each operation and asset
+const ADMIN_AUTHS_FILE: &str = "_admin_auths.json";
+
+// how old admin authorization timestamps may be (in seconds)
+const ADMIN_AUTH_MAX_AGE: u64 = 300;
+// how far in the future admin authorization timestamps may be, for clock skew (in seconds)
+const ADMIN_AUTH_MAX_SKEW: u64 = 30;
+
+// maximal number of admin operations accepted within ADMIN_RATE_WINDOW
+const ADMIN_RATE_LIMIT: usize = 10;
+const ADMIN_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+// how often to check whether the hook exited, when running it with a timeout
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+lazy_static! {
+    static ref EC: Secp256k1<secp256k1::VerifyOnly> = Secp256k1::verification_only();
+}
 
 #[derive(Debug)]
 pub struct Registry {
     directory: path::PathBuf,
-    chain: ChainQuery,
+    // missing for read-only registries
+    chain: Option<ChainQuery>,
     hook_cmd: Option<String>,
+    hook_timeout: Option<Duration>,
+    hook_fatal: bool,
+    // swapped as a whole when the policy files are reloaded, verifications use a snapshot
+    ctx: RwLock<Arc<VerifyContext>>,
+    policy_files: Option<PolicyFiles>,
+    // serializes changes to the registry directory (and the hook runs). held only briefly,
+    // after the verification network round-trips are done.
     write_lock: Arc<Mutex<()>>,
+    // per-asset locks held during verification, to prevent concurrent writes of the same asset
+    asset_locks: Mutex<HashMap<AssetId, Arc<AssetLock>>>,
+    admin_pubkey: Option<Vec<u8>>,
+    admin_state: Mutex<AdminState>,
+    registry_id: Option<String>,
+    legacy_deletions: bool,
+    keep_proofs: bool,
+    store_icons: bool,
+    keep_index: bool,
+    keep_checksums: bool,
+    compress: bool,
+    uniqueness: UniquenessPolicy,
+    layout: PartitionLayout,
+    // built on first use by find_by_ticker() and check_uniqueness()
+    field_index: Mutex<Option<FieldIndex>>,
 }
 
-impl Registry {
-    pub fn new(directory: &path::Path, chain: ChainQuery, hook_cmd: Option<String>) -> Self {
-        Registry {
+// Authorization for a privileged operation, signed by the registry admin key over
+// the message returned by format_admin_sig_msg()
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminAuth {
+    // unix timestamp in seconds
+    pub timestamp: u64,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct AdminState {
+    // when recent admin operations were accepted, for rate limiting
+    recent: Vec<Instant>,
+}
+
+// Configures and creates a Registry. Options that are not set keep their default behavior.
+#[derive(Debug)]
+pub struct RegistryBuilder {
+    directory: path::PathBuf,
+    chain: Option<ChainQuery>,
+    hook_cmd: Option<String>,
+    hook_timeout: Option<Duration>,
+    hook_fatal: bool,
+    ctx: VerifyContext,
+    policy_files: Option<PolicyFiles>,
+    admin_pubkey: Option<Vec<u8>>,
+    registry_id: Option<String>,
+    legacy_deletions: Option<bool>,
+    keep_proofs: bool,
+    store_icons: bool,
+    keep_index: bool,
+    keep_checksums: bool,
+    compress: bool,
+    uniqueness: UniquenessPolicy,
+    layout: PartitionLayout,
+}
+
+impl RegistryBuilder {
+    pub fn new(directory: &path::Path, chain: ChainQuery) -> Self {
+        RegistryBuilder::with_chain(directory, Some(chain))
+    }
+
+    // A registry that only serves the already stored assets, without a chain backend. Any
+    // attempt to write, delete or verify assets fails with AssetError::ReadOnly, so that no
+    // outbound requests are ever made.
+    pub fn read_only(directory: &path::Path) -> Self {
+        RegistryBuilder::with_chain(directory, None)
+    }
+
+    fn with_chain(directory: &path::Path, chain: Option<ChainQuery>) -> Self {
+        RegistryBuilder {
             directory: directory.to_path_buf(),
             chain,
-            hook_cmd,
+            hook_cmd: None,
+            hook_timeout: None,
+            hook_fatal: true,
+            ctx: VerifyContext::default(),
+            policy_files: None,
+            admin_pubkey: None,
+            registry_id: None,
+            legacy_deletions: None,
+            keep_proofs: false,
+            store_icons: false,
+            keep_index: false,
+            keep_checksums: false,
+            compress: false,
+            uniqueness: UniquenessPolicy::None,
+            layout: PartitionLayout::default(),
+        }
+    }
+
+    // Script to run after every registry update (see contrib/hook.sh)
+    pub fn hook_cmd(mut self, hook_cmd: Option<String>) -> Self {
+        self.hook_cmd = hook_cmd;
+        self
+    }
+
+    // Kill the hook and fail if it doesn't complete within the timeout
+    pub fn hook_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.hook_timeout = timeout;
+        self
+    }
+
+    // Whether hook failures abort the update (the default), or are only logged while the
+    // update is kept
+    pub fn hook_fatal(mut self, fatal: bool) -> Self {
+        self.hook_fatal = fatal;
+        self
+    }
+
+    pub fn context(mut self, ctx: VerifyContext) -> Self {
+        self.ctx = ctx;
+        self
+    }
+
+    // Load the context's policy lists from files, which Registry::reload_policy() may reload
+    // later. Replaces the lists of the context set so far, so must be set after it.
+    pub fn policy_files(mut self, files: PolicyFiles) -> Result<Self> {
+        files
+            .apply(&mut self.ctx)
+            .context("failed loading policy files")?;
+        self.policy_files = Some(files);
+        Ok(self)
+    }
+
+    // Keep the domain proofs fetched while verifying newly written assets
+    pub fn proof_evidence(mut self, keep_proofs: bool) -> Self {
+        self.keep_proofs = keep_proofs;
+        self
+    }
+
+    // Download the icons of newly written assets and store them alongside the asset json, so
+    // that wallets can get them from the registry
+    #[cfg(feature = "icons")]
+    pub fn store_icons(mut self, store_icons: bool) -> Self {
+        self.store_icons = store_icons;
+        self
+    }
+
+    // Maintain an index file of all stored assets, so that consumers can load a single file
+    // instead of walking the tree. Use Registry::sync_index() to build it for existing registries.
+    pub fn index(mut self, keep_index: bool) -> Self {
+        self.keep_index = keep_index;
+        self
+    }
+
+    // Store the sha256 of each written asset file alongside it (as `<asset-id>.sha256`, in the
+    // format of sha256sum), for detecting on-disk corruption with Registry::verify_storage().
+    // Use Registry::sync_checksums() for the assets written before enabling it.
+    pub fn checksums(mut self, keep_checksums: bool) -> Self {
+        self.keep_checksums = keep_checksums;
+        self
+    }
+
+    // Store newly written asset files gzip-compressed (as `<asset-id>.json.gz`). Assets are always
+    // verified against their decompressed json, and registries may hold a mix of compressed and
+    // uncompressed files. Use Registry::normalize_all() to convert the existing ones.
+    #[cfg(feature = "compression")]
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    // Reject new assets whose ticker and/or name is already used by another registered asset
+    pub fn uniqueness(mut self, uniqueness: UniquenessPolicy) -> Self {
+        self.uniqueness = uniqueness;
+        self
+    }
+
+    // How asset files are partitioned into sub-directories. Existing registries must be migrated
+    // with Registry::migrate_layout() after changing it.
+    pub fn partition_layout(mut self, layout: PartitionLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    // Enable the privileged admin operations, authorized by signatures from the given key. Once
+    // set, issuer deletions must be authorized by the admin key too.
+    pub fn admin_pubkey(mut self, pubkey: Vec<u8>) -> Result<Self> {
+        verify_pubkey(&pubkey).context("invalid admin pubkey")?;
+        self.admin_pubkey = Some(pubkey);
+        Ok(self)
+    }
+
+    // Identifies the registry in the deletion messages signed by issuers (see asset::IssuerMsg),
+    // so that they cannot be replayed against other registries
+    pub fn registry_id(mut self, registry_id: &str) -> Result<Self> {
+        validate_registry_id(registry_id)?;
+        self.registry_id = Some(registry_id.to_string());
+        Ok(self)
+    }
+
+    // Also accept deletions signed over the legacy messages that are not bound to a registry or
+    // carry no sequence number, for issuers that have not moved to the current one yet. These
+    // provide no replay protection: a legacy authorization can be replayed to remove the asset
+    // again whenever it is registered again. Enabled by default only for registries without an id,
+    // which cannot verify anything else.
+    pub fn legacy_deletions(mut self, accept: bool) -> Self {
+        self.legacy_deletions = Some(accept);
+        self
+    }
+
+    pub fn build(self) -> Registry {
+        if let Some(chain) = &self.chain {
+            if self.ctx.network != chain.network() {
+                warn!(
+                    "verifying against {}, but the chain backend is configured for {}",
+                    self.ctx.network,
+                    chain.network()
+                );
+            }
+        }
+        let legacy_deletions = self
+            .legacy_deletions
+            .unwrap_or_else(|| self.registry_id.is_none());
+        Registry {
+            directory: self.directory,
+            chain: self.chain,
+            hook_cmd: self.hook_cmd,
+            hook_timeout: self.hook_timeout,
+            hook_fatal: self.hook_fatal,
+            ctx: RwLock::new(Arc::new(self.ctx)),
+            policy_files: self.policy_files,
             write_lock: Arc::new(Mutex::new(())),
+            asset_locks: Mutex::new(HashMap::new()),
+            admin_pubkey: self.admin_pubkey,
+            admin_state: Mutex::new(AdminState::default()),
+            registry_id: self.registry_id,
+            legacy_deletions,
+            keep_proofs: self.keep_proofs,
+            store_icons: self.store_icons,
+            keep_index: self.keep_index,
+            keep_checksums: self.keep_checksums,
+            compress: self.compress,
+            uniqueness: self.uniqueness,
+            layout: self.layout,
+            field_index: Mutex::new(None),
         }
     }
+}
 
-    pub fn load(&self, asset_id: &AssetId) -> Result<Option<Asset>> {
+impl Registry {
+    pub fn new(directory: &path::Path, chain: ChainQuery, hook_cmd: Option<String>) -> Self {
+        RegistryBuilder::new(directory, chain)
+            .hook_cmd(hook_cmd)
+            .build()
+    }
+
+    pub fn builder(directory: &path::Path, chain: ChainQuery) -> RegistryBuilder {
+        RegistryBuilder::new(directory, chain)
+    }
+
+    // A read-only registry serving the stored assets (see RegistryBuilder::read_only())
+    pub fn read_only(directory: &path::Path) -> Self {
+        RegistryBuilder::read_only(directory).build()
+    }
+
+    // The path where the uncompressed asset json file is stored (or would be)
+    pub fn path_for(&self, asset_id: &AssetId) -> path::PathBuf {
         let name = format!("{}.json", asset_id.to_hex());
-        let subdir = self.directory.join(&name[0..DIR_PARTITION_LEN]);
-        let path = subdir.join(name);
+        let mut path = self.directory.clone();
+        for level in 0..self.layout.levels {
+            path.push(&name[level * self.layout.width..(level + 1) * self.layout.width]);
+        }
+        path.join(name)
+    }
 
-        Ok(if path.exists() {
-            Some(Asset::load(path)?)
-        } else {
-            None
+    // The path of the stored asset json file, either compressed or not, if it exists
+    pub fn stored_path(&self, asset_id: &AssetId) -> Option<path::PathBuf> {
+        find_stored(&self.path_for(asset_id))
+    }
+
+    pub fn load(&self, asset_id: &AssetId) -> Result<Option<Asset>> {
+        Ok(match self.stored_path(asset_id) {
+            Some(path) => Some(Asset::load(path)?),
+            None => None,
         })
     }
 
+    // The stored asset json (decompressed when stored compressed) along with its hex sha256, for
+    // serving with the hash as an HTTP ETag. Unlike the file's mtime, the hash stays the same when
+    // the registry is copied or restored from a backup, and for uncompressed files it matches the
+    // hash recorded in the checksum file.
+    pub fn load_json_with_hash(&self, asset_id: &AssetId) -> Result<Option<(Vec<u8>, String)>> {
+        Ok(self.read_raw(asset_id)?.map(|json| {
+            let hash = content_hash(&json);
+            (json, hash)
+        }))
+    }
+
     pub fn write(&self, asset: &Asset) -> Result<()> {
-        asset.verify(Some(&self.chain))?;
+        enter_span!("write", asset_id = %asset.asset_id);
+        let chain = self.chain()?;
+        let _asset_lock = self.lock_asset(&asset.asset_id);
+
+        // fail early for existing assets, before verifying
+        if self.stored_path(&asset.asset_id).is_some() {
+            return Err(AssetError::AlreadyExists.into());
+        }
+
+        self.invalidate_link_cache(&asset.asset_id);
+        let verified = asset.verify_capturing(Some(chain), &self.ctx())?;
+        let icon = self.prepare_icon(verified.icon.as_deref())?;
+
+        self.commit_write(asset, verified, icon)
+    }
+
+    // Run the same verification as write() without writing the asset or executing the hook, to
+    // check whether the asset would be accepted
+    pub fn validate(&self, asset: &Asset) -> Result<()> {
+        let chain = self.chain()?;
+        if self.stored_path(&asset.asset_id).is_some() {
+            return Err(AssetError::AlreadyExists.into());
+        }
+
+        self.invalidate_link_cache(&asset.asset_id);
+        asset.verify_with(Some(chain), &self.ctx())?;
+
+        if self.file_handle(asset).ns_exists() {
+            return Err(AssetError::Conflict(NS_CONFLICT.into()).into());
+        }
+        self.check_uniqueness(asset)
+    }
+
+    // Like write(), but verifying the asset with non-blocking requests. The registry directory
+    // is then updated on the blocking thread pool, as the write lock and the hook are shared
+    // with the sync api.
+    #[cfg(feature = "async")]
+    pub async fn write_async(self: Arc<Self>, asset: Asset) -> Result<()> {
+        let chain = self.chain()?;
+        let registry = Arc::clone(&self);
+        let asset_id = asset.asset_id;
+        let _asset_lock =
+            tokio::task::spawn_blocking(move || registry.lock_asset(&asset_id)).await?;
+
+        // fail early for existing assets, before verifying
+        if self.stored_path(&asset.asset_id).is_some() {
+            return Err(AssetError::AlreadyExists.into());
+        }
+
+        self.invalidate_link_cache(&asset.asset_id);
+        let verified = asset.verify_async(Some(chain), &self.ctx()).await?;
+
+        tokio::task::spawn_blocking(move || {
+            let icon = self.prepare_icon(verified.icon.as_deref())?;
+            self.commit_write(&asset, verified, icon)
+        })
+        .await?
+    }
+
+    fn commit_write(
+        &self,
+        asset: &Asset,
+        verified: Verified,
+        icon: Option<(Vec<u8>, IconFormat)>,
+    ) -> Result<()> {
+        let asset = &asset.with_issuance_block(verified.issuance_block);
 
         let _lock = self.write_lock.lock().unwrap();
-        let asset_fh = AssetFileHandle::new(asset, &self.directory);
+        let asset_fh = self.file_handle(asset);
 
-        ensure!(!asset_fh.exists(), "updates are not allowed");
-        ensure!(
-            !asset_fh.ns_exists(),
-            "another asset is already registered with this entity/ticker"
-        );
+        if asset_fh.exists() {
+            return Err(AssetError::AlreadyExists.into());
+        }
+        if asset_fh.ns_exists() {
+            return Err(AssetError::Conflict(NS_CONFLICT.into()).into());
+        }
+        self.check_uniqueness(asset)?;
 
         asset_fh.write()?;
+        // stored before the hook runs, so that it can publish them along with the asset
+        if let Some(icon) = icon {
+            self.write_icon(&asset.asset_id, icon)
+                .context("failed storing icon")?;
+        }
+        let previous_proof = self
+            .keep_proof(&asset.asset_id, verified.proof.as_ref())
+            .context("failed writing proof evidence")?;
 
+        // the index is updated before the hook too, so that it can publish it along with the asset
+        let abs_path = asset_fh.abs_path()?;
         if let Err(err) = self
-            .exec_hook(&asset.asset_id, &asset_fh.abs_path()?, "add", None)
-            .context("hook script failed")
+            .update_index(asset, true)
+            .context("failed updating index")
+            .and_then(|()| {
+                self.exec_hook(asset, &abs_path, HookEvent::Add, &[])
+                    .context("hook script failed")
+            })
         {
             warn!("hook failed: {:?}", err);
             // cleanup created files if the hook fails (might've already been cleaned by the hook script)
             asset_fh.delete()?;
+            self.remove_icons(&asset.asset_id)?;
+            self.restore_proof(&asset.asset_id, previous_proof)?;
+            self.update_index(asset, false)?;
             bail!(err)
         }
 
+        self.append_changelog(&asset.asset_id, ChangeAction::Add, &asset_fh.read()?)
+            .context("failed writing changelog")?;
+        self.update_field_index(asset, true);
+
         Ok(())
     }
 
-    pub fn delete(&self, asset: &Asset, signature: &[u8]) -> Result<()> {
-        asset.verify_deletion(signature)?;
+    // Delete the asset, authorized by the issuer's signature over the registry's deletion message
+    // with the given sequence number (see asset::IssuerMsg), and by the admin when an admin key
+    // is configured
+    pub fn delete(
+        &self,
+        asset: &Asset,
+        signature: &[u8],
+        sequence: Option<u64>,
+        admin_auth: Option<&AdminAuth>,
+    ) -> Result<()> {
+        self.ensure_writable()?;
+        let signed_sequence = asset.verify_deletion(
+            signature,
+            self.registry_id.as_deref(),
+            sequence,
+            self.legacy_deletions,
+        )?;
+
+        let _lock = self.write_lock.lock().unwrap();
+        self.require_admin("delete", &asset.asset_id, admin_auth)?;
+        self.use_sequence(&asset.asset_id, signed_sequence)?;
+        self.delete_locked(asset, &[signature.to_vec()])
+    }
+
+    // Delete the stored asset, authorized by the issuer's signature over it (and by the admin,
+    // like delete())
+    pub fn remove(
+        &self,
+        asset_id: &AssetId,
+        signature: &[u8],
+        sequence: Option<u64>,
+        admin_auth: Option<&AdminAuth>,
+    ) -> Result<()> {
+        self.remove_with_sigs(asset_id, &[signature.to_vec()], sequence, admin_auth)
+    }
 
+    // Like remove(), for multisig issuers that require multiple signatures
+    pub fn remove_with_sigs(
+        &self,
+        asset_id: &AssetId,
+        signatures: &[Vec<u8>],
+        sequence: Option<u64>,
+        admin_auth: Option<&AdminAuth>,
+    ) -> Result<()> {
+        self.ensure_writable()?;
         let _lock = self.write_lock.lock().unwrap();
-        let asset_fh = AssetFileHandle::new(asset, &self.directory);
-        ensure!(asset_fh.exists(), "asset does not exists");
+        let asset = self.load(asset_id)?.or_err(AssetError::NotFound)?;
+        let signed_sequence = asset
+            .verify_deletion_sigs(
+                signatures,
+                self.registry_id.as_deref(),
+                sequence,
+                self.legacy_deletions,
+            )
+            .context("invalid deletion signature")?;
+
+        self.require_admin("delete", asset_id, admin_auth)?;
+        self.use_sequence(asset_id, signed_sequence)?;
+        self.delete_locked(&asset, signatures)
+    }
+
+    // The last sequence number accepted for the asset's deletion or update, if any
+    pub fn last_sequence(&self, asset_id: &AssetId) -> Result<Option<u64>> {
+        Ok(self.sequences()?.get(&asset_id.to_hex()).copied())
+    }
+
+    fn sequences(&self) -> Result<BTreeMap<String, u64>> {
+        let path = self.directory.join(SEQUENCES_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?).context("invalid sequences file")?)
+    }
+
+    // Reject sequence numbers that don't increase over the last accepted one, then record it.
+    // Recorded before the operation is carried out, so that failed ones cannot be replayed
+    // either. Only takes the sequence numbers the issuer actually signed for (None for legacy
+    // messages, which cannot be protected), so that an unsigned client-supplied sequence cannot
+    // be used to block later authorizations. Must be called while holding the write lock.
+    fn use_sequence(&self, asset_id: &AssetId, signed_sequence: Option<u64>) -> Result<()> {
+        let sequence = match signed_sequence {
+            Some(sequence) => sequence,
+            None => return Ok(()),
+        };
+        let mut sequences = self.sequences()?;
+        if let Some(&last) = sequences.get(&asset_id.to_hex()) {
+            if sequence <= last {
+                return Err(AssetError::InvalidSignature(format!(
+                    "sequence number must be greater than {}",
+                    last
+                ))
+                .into());
+            }
+        }
+        sequences.insert(asset_id.to_hex(), sequence);
+        write_atomic(
+            &self.directory.join(SEQUENCES_FILE),
+            serde_json::to_string(&sequences)?.as_bytes(),
+        )
+        .context("failed writing sequences file")?;
+        Ok(())
+    }
+
+    // Must be called while holding the write lock
+    fn delete_locked(&self, asset: &Asset, signatures: &[Vec<u8>]) -> Result<()> {
+        enter_span!("delete", asset_id = %asset.asset_id);
+        let asset_fh = self.file_handle(asset);
+        if !asset_fh.exists() {
+            return Err(AssetError::NotFound.into());
+        }
         let abs_path = asset_fh.abs_path()?;
 
+        let contents = asset_fh.read()?;
+        // kept as stored (possibly compressed), to restore the asset if the hook fails
+        let stored = fs::read(&abs_path)?;
+
         debug!("deleting asset {:?}", asset.asset_id);
         asset_fh.delete()?;
 
-        self.exec_hook(&asset.asset_id, &abs_path, "delete", Some(signature))
-            .context("hook script failed")?;
+        if let Err(err) = self
+            .update_index(asset, false)
+            .context("failed updating index")
+            .and_then(|()| {
+                self.exec_hook(asset, &abs_path, HookEvent::Delete, signatures)
+                    .context("hook script failed")
+            })
+        {
+            warn!("hook failed: {:?}", err);
+            // restore the deleted files if the hook fails, so that the deletion isn't left unlogged
+            asset_fh.restore(&abs_path, &stored)?;
+            self.update_index(asset, true)?;
+            bail!(err)
+        }
+
+        self.append_changelog(&asset.asset_id, ChangeAction::Delete, &contents)
+            .context("failed writing changelog")?;
+        self.update_field_index(asset, false);
+
+        self.remove_icons(&asset.asset_id)?;
+
+        // prune the partition directories once empty (after the hook, which gets the asset path)
+        self.prune_partition_dirs(abs_path.parent().req()?)?;
 
         Ok(())
     }
 
-    fn exec_hook(
-        &self,
-        asset_id: &AssetId,
-        asset_path: &path::Path,
-        update_type: &str,
-        signature: Option<&[u8]>,
-    ) -> Result<()> {
-        if let Some(cmd) = &self.hook_cmd {
-            debug!("running hook {} for {:?}", cmd, asset_id);
+    // Check the asset against the stored ones according to the uniqueness policy. Must be called
+    // while holding the write lock.
+    fn check_uniqueness(&self, asset: &Asset) -> Result<()> {
+        if self.uniqueness == UniquenessPolicy::None {
+            return Ok(());
+        }
+        let ticker = asset.fields.ticker.as_ref().map(|t| t.to_lowercase());
+        let name = asset.fields.name.to_lowercase();
 
-            let mut envs = HashMap::new();
-            if let Some(sig) = signature {
-                envs.insert("AUTHORIZING_SIG", base64::encode(sig));
+        let mut candidates: Vec<AssetId> = self.with_field_index(|index| {
+            let mut asset_ids = HashSet::new();
+            if let (true, Some(ticker)) = (self.uniqueness.ticker_unique(), &ticker) {
+                asset_ids.extend(index.tickers.get(ticker).into_iter().flatten());
             }
+            if self.uniqueness.name_unique() {
+                asset_ids.extend(index.names.get(&name).into_iter().flatten());
+            }
+            asset_ids.remove(&asset.asset_id);
+            asset_ids.into_iter().collect()
+        })?;
+        candidates.sort_by_key(|asset_id| asset_id.to_hex());
 
-            let output = Command::new(cmd)
-                .current_dir(&self.directory)
-                .arg(asset_id.to_hex())
-                .arg(asset_path.to_str().req()?)
-                .arg(update_type)
-                .envs(envs)
-                .output()?;
-            debug!(
-                "hook exited with {:?}\n## stdout: {}\n## stderr: {}",
-                output.status,
-                String::from_utf8_lossy(&output.stdout),
-                String::from_utf8_lossy(&output.stderr)
-            );
-
-            ensure!(output.status.success(), "hook script failed");
+        for asset_id in candidates {
+            // might've been deleted without the index being updated (if the hook failed)
+            let other = match self.load(&asset_id)? {
+                Some(other) => other,
+                None => continue,
+            };
+            if self.uniqueness.ticker_unique() {
+                let other_ticker = other.fields.ticker.as_ref().map(|t| t.to_lowercase());
+                if ticker.is_some() && ticker == other_ticker {
+                    return Err(AssetError::Conflict(format!(
+                        "ticker {} already registered by asset {}",
+                        asset.fields.ticker.as_ref().unwrap(),
+                        other.asset_id.to_hex()
+                    ))
+                    .into());
+                }
+            }
+            if self.uniqueness.name_unique() && name == other.fields.name.to_lowercase() {
+                return Err(AssetError::Conflict(format!(
+                    "name {} already registered by asset {}",
+                    asset.fields.name,
+                    other.asset_id.to_hex()
+                ))
+                .into());
+            }
         }
         Ok(())
     }
 
-    pub fn chain(&self) -> &ChainQuery {
-        &self.chain
+    fn asset_lock(&self, asset_id: &AssetId) -> Arc<AssetLock> {
+        let mut locks = self.asset_locks.lock().unwrap();
+        // drop the locks no longer held by anyone but the map
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        Arc::clone(locks.entry(*asset_id).or_default())
     }
-}
 
-struct AssetFileHandle<'a> {
-    asset: &'a Asset,
-    // directory and full path to main asset json file
-    path: path::PathBuf,
-    // path for unique namespace identifier file
-    ns_path: Option<path::PathBuf>,
-}
+    // Blocks until the asset's lock is available
+    fn lock_asset(&self, asset_id: &AssetId) -> AssetLockGuard {
+        AssetLockGuard::acquire(self.asset_lock(asset_id))
+    }
 
-impl<'a> AssetFileHandle<'a> {
-    fn new(asset: &'a Asset, base_dir: &path::Path) -> Self {
-        let name = format!("{}.json", asset.asset_id.to_hex());
-        let dir = base_dir.join(&name[0..DIR_PARTITION_LEN]);
-        let path = dir.join(name);
+    // Delete an asset without the issuer's signature, authorized by the admin key instead
+    pub fn admin_delete(&self, asset_id: &AssetId, auth: &AdminAuth) -> Result<()> {
+        self.ensure_writable()?;
+        self.verify_admin("delete", asset_id, auth)?;
 
-        // XXX use sub-dirs inside map too, use the hash of the unique_key as filename?
-        let ns_dir = base_dir.join("_map");
-        let ns_path = make_unique_ns_filename(&asset.fields.entity, asset.fields.ticker.as_ref())
-            .map(|filename| ns_dir.join(filename));
+        let _lock = self.write_lock.lock().unwrap();
+        let asset = self.load(asset_id)?.or_err(AssetError::NotFound)?;
 
-        AssetFileHandle {
-            asset,
-            path,
-            ns_path,
-        }
+        debug!("admin deleting asset {:?}", asset_id);
+        self.delete_locked(&asset, &[])
     }
 
-    fn exists(&self) -> bool {
-        self.path.exists()
+    // Write an asset, replacing the existing one if already registered. The new asset is fully
+    // verified, but updating it is authorized by the admin key rather than the issuer.
+    pub fn write_overwrite(&self, asset: &Asset, auth: &AdminAuth) -> Result<()> {
+        self.overwrite(asset, "overwrite", auth, false)
     }
 
-    fn ns_exists(&self) -> bool {
-        self.ns_path.as_ref().map_or(false, |path| path.exists())
+    // Like write_overwrite(), but only for assets that are already registered
+    pub fn replace(&self, asset: &Asset, auth: &AdminAuth) -> Result<()> {
+        self.overwrite(asset, "replace", auth, true)
     }
 
-    fn abs_path(&self) -> Result<path::PathBuf> {
-        Ok(self.path.canonicalize()?)
-    }
+    fn overwrite(
+        &self,
+        asset: &Asset,
+        operation: &str,
+        auth: &AdminAuth,
+        must_exist: bool,
+    ) -> Result<()> {
+        let chain = self.chain()?;
+        if must_exist && self.stored_path(&asset.asset_id).is_none() {
+            return Err(AssetError::NotFound.into());
+        }
+        self.verify_admin(operation, &asset.asset_id, auth)?;
 
-    fn write(&self) -> Result<()> {
-        let dir = self.path.parent().unwrap();
-        let ns_dir = self.ns_path.as_ref().map(|path| path.parent().unwrap());
+        let _asset_lock = self.lock_asset(&asset.asset_id);
+        self.invalidate_link_cache(&asset.asset_id);
+        let verified = asset.verify_capturing(Some(chain), &self.ctx())?;
+        let icon = self.prepare_icon(verified.icon.as_deref())?;
+        let asset = &asset.with_issuance_block(verified.issuance_block);
 
-        if !dir.exists() {
-            fs::create_dir(&dir)?;
+        let _lock = self.write_lock.lock().unwrap();
+        let asset_fh = self.file_handle(asset);
+        let previous = self.load(&asset.asset_id)?;
+        if must_exist && previous.is_none() {
+            return Err(AssetError::NotFound.into());
         }
-        if let Some(ns_dir) = ns_dir {
-            if !ns_dir.exists() {
-                fs::create_dir(&ns_dir)?;
+
+        // the entity/ticker may only be taken by the asset being replaced
+        let same_ns = previous.as_ref().map_or(false, |previous| {
+            make_unique_ns_filename(&previous.fields.entity, previous.fields.ticker.as_ref())
+                == make_unique_ns_filename(&asset.fields.entity, asset.fields.ticker.as_ref())
+        });
+        if !same_ns && asset_fh.ns_exists() {
+            return Err(AssetError::Conflict(NS_CONFLICT.into()).into());
+        }
+        self.check_uniqueness(asset)?;
+
+        if let Some(previous) = &previous {
+            debug!("admin overwriting asset {:?}", asset.asset_id);
+            self.file_handle(previous).delete()?;
+            self.update_field_index(previous, false);
+        }
+        asset_fh.write()?;
+        let previous_icon = self.icon(&asset.asset_id)?;
+        self.remove_icons(&asset.asset_id)?;
+        if let Some(icon) = icon {
+            self.write_icon(&asset.asset_id, icon)
+                .context("failed storing icon")?;
+        }
+        let previous_proof = self
+            .keep_proof(&asset.asset_id, verified.proof.as_ref())
+            .context("failed writing proof evidence")?;
+
+        let event = if previous.is_some() {
+            HookEvent::Update
+        } else {
+            HookEvent::Add
+        };
+        let abs_path = asset_fh.abs_path()?;
+        if let Err(err) = self
+            .update_index(asset, true)
+            .context("failed updating index")
+            .and_then(|()| {
+                self.exec_hook(asset, &abs_path, event, &[])
+                    .context("hook script failed")
+            })
+        {
+            warn!("hook failed: {:?}", err);
+            // restore the previous asset (if any) when the hook fails
+            asset_fh.delete()?;
+            self.remove_icons(&asset.asset_id)?;
+            match &previous {
+                Some(previous) => {
+                    self.file_handle(previous).write()?;
+                    self.update_field_index(previous, true);
+                    self.update_index(previous, true)?;
+                }
+                None => self.update_index(asset, false)?,
             }
+            if let Some(previous_icon) = previous_icon {
+                self.write_icon(&asset.asset_id, previous_icon)?;
+            }
+            self.restore_proof(&asset.asset_id, previous_proof)?;
+            bail!(err)
         }
 
-        fs::write(&self.path, serde_json::to_string(&self.asset)?)
-            .context("failed writing asset to fs")?;
+        self.append_changelog(&asset.asset_id, ChangeAction::Add, &asset_fh.read()?)
+            .context("failed writing changelog")?;
+        self.update_field_index(asset, true);
 
-        if let Some(ns_path) = &self.ns_path {
-            fs::write(ns_path, self.asset.asset_id.to_hex())
-                .context("failed writing asset map to fs")?;
+        Ok(())
+    }
+
+    // Destructive operations additionally require the admin's authorization when an admin key is
+    // configured (see RegistryBuilder::admin_pubkey())
+    fn require_admin(
+        &self,
+        operation: &str,
+        asset_id: &AssetId,
+        auth: Option<&AdminAuth>,
+    ) -> Result<()> {
+        if self.admin_pubkey.is_none() {
+            return Ok(());
+        }
+        let auth = auth.or_err(AssetError::Unauthorized(
+            "admin authorization required".into(),
+        ))?;
+        self.verify_admin(operation, asset_id, auth)
+    }
+
+    // Verify the admin authorization for the operation. Authorizations are single-use and must
+    // be recent, and the number of accepted operations is rate limited. Used authorizations are
+    // recorded per operation and asset in the registry directory, so that they cannot be
+    // replayed after a restart either.
+    fn verify_admin(&self, operation: &str, asset_id: &AssetId, auth: &AdminAuth) -> Result<()> {
+        let admin_pubkey = self.admin_pubkey.as_ref().or_err(AssetError::Unauthorized(
+            "admin operations are disabled".into(),
+        ))?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        if auth.timestamp > now + ADMIN_AUTH_MAX_SKEW
+            || now.saturating_sub(auth.timestamp) > ADMIN_AUTH_MAX_AGE
+        {
+            return Err(AssetError::Unauthorized("admin authorization expired".into()).into());
+        }
+
+        let msg = format_admin_sig_msg(operation, asset_id, auth.timestamp);
+        verify_bitcoin_msg(&EC, admin_pubkey, &auth.signature, &msg)
+            .context(AssetError::Unauthorized("invalid admin signature".into()))?;
+
+        // also serializes the updates of the used authorizations file
+        let mut state = self.admin_state.lock().unwrap();
+        let key = format!("{}:{}", operation, asset_id.to_hex());
+        let mut used = self.used_admin_auths()?;
+        if used.get(&key).map_or(false, |&last| auth.timestamp <= last) {
+            return Err(
+                AssetError::Unauthorized("admin authorization was already used".into()).into(),
+            );
+        }
+        state
+            .recent
+            .retain(|accepted| accepted.elapsed() < ADMIN_RATE_WINDOW);
+        if state.recent.len() >= ADMIN_RATE_LIMIT {
+            return Err(AssetError::RateLimited.into());
         }
 
+        // authorizations this old are rejected as expired anyway
+        used.retain(|_, last| now.saturating_sub(*last) <= ADMIN_AUTH_MAX_AGE);
+        used.insert(key, auth.timestamp);
+        fs::create_dir_all(&self.directory)?;
+        write_atomic(
+            &self.directory.join(ADMIN_AUTHS_FILE),
+            serde_json::to_string(&used)?.as_bytes(),
+        )
+        .context("failed writing admin authorizations file")?;
+        state.recent.push(Instant::now());
+
+        info!("authorized admin {} of {:?}", operation, asset_id);
         Ok(())
     }
 
-    fn delete(&self) -> Result<()> {
-        if self.exists() {
-            fs::remove_file(&self.path)?;
+    fn used_admin_auths(&self) -> Result<BTreeMap<String, u64>> {
+        let path = self.directory.join(ADMIN_AUTHS_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
         }
-        if self.ns_exists() {
-            fs::remove_file(self.ns_path.as_ref().unwrap())?;
+        Ok(
+            serde_json::from_slice(&fs::read(path)?)
+                .context("invalid admin authorizations file")?,
+        )
+    }
+
+    fn proof_path(&self, asset_id: &AssetId) -> path::PathBuf {
+        self.directory
+            .join(PROOFS_DIR)
+            .join(format!("{}.json", asset_id.to_hex()))
+    }
+
+    // Keep the verified proof as evidence, when enabled. Returns the raw proof previously kept for
+    // the asset (if any), to restore with restore_proof() if the write is rolled back.
+    fn keep_proof(&self, asset_id: &AssetId, proof: Option<&LinkProof>) -> Result<Option<Vec<u8>>> {
+        let path = self.proof_path(asset_id);
+        let previous = if path.exists() {
+            Some(fs::read(&path)?)
+        } else {
+            None
+        };
+        if let (true, Some(proof)) = (self.keep_proofs, proof) {
+            let dir = path.parent().req()?;
+            if !dir.exists() {
+                fs::create_dir(&dir)?;
+            }
+            write_atomic(&path, serde_json::to_string(proof)?.as_bytes())?;
+        }
+        Ok(previous)
+    }
+
+    fn restore_proof(&self, asset_id: &AssetId, previous: Option<Vec<u8>>) -> Result<()> {
+        let path = self.proof_path(asset_id);
+        match previous {
+            Some(previous) => write_atomic(&path, &previous)?,
+            None if path.exists() => fs::remove_file(&path)?,
+            None => (),
         }
         Ok(())
     }
-}
 
-fn make_unique_ns_filename(entity: &AssetEntity, ticker: Option<&String>) -> Option<String> {
-    ticker.map(|ticker| format!("{}@{}", ticker, entity))
+    // The domain proof kept as evidence when the asset was written, if any. Proofs are retained
+    // after the asset is deleted.
+    pub fn proof_evidence(&self, asset_id: &AssetId) -> Result<Option<LinkProof>> {
+        let path = self.proof_path(asset_id);
+
+        Ok(if path.exists() {
+            Some(serde_json::from_slice(&fs::read(path)?).context("invalid proof evidence")?)
+        } else {
+            None
+        })
+    }
+
+    // Normalize the icon fetched during verification for storage, when enabled
+    fn prepare_icon(&self, icon: Option<&[u8]>) -> Result<Option<(Vec<u8>, IconFormat)>> {
+        match (icon, self.store_icons) {
+            (Some(icon), true) => Ok(Some(normalize_icon(icon)?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn write_icon(&self, asset_id: &AssetId, (icon, format): (Vec<u8>, IconFormat)) -> Result<()> {
+        let path = self.path_for(asset_id).with_extension(format.extension());
+        write_atomic(&path, &icon)?;
+        Ok(())
+    }
+
+    fn remove_icons(&self, asset_id: &AssetId) -> Result<()> {
+        for extension in ICON_EXTENSIONS {
+            let path = self.path_for(asset_id).with_extension(extension);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The stored icon of the asset and its format, if any. Only PNGs are stored, SVGs stored by
+    // earlier versions are not served (they are removed along with the asset).
+    pub fn icon(&self, asset_id: &AssetId) -> Result<Option<(Vec<u8>, IconFormat)>> {
+        let format = IconFormat::Png;
+        let path = self.path_for(asset_id).with_extension(format.extension());
+        Ok(if path.exists() {
+            Some((fs::read(path)?, format))
+        } else {
+            None
+        })
+    }
+
+    fn append_changelog(
+        &self,
+        asset_id: &AssetId,
+        action: ChangeAction,
+        contents: &[u8],
+    ) -> Result<()> {
+        let entry = ChangelogEntry {
+            asset_id: *asset_id,
+            action,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            content_hash: sha256::Hash::hash(contents),
+        };
+
+        let mut changelog = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.directory.join(CHANGELOG_FILE))?;
+        writeln!(changelog, "{}", serde_json::to_string(&entry)?)?;
+
+        Ok(())
+    }
+
+    pub fn changelog(&self) -> Result<Vec<ChangelogEntry>> {
+        let path = self.directory.join(CHANGELOG_FILE);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        BufReader::new(fs::File::open(path)?)
+            .lines()
+            .map(|line| -> Result<ChangelogEntry> {
+                Ok(serde_json::from_str(&line?).context("invalid changelog entry")?)
+            })
+            .collect()
+    }
+
+    // The changelog entries newest first, reading the changelog backwards so that only its tail
+    // is read when stopping early
+    fn changelog_rev(&self) -> Result<impl Iterator<Item = Result<ChangelogEntry>>> {
+        let path = self.directory.join(CHANGELOG_FILE);
+        let lines = if path.exists() {
+            Some(RevLines::new(fs::File::open(path)?, CHANGELOG_BLOCK_SIZE)?)
+        } else {
+            None
+        };
+        Ok(lines
+            .into_iter()
+            .flatten()
+            .map(|line| -> Result<ChangelogEntry> {
+                Ok(serde_json::from_slice(&line?).context("invalid changelog entry")?)
+            }))
+    }
+
+    // Re-verify the assets updated since the given checkpoint (inclusive) against their latest
+    // changelog entry. Added (or normalized) assets are checked to match the logged content hash
+    // and fully verified, deleted assets are checked to be gone from storage.
+    pub fn verify_changelog_tail(&self, since: SystemTime) -> Result<Vec<(AssetId, Result<()>)>> {
+        self.ensure_writable()?;
+        let since = since.duration_since(UNIX_EPOCH)?.as_secs();
+
+        // only the latest change of each asset is relevant
+        let mut seen = HashSet::new();
+        let mut entries = vec![];
+        for entry in self.changelog_rev()? {
+            let entry = entry?;
+            if entry.timestamp < since {
+                break;
+            }
+            if seen.insert(entry.asset_id) {
+                entries.push(entry);
+            }
+        }
+        entries.reverse();
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.asset_id, self.verify_changelog_entry(&entry)))
+            .collect())
+    }
+
+    fn verify_changelog_entry(&self, entry: &ChangelogEntry) -> Result<()> {
+        let contents = self.read_raw(&entry.asset_id)?;
+
+        match entry.action {
+            ChangeAction::Add | ChangeAction::Normalize => {
+                let contents = contents.or_err("asset missing from storage")?;
+                ensure!(
+                    sha256::Hash::hash(&contents) == entry.content_hash,
+                    "stored asset does not match changelog"
+                );
+                let asset: Asset = serde_json::from_slice(&contents)?;
+                asset.verify_with(Some(self.chain()?), &self.reverify_ctx())?;
+            }
+            ChangeAction::Delete => {
+                ensure!(contents.is_none(), "deleted asset still present in storage")
+            }
+        }
+
+        debug!("verified changelog entry {:?}", entry);
+        Ok(())
+    }
+
+    // Re-verify all stored assets using up to `concurrency` worker threads, each verifying one
+    // asset at a time. The on-chain issuance is checked against the chain's asset index, which is
+    // fetched in batches upfront when the backend supports it, or against the issuance
+    // transaction for backends without one. Domain links verified within the ttl of the context's
+    // link cache (if any) are not fetched again. Failing to load an asset is reported as its
+    // result rather than aborting the run. Results are in asset id order.
+    pub fn verify_all(self: Arc<Self>, concurrency: usize) -> Result<Vec<(AssetId, Result<()>)>> {
+        let chain = self.chain()?;
+        let asset_ids = self.list()?;
+        let assets_data: Option<HashMap<AssetId, Option<Value>>> = if chain.has_asset_index() {
+            let assets_data = chain.get_assets(&asset_ids)?;
+            Some(asset_ids.iter().cloned().zip(assets_data).collect())
+        } else {
+            None
+        };
+
+        let ctx = Arc::new(self.reverify_ctx());
+        let assets_data = Arc::new(assets_data);
+        let queue = Arc::new(Mutex::new(asset_ids.clone().into_iter()));
+        let (tx, rx) = mpsc::channel();
+
+        let workers: Vec<_> = (0..concurrency.max(1).min(asset_ids.len()))
+            .map(|_| {
+                let (registry, queue, tx) = (Arc::clone(&self), Arc::clone(&queue), tx.clone());
+                let (ctx, assets_data) = (Arc::clone(&ctx), Arc::clone(&assets_data));
+                thread::spawn(move || loop {
+                    let asset_id = match queue.lock().unwrap().next() {
+                        Some(asset_id) => asset_id,
+                        None => break,
+                    };
+                    let res = registry.reverify(&asset_id, &ctx, assets_data.as_ref().as_ref());
+                    if let Err(err) = &res {
+                        warn!(
+                            "asset {} failed re-verification: {}",
+                            asset_id.to_hex(),
+                            err
+                        );
+                    }
+                    tx.send((asset_id, res)).unwrap();
+                })
+            })
+            .collect();
+        drop(tx);
+
+        let mut results: HashMap<AssetId, Result<()>> = rx.iter().collect();
+        for worker in workers {
+            worker
+                .join()
+                .map_err(|_| format_err!("re-verification worker panicked"))?;
+        }
+
+        Ok(asset_ids
+            .into_iter()
+            .filter_map(|asset_id| Some((asset_id, results.remove(&asset_id)?)))
+            .collect())
+    }
+
+    // Re-verify the stored asset, against its prefetched on-chain data when available
+    fn reverify(
+        &self,
+        asset_id: &AssetId,
+        ctx: &VerifyContext,
+        assets_data: Option<&HashMap<AssetId, Option<Value>>>,
+    ) -> Result<()> {
+        let asset = self.load(asset_id)?.or_err("asset missing from storage")?;
+        match assets_data {
+            Some(assets_data) => {
+                let asset_data = assets_data.get(asset_id).and_then(Option::as_ref);
+                verify_with_chain_data(&asset, asset_data, ctx)
+            }
+            None => asset.verify_with(Some(self.chain()?), ctx),
+        }
+    }
+
+    // Check that the stored asset is still issued on-chain as it was when accepted, without
+    // re-verifying anything else (like its domain link). Only fails if the asset is not stored or
+    // the chain backend cannot be reached, so that callers polling it periodically can tell
+    // transient failures apart from assets that need attention.
+    pub fn audit(&self, asset_id: &AssetId) -> Result<AuditStatus> {
+        let chain = self.chain()?;
+        let asset = self.load(asset_id)?.or_err(AssetError::NotFound)?;
+
+        let res = verify_asset_issuance_tx(chain, &asset, &self.ctx())
+            .and_then(|block| asset.check_issuance_block(block));
+
+        Ok(match res {
+            Ok(block) => AuditStatus::Confirmed {
+                height: block.block_height,
+            },
+            Err(err) => match asset_error(&err) {
+                Some(AssetError::IssuanceNotFound) | Some(AssetError::IssuanceUnconfirmed) => {
+                    AuditStatus::NotFound
+                }
+                _ if is_fetch_error(&err) => return Err(err),
+                _ => AuditStatus::Mismatch {
+                    error: join_err(&err),
+                },
+            },
+        })
+    }
+
+    // Audit all stored assets, in asset id order
+    pub fn audit_all(&self) -> Result<Vec<(AssetId, Result<AuditStatus>)>> {
+        self.ensure_writable()?;
+        Ok(self
+            .list()?
+            .into_iter()
+            .map(|asset_id| (asset_id, self.audit(&asset_id)))
+            .collect())
+    }
+
+    // Like verify_all(), but fetching all the domain proofs again even if recently verified
+    // according to the link cache
+    pub fn verify_all_forced(
+        self: Arc<Self>,
+        concurrency: usize,
+    ) -> Result<Vec<(AssetId, Result<()>)>> {
+        if let Some(cache) = &self.ctx().link_cache {
+            cache.clear();
+        }
+        self.verify_all(concurrency)
+    }
+
+    // New and overwritten assets are always verified against a freshly fetched proof
+    fn invalidate_link_cache(&self, asset_id: &AssetId) {
+        if let Some(cache) = &self.ctx().link_cache {
+            cache.invalidate(asset_id);
+        }
+    }
+
+    // Write the asset json files found in the directory (and its sub-directories) into the
+    // registry, like when restoring from a backup. With `verify`, each asset is fully verified
+    // like write() does. Otherwise only the checks that need no network access are run (the
+    // fields, the commitment and the fields matching the contract), to catch corrupt or edited
+    // files. Failures are reported per file without aborting the import.
+    pub fn import(
+        &self,
+        dir: &path::Path,
+        verify: bool,
+    ) -> Result<Vec<(path::PathBuf, Result<AssetId>)>> {
+        self.ensure_writable()?;
+
+        let mut paths = vec![];
+        find_json_files(dir, &mut paths)?;
+        paths.sort();
+
+        let results: Vec<_> = paths
+            .into_iter()
+            .map(|path| {
+                let res = self.import_file(&path, verify);
+                if let Err(err) = &res {
+                    warn!("failed importing {}: {}", path.display(), join_err(err));
+                }
+                (path, res)
+            })
+            .collect();
+
+        let imported = results.iter().filter(|(_, res)| res.is_ok()).count();
+        info!(
+            "imported {} assets, {} failed",
+            imported,
+            results.len() - imported
+        );
+        Ok(results)
+    }
+
+    fn import_file(&self, path: &path::Path, verify: bool) -> Result<AssetId> {
+        let asset = Asset::load(path.to_path_buf())?;
+        if verify {
+            self.write(&asset)?;
+        } else {
+            asset.verify_offline(&self.ctx())?;
+            let verified = Verified {
+                proof: None,
+                issuance_block: None,
+                icon: None,
+            };
+            self.commit_write(&asset, verified, None)?;
+        }
+        Ok(asset.asset_id)
+    }
+
+    // Stream all stored assets in the given format, in asset id order. Assets are loaded one at a
+    // time, so that large registries are never held in memory.
+    pub fn export<W: Write>(&self, mut writer: W, format: ExportFormat) -> Result<()> {
+        let mut paths: Vec<(String, path::PathBuf)> = self
+            .stored_paths()?
+            .into_iter()
+            .filter_map(|path| Some((path_asset_id(&path)?.to_hex(), path)))
+            .collect();
+        paths.sort();
+
+        if format == ExportFormat::JsonArray {
+            writer.write_all(b"[")?;
+        }
+        for (i, (_, path)) in paths.into_iter().enumerate() {
+            let asset = Asset::load(path)?;
+            match format {
+                ExportFormat::Ndjson => {
+                    serde_json::to_writer(&mut writer, &asset)?;
+                    writer.write_all(b"\n")?;
+                }
+                ExportFormat::JsonArray => {
+                    if i > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    serde_json::to_writer(&mut writer, &asset)?;
+                }
+            }
+        }
+        if format == ExportFormat::JsonArray {
+            writer.write_all(b"]")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Export all stored assets as a json map of asset id to Elements registry entries,
+    // in asset id order
+    pub fn export_elements_format<W: Write>(&self, mut writer: W) -> Result<()> {
+        let mut paths = self.stored_paths()?;
+        paths.sort();
+
+        writer.write_all(b"{")?;
+        for (i, path) in paths.into_iter().enumerate() {
+            let asset = Asset::load(path)?;
+            if i > 0 {
+                writer.write_all(b",")?;
+            }
+            serde_json::to_writer(&mut writer, &asset.asset_id.to_hex())?;
+            writer.write_all(b":")?;
+            serde_json::to_writer(&mut writer, &ElementsAssetEntry::from(&asset))?;
+        }
+        writer.write_all(b"}")?;
+        Ok(())
+    }
+
+    // All stored assets linked to the given entity (including DomainTxt assets for a DomainName
+    // and vice versa), in asset id order. Looked up in the index file when it is maintained,
+    // loading only the matching assets, or by loading every stored asset otherwise (skipping
+    // unreadable ones).
+    pub fn bundle_for_entity(&self, entity: &AssetEntity) -> Result<Vec<Asset>> {
+        let mut assets = match self.maintained_index()? {
+            Some(index) => {
+                let mut assets = vec![];
+                for (asset_id, entry) in index {
+                    if !entry.entity.same_entity(entity) {
+                        continue;
+                    }
+                    // might've been deleted without the index being updated (if the hook failed)
+                    if let Some(asset) = self.load(&AssetId::from_hex(&asset_id)?)? {
+                        assets.push(asset);
+                    }
+                }
+                assets
+            }
+            None => self
+                .readable_assets()?
+                .into_iter()
+                .filter(|asset| asset.entity().same_entity(entity))
+                .collect(),
+        };
+        assets.sort_by_key(|asset| asset.asset_id.to_hex());
+        Ok(assets)
+    }
+
+    // All stored assets with the given ticker (case-insensitive), in asset id order. Tickers are
+    // only unique per entity, so there may be multiple matches.
+    pub fn find_by_ticker(&self, ticker: &str) -> Result<Vec<Asset>> {
+        let asset_ids: Vec<AssetId> = self.with_field_index(|index| {
+            index
+                .tickers
+                .get(&ticker.to_lowercase())
+                .map_or_else(Vec::new, |asset_ids| asset_ids.iter().copied().collect())
+        })?;
+
+        let mut assets = vec![];
+        for asset_id in asset_ids {
+            // might've been deleted without the index being updated (if the hook failed)
+            if let Some(asset) = self.load(&asset_id)? {
+                assets.push(asset);
+            }
+        }
+        assets.sort_by_key(|asset| asset.asset_id.to_hex());
+        Ok(assets)
+    }
+
+    fn with_field_index<T>(&self, f: impl FnOnce(&FieldIndex) -> T) -> Result<T> {
+        let mut index = self.field_index.lock().unwrap();
+        if index.is_none() {
+            *index = Some(self.build_field_index()?);
+        }
+        Ok(f(index.as_ref().unwrap()))
+    }
+
+    // Built from the index file when it is maintained, or by loading every stored asset otherwise
+    fn build_field_index(&self) -> Result<FieldIndex> {
+        let mut index = FieldIndex::default();
+        match self.maintained_index() {
+            Ok(Some(entries)) => {
+                for (asset_id, entry) in entries {
+                    let asset_id = AssetId::from_hex(&asset_id)?;
+                    index.update_fields(asset_id, &entry.name, entry.ticker.as_ref(), true);
+                }
+                return Ok(index);
+            }
+            Ok(None) => (),
+            Err(err) => warn!("failed reading the index file: {}", join_err(&err)),
+        }
+        for asset in self.readable_assets()? {
+            index.update(&asset, true);
+        }
+        Ok(index)
+    }
+
+    // Keep the field index (if already built) in sync with added and removed assets
+    fn update_field_index(&self, asset: &Asset, added: bool) {
+        if let Some(index) = self.field_index.lock().unwrap().as_mut() {
+            index.update(asset, added);
+        }
+    }
+
+    // Add or remove the asset from the index file, if enabled. Must be called while holding the
+    // write lock.
+    fn update_index(&self, asset: &Asset, added: bool) -> Result<()> {
+        if !self.keep_index {
+            return Ok(());
+        }
+        let mut index = match self.index()? {
+            Some(index) => index,
+            None => self.build_index()?,
+        };
+        if added {
+            index.insert(asset.asset_id.to_hex(), IndexEntry::from(asset));
+        } else {
+            index.remove(&asset.asset_id.to_hex());
+        }
+        self.write_index(&index)
+    }
+
+    // The index of stored assets keyed by their hex asset id, if the index file exists
+    pub fn index(&self) -> Result<Option<BTreeMap<String, IndexEntry>>> {
+        let path = self.directory.join(INDEX_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let index = serde_json::from_slice(&fs::read(path)?).context("invalid index file")?;
+        Ok(Some(index))
+    }
+
+    // Rebuild the index file from the stored assets if it's missing or stale (not matching the
+    // stored assets, including ones updated since), as may happen for registries that had it
+    // disabled. Returns whether it was rebuilt.
+    pub fn sync_index(&self) -> Result<bool> {
+        self.ensure_writable()?;
+        let _lock = self.write_lock.lock().unwrap();
+
+        let stored = self.build_index()?;
+        if let Ok(Some(index)) = self.index() {
+            if index == stored {
+                return Ok(false);
+            }
+        }
+
+        info!("rebuilding the index of {} assets", stored.len());
+        self.write_index(&stored)?;
+        Ok(true)
+    }
+
+    // Re-hash every stored asset file and compare it to its checksum file (see
+    // RegistryBuilder::checksums()), returning the assets that fail. Catches corruption and
+    // tampering that still parses as a valid asset.
+    pub fn verify_storage(&self) -> Result<Vec<(AssetId, StorageIssue)>> {
+        let mut issues = vec![];
+        for path in self.stored_paths()? {
+            let asset_id = path_asset_id(&path).req()?;
+            let checksum_path = plain_path(&path).with_extension(CHECKSUM_EXTENSION);
+            if !checksum_path.exists() {
+                issues.push((asset_id, StorageIssue::MissingChecksum));
+                continue;
+            }
+            let recorded = fs::read_to_string(&checksum_path)?;
+            let expected = recorded.split_whitespace().next().unwrap_or("");
+            let actual = content_hash(&fs::read(&path)?);
+            if expected != actual {
+                warn!("asset file {} does not match its checksum", path.display());
+                issues.push((
+                    asset_id,
+                    StorageIssue::Mismatch {
+                        expected: expected.to_string(),
+                        actual,
+                    },
+                ));
+            }
+        }
+        issues.sort_by_key(|(asset_id, _)| asset_id.to_hex());
+        Ok(issues)
+    }
+
+    // Write the missing checksum files of stored assets, as for registries that had checksums
+    // disabled. Mismatching checksums are left as-is. Returns the number of written checksums.
+    pub fn sync_checksums(&self) -> Result<usize> {
+        self.ensure_writable()?;
+        let _lock = self.write_lock.lock().unwrap();
+
+        let mut written = 0;
+        for path in self.stored_paths()? {
+            let checksum_path = plain_path(&path).with_extension(CHECKSUM_EXTENSION);
+            if !checksum_path.exists() {
+                let checksum = format_checksum(&path, &fs::read(&path)?)?;
+                write_atomic(&checksum_path, checksum.as_bytes())?;
+                written += 1;
+            }
+        }
+        if written > 0 {
+            info!("wrote the missing checksums of {} assets", written);
+        }
+        Ok(written)
+    }
+
+    fn build_index(&self) -> Result<BTreeMap<String, IndexEntry>> {
+        Ok(self
+            .stored_assets()?
+            .iter()
+            .map(|asset| (asset.asset_id.to_hex(), IndexEntry::from(asset)))
+            .collect())
+    }
+
+    fn write_index(&self, index: &BTreeMap<String, IndexEntry>) -> Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        write_atomic(
+            &self.directory.join(INDEX_FILE),
+            &serde_json::to_vec(index)?,
+        )
+    }
+
+    // Asset counts for monitoring the registry's growth. Read from the index file when it is
+    // maintained (see RegistryBuilder::index()), or by loading every stored asset otherwise.
+    pub fn stats(&self) -> Result<RegistryStats> {
+        let assets: Vec<(AssetId, String)> = match self.maintained_index()? {
+            Some(index) => index
+                .into_iter()
+                .map(|(asset_id, entry)| {
+                    Ok((
+                        AssetId::from_hex(&asset_id)?,
+                        entry.entity.kind().to_string(),
+                    ))
+                })
+                .collect::<Result<_>>()?,
+            None => self
+                .stored_assets()?
+                .into_iter()
+                .map(|asset| (asset.asset_id, asset.fields.entity.kind().to_string()))
+                .collect(),
+        };
+
+        let mut stats = RegistryStats {
+            network: self.network(),
+            total_assets: assets.len(),
+            entity_types: BTreeMap::new(),
+            partitions: BTreeMap::new(),
+        };
+        for (asset_id, kind) in assets {
+            *stats.entity_types.entry(kind).or_default() += 1;
+            *stats
+                .partitions
+                .entry(self.partition_name(&asset_id))
+                .or_default() += 1;
+        }
+        Ok(stats)
+    }
+
+    fn maintained_index(&self) -> Result<Option<BTreeMap<String, IndexEntry>>> {
+        if self.keep_index {
+            self.index()
+        } else {
+            Ok(None)
+        }
+    }
+
+    // The partition directory of the asset relative to the registry directory (like `ab/cd`),
+    // empty for unpartitioned layouts
+    fn partition_name(&self, asset_id: &AssetId) -> String {
+        let path = self.path_for(asset_id);
+        let dir = path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(&self.directory).ok());
+        dir.map_or_else(String::new, |dir| dir.to_string_lossy().into_owned())
+    }
+
+    // Find registered assets with a name or ticker that looks similar to the given fields, to
+    // surface likely impersonation attempts for human review. This is advisory only.
+    // Returns the asset ids and their similarity score (0 to 1), most similar first.
+    pub fn find_similar(&self, fields: &AssetFields) -> Result<Vec<(AssetId, f64)>> {
+        let mut similar: Vec<(AssetId, f64)> = self
+            .readable_assets()?
+            .into_iter()
+            .filter_map(|asset| {
+                let mut score = similarity(&fields.name, &asset.fields.name);
+                if let (Some(ticker), Some(other_ticker)) = (&fields.ticker, &asset.fields.ticker) {
+                    score = score.max(similarity(ticker, other_ticker));
+                }
+                if score >= SIMILARITY_THRESHOLD {
+                    Some((asset.asset_id, score))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        similar.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        Ok(similar)
+    }
+
+    fn stored_assets(&self) -> Result<Vec<Asset>> {
+        self.stored_paths()?.into_iter().map(Asset::load).collect()
+    }
+
+    // Like stored_assets(), but skipping files that fail to load, for lookups that shouldn't fail
+    // for the whole registry because of a single corrupt file
+    fn readable_assets(&self) -> Result<Vec<Asset>> {
+        Ok(self
+            .stored_paths()?
+            .into_iter()
+            .filter_map(|path| match Asset::load(path.clone()) {
+                Ok(asset) => Some(asset),
+                Err(err) => {
+                    warn!("skipping unreadable {}: {}", path.display(), join_err(&err));
+                    None
+                }
+            })
+            .collect())
+    }
+
+    fn stored_paths(&self) -> Result<Vec<path::PathBuf>> {
+        let mut paths = vec![];
+        self.collect_paths(&self.directory, 0, &mut paths)?;
+        Ok(paths)
+    }
+
+    fn collect_paths(
+        &self,
+        dir: &path::Path,
+        level: usize,
+        paths: &mut Vec<path::PathBuf>,
+    ) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if level < self.layout.levels {
+                // skip non-partition directories (like _map) and files
+                if path.is_dir() && is_partition_dir(&path, Some(self.layout.width)) {
+                    self.collect_paths(&path, level + 1, paths)?;
+                }
+            } else if path.is_file() {
+                if path_asset_id(&path).is_some() {
+                    // when both variants are stored, only the current one is listed
+                    if find_stored(&plain_path(&path)).as_ref() == Some(&path) {
+                        paths.push(path);
+                    }
+                } else if !is_internal_file(&path)
+                    && !is_icon_file(&path)
+                    && !is_checksum_file(&path)
+                {
+                    warn!("skipping unexpected file {}", path.display());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Remove the given partition directory and its parents, as long as they're empty
+    fn prune_partition_dirs(&self, mut dir: &path::Path) -> Result<()> {
+        let base_dir = self.directory.canonicalize()?;
+        while dir != base_dir && fs::read_dir(dir)?.next().is_none() {
+            fs::remove_dir(dir)?;
+            dir = dir.parent().req()?;
+        }
+        Ok(())
+    }
+
+    // Move asset files stored under any other partition layout (including unpartitioned) to
+    // where the current layout expects them, pruning the directories left empty. Returns the
+    // number of moved assets. The hook script is not run for moved assets.
+    pub fn migrate_layout(&self) -> Result<usize> {
+        self.ensure_writable()?;
+        let _lock = self.write_lock.lock().unwrap();
+
+        let mut found = vec![];
+        find_asset_files(&self.directory, &mut found)?;
+
+        let mut moved = 0;
+        for path in found {
+            let asset_id = path_asset_id(&path).req()?;
+            let plain_new_path = self.path_for(&asset_id);
+            // compressed files are moved as-is
+            let new_path = if is_compressed(&path) {
+                compressed_path(&plain_new_path)
+            } else {
+                plain_new_path.clone()
+            };
+            if path == new_path {
+                continue;
+            }
+            ensure!(
+                find_stored(&plain_new_path).is_none(),
+                "asset {} is stored more than once",
+                asset_id.to_hex()
+            );
+
+            debug!("moving {} to {}", path.display(), new_path.display());
+            fs::create_dir_all(new_path.parent().req()?)?;
+            fs::rename(&path, &new_path)?;
+            // along with the stored icon and checksum, if any
+            let plain_old_path = plain_path(&path);
+            for extension in ICON_EXTENSIONS.iter().chain(&[CHECKSUM_EXTENSION]) {
+                if plain_old_path.with_extension(extension).exists() {
+                    fs::rename(
+                        plain_old_path.with_extension(extension),
+                        plain_new_path.with_extension(extension),
+                    )?;
+                }
+            }
+            moved += 1;
+
+            let old_dir = path.parent().req()?.canonicalize()?;
+            self.prune_partition_dirs(&old_dir)?;
+        }
+
+        info!("migrated {} assets to the {:?} layout", moved, self.layout);
+        Ok(moved)
+    }
+
+    // Rewrite every stored asset file in the registry's current serialization, as for rolling out
+    // a change to how assets are serialized. Each asset's commitment is re-verified first, and
+    // assets that no longer match it are reported and left untouched. Retained `contract_raw`
+    // bytes are kept as-is. Files are also compressed or decompressed to match the registry's
+    // compression setting. Returns whether each asset was rewritten (false when it was already
+    // normalized). Rewritten assets are logged to the changelog as normalizations, but the hook
+    // script is not run.
+    pub fn normalize_all(&self) -> Result<Vec<(AssetId, Result<bool>)>> {
+        self.ensure_writable()?;
+        let _lock = self.write_lock.lock().unwrap();
+
+        let mut results = vec![];
+        for path in self.stored_paths()? {
+            let asset_id = path_asset_id(&path).req()?;
+            let result = self.normalize_file(&asset_id, &path);
+            if let Err(err) = &result {
+                warn!("failed normalizing {}: {}", path.display(), err);
+            }
+            results.push((asset_id, result));
+        }
+
+        let rewritten = results
+            .iter()
+            .filter(|(_, r)| r.as_ref().ok() == Some(&true));
+        info!("normalized {} assets", rewritten.count());
+        Ok(results)
+    }
+
+    fn normalize_file(&self, asset_id: &AssetId, path: &path::Path) -> Result<bool> {
+        let contents = read_json_file(path)?;
+        let asset: Asset = serde_json::from_slice(&contents)?;
+        ensure!(
+            asset.asset_id == *asset_id,
+            "stored under the wrong asset id"
+        );
+        asset.verify_commitment()?;
+
+        let normalized = serde_json::to_vec(&asset)?;
+        if normalized == contents && is_compressed(path) == self.compress {
+            return Ok(false);
+        }
+
+        // the rewritten file must commit to the exact same contract, in the same canonical form
+        let reparsed: Asset = serde_json::from_slice(&normalized)?;
+        ensure!(
+            reparsed.contract_raw == asset.contract_raw
+                && canonical_contract_json(&reparsed.contract)?
+                    == canonical_contract_json(&asset.contract)?
+                && reparsed.contract_hash()? == asset.contract_hash()?,
+            "normalizing would change the contract hash"
+        );
+
+        debug!("rewriting {} in normalized form", path.display());
+        self.file_handle(&asset).write()?;
+        // the changelog hashes the uncompressed json, which only changes when reserialized
+        if normalized != contents {
+            self.append_changelog(asset_id, ChangeAction::Normalize, &normalized)
+                .context("failed writing changelog")?;
+        }
+        Ok(true)
+    }
+
+    // The ids of all stored assets, in asset id order
+    pub fn list(&self) -> Result<Vec<AssetId>> {
+        let mut asset_ids: Vec<AssetId> = self
+            .stored_paths()?
+            .iter()
+            .filter_map(|path| path_asset_id(path))
+            .collect();
+        asset_ids.sort_by_key(|asset_id| asset_id.to_hex());
+        Ok(asset_ids)
+    }
+
+    // A page of up to `limit` stored asset ids starting at `offset`, in asset id order, along with
+    // the total number of stored assets. Read from the index file when it is maintained, or by
+    // walking the file names otherwise (without loading the assets).
+    pub fn list_paged(&self, offset: usize, limit: usize) -> Result<AssetPage> {
+        let (asset_ids, total) = match self.maintained_index()? {
+            Some(index) => (
+                index
+                    .keys()
+                    .skip(offset)
+                    .take(limit)
+                    .map(|asset_id| Ok(AssetId::from_hex(asset_id)?))
+                    .collect::<Result<_>>()?,
+                index.len(),
+            ),
+            None => {
+                let asset_ids = self.list()?;
+                let total = asset_ids.len();
+                (
+                    asset_ids.into_iter().skip(offset).take(limit).collect(),
+                    total,
+                )
+            }
+        };
+        Ok(AssetPage {
+            asset_ids,
+            offset,
+            total,
+        })
+    }
+
+    // All stored assets, in asset id order
+    pub fn list_assets(&self) -> Result<Vec<Asset>> {
+        let mut assets = vec![];
+        for asset_id in self.list()? {
+            // might've been deleted since listing
+            if let Some(asset) = self.load(&asset_id)? {
+                assets.push(asset);
+            }
+        }
+        Ok(assets)
+    }
+
+    // The most recently added assets since the given time, newest first. Uses the changelog
+    // when available, or falls back to scanning the file modification times.
+    pub fn list_recent(&self, since: SystemTime, limit: usize) -> Result<Vec<Asset>> {
+        if self.directory.join(CHANGELOG_FILE).exists() {
+            let since = since.duration_since(UNIX_EPOCH)?.as_secs();
+            let mut seen = HashSet::new();
+            let mut assets = vec![];
+
+            for entry in self.changelog_rev()? {
+                let entry = entry?;
+                if assets.len() >= limit || entry.timestamp < since {
+                    break;
+                }
+                // normalizations don't make assets recent
+                if entry.action == ChangeAction::Normalize {
+                    continue;
+                }
+                // skip assets that were since deleted or already listed
+                if seen.insert(entry.asset_id) && entry.action == ChangeAction::Add {
+                    if let Some(asset) = self.load(&entry.asset_id)? {
+                        assets.push(asset);
+                    }
+                }
+            }
+            Ok(assets)
+        } else {
+            let mut recent = vec![];
+            for path in self.stored_paths()? {
+                let mtime = fs::metadata(&path)?.modified()?;
+                if mtime >= since {
+                    recent.push((mtime, path));
+                }
+            }
+            recent.sort_by(|a, b| b.0.cmp(&a.0));
+
+            recent
+                .into_iter()
+                .take(limit)
+                .map(|(_, path)| Asset::load(path))
+                .collect()
+        }
+    }
+
+    // The stored asset json bytes, decompressed
+    fn read_raw(&self, asset_id: &AssetId) -> Result<Option<Vec<u8>>> {
+        Ok(match self.stored_path(asset_id) {
+            Some(path) => Some(read_json_file(&path)?),
+            None => None,
+        })
+    }
+
+    fn file_handle<'a>(&self, asset: &'a Asset) -> AssetFileHandle<'a> {
+        AssetFileHandle::new(
+            asset,
+            self.path_for(&asset.asset_id),
+            &self.directory,
+            self.keep_checksums,
+            self.compress,
+        )
+    }
+
+    fn exec_hook(
+        &self,
+        asset: &Asset,
+        asset_path: &path::Path,
+        event: HookEvent,
+        signatures: &[Vec<u8>],
+    ) -> Result<()> {
+        enter_span!("exec_hook", asset_id = %asset.asset_id, event = %event);
+        match self.run_hook(asset, asset_path, event, signatures) {
+            Err(err) if !self.hook_fatal => {
+                warn!(
+                    "{} hook for {:?} failed, keeping the update: {}",
+                    event,
+                    asset.asset_id,
+                    join_err(&err)
+                );
+                Ok(())
+            }
+            res => res,
+        }
+    }
+
+    fn run_hook(
+        &self,
+        asset: &Asset,
+        asset_path: &path::Path,
+        event: HookEvent,
+        signatures: &[Vec<u8>],
+    ) -> Result<()> {
+        if let Some(cmd) = &self.hook_cmd {
+            debug!("running {} hook {} for {:?}", event, cmd, asset.asset_id);
+
+            // space-separated, in case of multiple signatures from multisig issuers
+            let mut envs = HashMap::new();
+            if !signatures.is_empty() {
+                let signatures: Vec<String> = signatures.iter().map(base64::encode).collect();
+                envs.insert("AUTHORIZING_SIG", signatures.join(" "));
+            }
+
+            // The hook runs while the write lock is held, so that hooks that publish the registry
+            // (like contrib/hook.sh committing to git) are serialized. It must therefore never
+            // block on input: the asset json is written to stdin from a separate thread (hooks
+            // may exit without reading it), which is then closed so hooks reading it get an EOF.
+            // Other descriptors are not leaked into the child, as std opens all of them with
+            // O_CLOEXEC.
+            let mut command = Command::new(cmd);
+            command
+                .current_dir(&self.directory)
+                .arg(asset.asset_id.to_hex())
+                .arg(asset_path.to_str().req()?)
+                .arg(event.to_string())
+                .envs(envs)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            // run the hook in a process group of its own, so that it can be killed on timeout
+            // along with any processes it spawned (see wait_with_timeout())
+            unsafe {
+                command.pre_exec(|| match libc::setpgid(0, 0) {
+                    0 => Ok(()),
+                    _ => Err(io::Error::last_os_error()),
+                });
+            }
+            let mut child = command.spawn()?;
+
+            let mut stdin = child.stdin.take().req()?;
+            let json = serde_json::to_vec(asset)?;
+            thread::spawn(move || stdin.write_all(&json).ok());
+
+            let output = match self.hook_timeout {
+                Some(timeout) => wait_with_timeout(child, timeout)?,
+                None => child.wait_with_output()?,
+            };
+            debug!(
+                "hook exited with {:?}\n## stdout: {}\n## stderr: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+
+            if !output.status.success() {
+                warn!(
+                    "{} hook for {:?} exited with {:?}: {}",
+                    event,
+                    asset.asset_id,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                bail!("hook script failed");
+            }
+        }
+        Ok(())
+    }
+
+    // Check the operational preconditions of the registry: the directory is readable and
+    // writable, the chain backend is reachable and the hook script (if any) is executable.
+    // Read-only registries only need the directory to be readable.
+    pub fn health(&self) -> HealthReport {
+        HealthReport {
+            directory: self.check_directory().into(),
+            chain: match &self.chain {
+                Some(chain) => chain.get_tip_height().map(|_| ()).into(),
+                None => HealthCheck::Skipped,
+            },
+            hook: match &self.hook_cmd {
+                Some(cmd) => check_executable(cmd).into(),
+                None => HealthCheck::Skipped,
+            },
+        }
+    }
+
+    fn check_directory(&self) -> Result<()> {
+        fs::read_dir(&self.directory).context("directory is not readable")?;
+        if self.chain.is_none() {
+            return Ok(());
+        }
+
+        // probe under the write lock, so that the hook never sees the probe file
+        let _lock = self.write_lock.lock().unwrap();
+        let test_path = self.directory.join(".health-check");
+        fs::write(&test_path, b"").context("directory is not writable")?;
+        fs::remove_file(&test_path).context("directory is not writable")?;
+        Ok(())
+    }
+
+    // The chain backend, which read-only registries don't have
+    pub fn chain(&self) -> Result<&ChainQuery> {
+        self.chain
+            .as_ref()
+            .ok_or_else(|| AssetError::ReadOnly.into())
+    }
+
+    // The message the issuer has to sign to remove the asset from this registry with the given
+    // sequence number (the legacy one for registries without an id)
+    pub fn deletion_msg(&self, asset_id: &AssetId, sequence: u64) -> String {
+        match &self.registry_id {
+            Some(registry_id) => deletion_sig_msg(asset_id, registry_id, sequence).to_string(),
+            None => legacy_deletion_sig_msg(asset_id),
+        }
+    }
+
+    // The message the issuer has to sign to authorize updating the asset in this registry with the
+    // given sequence number. Signed updates require a registry id.
+    pub fn update_msg(&self, asset_id: &AssetId, sequence: u64) -> Option<String> {
+        let registry_id = self.registry_id.as_ref()?;
+        Some(update_sig_msg(asset_id, registry_id, sequence).to_string())
+    }
+
+    // Verify the issuer's authorization to update the stored asset and record its sequence number,
+    // which is shared with deletions. To be called by the update path before writing the update.
+    pub fn authorize_update(
+        &self,
+        asset_id: &AssetId,
+        signatures: &[Vec<u8>],
+        sequence: u64,
+    ) -> Result<()> {
+        self.ensure_writable()?;
+        let registry_id = self
+            .registry_id
+            .as_ref()
+            .or_err("signed updates require a registry id")?;
+        let _lock = self.write_lock.lock().unwrap();
+        let asset = self.load(asset_id)?.or_err(AssetError::NotFound)?;
+        let signed_sequence = asset
+            .verify_update_sigs(signatures, registry_id, sequence)
+            .context("invalid update signature")?;
+        self.use_sequence(asset_id, Some(signed_sequence))
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.chain.is_none()
+    }
+
+    // Whether a write is in progress, holding the write lock. The hook runs while it is held (see
+    // run_hook()), and it is released once the write completes or fails.
+    pub fn is_write_locked(&self) -> bool {
+        matches!(self.write_lock.try_lock(), Err(TryLockError::WouldBlock))
+    }
+
+    // Whether the index file is maintained (see RegistryBuilder::index())
+    pub fn has_index(&self) -> bool {
+        self.keep_index
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        self.chain().map(|_| ())
+    }
+
+    // A snapshot of the verification context, which may be swapped by reload_policy()
+    pub fn context(&self) -> Arc<VerifyContext> {
+        Arc::clone(&self.ctx.read().unwrap())
+    }
+
+    fn ctx(&self) -> Arc<VerifyContext> {
+        self.context()
+    }
+
+    // The verification context for re-verifying stored assets (see VerifyContext::intake)
+    fn reverify_ctx(&self) -> VerifyContext {
+        VerifyContext {
+            intake: false,
+            ..(*self.ctx()).clone()
+        }
+    }
+
+    // Reload the policy files (see RegistryBuilder::policy_files()) and swap them into the
+    // verification context, logging what changed. Invalid files fail the reload, keeping the
+    // previously loaded lists. Returns whether anything changed.
+    pub fn reload_policy(&self) -> Result<bool> {
+        let files = match &self.policy_files {
+            Some(files) => files,
+            None => return Ok(false),
+        };
+        let _lock = self.write_lock.lock().unwrap();
+        let old_ctx = self.context();
+        let mut new_ctx = (*old_ctx).clone();
+        files
+            .apply(&mut new_ctx)
+            .context("invalid policy files, keeping the previous ones")?;
+
+        let mut changed = log_set_changes(
+            "reserved symbols",
+            &old_ctx.reserved_symbols,
+            &new_ctx.reserved_symbols,
+        );
+        let empty = HashSet::new();
+        changed |= log_set_changes(
+            "domain allowlist",
+            old_ctx.domain_allowlist.as_ref().unwrap_or(&empty),
+            new_ctx.domain_allowlist.as_ref().unwrap_or(&empty),
+        );
+        if changed {
+            *self.ctx.write().unwrap() = Arc::new(new_ctx);
+        }
+        Ok(changed)
+    }
+
+    pub fn network(&self) -> Network {
+        self.chain
+            .as_ref()
+            .map_or(self.ctx().network, ChainQuery::network)
+    }
+}
+
+// How asset files are partitioned into nested sub-directories named by the asset id's hex
+// prefix. For example, 2 levels of width 2 store assets under `ab/cd/abcd...json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionLayout {
+    levels: usize,
+    width: usize,
+}
+
+impl PartitionLayout {
+    pub fn new(levels: usize, width: usize) -> Result<Self> {
+        ensure!(levels == 0 || width > 0, "partition width must be positive");
+        ensure!(
+            levels * width <= 64,
+            "partition layout longer than the asset id"
+        );
+        Ok(PartitionLayout { levels, width })
+    }
+
+    // No sub-directories, all assets are stored directly in the registry directory
+    pub fn flat() -> Self {
+        PartitionLayout {
+            levels: 0,
+            width: 0,
+        }
+    }
+}
+
+impl Default for PartitionLayout {
+    // A single level of 2 hex characters (256 sub-directories)
+    fn default() -> Self {
+        PartitionLayout {
+            levels: 1,
+            width: 2,
+        }
+    }
+}
+
+// The output format of Registry::export()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    // One asset json per line
+    Ndjson,
+    // A single json array of assets
+    JsonArray,
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "ndjson" => ExportFormat::Ndjson,
+            "json" => ExportFormat::JsonArray,
+            _ => bail!("unknown export format {}", s),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UniquenessPolicy {
+    None,
+    TickerUnique,
+    NameUnique,
+    Both,
+}
+
+impl UniquenessPolicy {
+    fn ticker_unique(self) -> bool {
+        self == UniquenessPolicy::TickerUnique || self == UniquenessPolicy::Both
+    }
+
+    fn name_unique(self) -> bool {
+        self == UniquenessPolicy::NameUnique || self == UniquenessPolicy::Both
+    }
+}
+
+impl FromStr for UniquenessPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "none" => UniquenessPolicy::None,
+            "ticker" => UniquenessPolicy::TickerUnique,
+            "name" => UniquenessPolicy::NameUnique,
+            "both" => UniquenessPolicy::Both,
+            _ => bail!("unknown uniqueness policy {}", s),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub directory: HealthCheck,
+    pub chain: HealthCheck,
+    pub hook: HealthCheck,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        [&self.directory, &self.chain, &self.hook]
+            .iter()
+            .all(|check| !matches!(check, HealthCheck::Failed(_)))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegistryStats {
+    pub network: Network,
+    pub total_assets: usize,
+    // asset counts by entity type (like `domain`)
+    pub entity_types: BTreeMap<String, usize>,
+    // asset counts by partition directory (see Registry::partition_name())
+    pub partitions: BTreeMap<String, usize>,
+}
+
+// A page of stored asset ids, as returned by Registry::list_paged()
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssetPage {
+    pub asset_ids: Vec<AssetId>,
+    pub offset: usize,
+    // the number of stored assets across all pages
+    pub total: usize,
+}
+
+// Stored asset files that failed Registry::verify_storage()
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum StorageIssue {
+    // The file was modified since it was written by the registry
+    Mismatch { expected: String, actual: String },
+    // No checksum was recorded for the file
+    MissingChecksum,
+}
+
+// The on-chain state of a stored asset's issuance, as reported by Registry::audit()
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum AuditStatus {
+    // Confirmed in the same block as when accepted (if recorded)
+    Confirmed { height: usize },
+    // The issuance transaction is missing or no longer confirmed, like after a reorg
+    NotFound,
+    // The issuance no longer matches the asset, or was reorged into a different block
+    Mismatch { error: String },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", content = "error", rename_all = "lowercase")]
+pub enum HealthCheck {
+    Ok,
+    Failed(String),
+    Skipped,
+}
+
+impl From<Result<()>> for HealthCheck {
+    fn from(res: Result<()>) -> Self {
+        match res {
+            Ok(()) => HealthCheck::Ok,
+            Err(err) => HealthCheck::Failed(join_err(&err)),
+        }
+    }
+}
+
+// Failures to reach the chain backend, as opposed to failed verification
+fn is_fetch_error(err: &Error) -> bool {
+    err.iter_chain()
+        .any(|cause| cause.downcast_ref::<reqwest::Error>().is_some())
+}
+
+// Check that the command refers to an executable file, either directly or through $PATH
+fn check_executable(cmd: &str) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let candidates: Vec<path::PathBuf> = if cmd.contains('/') {
+        vec![path::PathBuf::from(cmd)]
+    } else {
+        std::env::var_os("PATH")
+            .map(|paths| {
+                std::env::split_paths(&paths)
+                    .map(|dir| dir.join(cmd))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let executable = candidates.iter().any(|path| {
+        fs::metadata(path).map_or(false, |meta| {
+            meta.is_file() && meta.permissions().mode() & 0o111 != 0
+        })
+    });
+    ensure!(executable, "hook command {} is not executable", cmd);
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeAction {
+    Add,
+    Delete,
+    // rewritten in normalized form by normalize_all(), with the same asset
+    Normalize,
+}
+
+// The kind of update, passed to the hook as its third argument
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    Add,
+    Update,
+    Delete,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            HookEvent::Add => "add",
+            HookEvent::Update => "update",
+            HookEvent::Delete => "delete",
+        })
+    }
+}
+
+// The summary of a stored asset kept in the index file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub name: String,
+    pub ticker: Option<String>,
+    pub entity: AssetEntity,
+}
+
+impl From<&Asset> for IndexEntry {
+    fn from(asset: &Asset) -> Self {
+        IndexEntry {
+            name: asset.fields.name.clone(),
+            ticker: asset.fields.ticker.clone(),
+            entity: asset.fields.entity.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangelogEntry {
+    pub asset_id: AssetId,
+    pub action: ChangeAction,
+    // unix timestamp in seconds
+    pub timestamp: u64,
+    // sha256 of the asset json (as written, or prior to deletion), decompressed for assets that
+    // are stored compressed
+    pub content_hash: sha256::Hash,
+}
+
+// Lowercased tickers and names to the assets using them
+#[derive(Debug, Default)]
+struct FieldIndex {
+    tickers: HashMap<String, HashSet<AssetId>>,
+    names: HashMap<String, HashSet<AssetId>>,
+}
+
+impl FieldIndex {
+    fn update(&mut self, asset: &Asset, added: bool) {
+        let fields = &asset.fields;
+        self.update_fields(asset.asset_id, &fields.name, fields.ticker.as_ref(), added);
+    }
+
+    fn update_fields(
+        &mut self,
+        asset_id: AssetId,
+        name: &str,
+        ticker: Option<&String>,
+        added: bool,
+    ) {
+        let mut entries = vec![self.names.entry(name.to_lowercase())];
+        if let Some(ticker) = ticker {
+            entries.push(self.tickers.entry(ticker.to_lowercase()));
+        }
+        for entry in entries {
+            let asset_ids = entry.or_default();
+            if added {
+                asset_ids.insert(asset_id);
+            } else {
+                asset_ids.remove(&asset_id);
+            }
+        }
+    }
+}
+
+// Held during the verification of an asset. Unlike a MutexGuard, its guard may be held across
+// awaits and moved between threads, as write_async() does.
+#[derive(Debug, Default)]
+struct AssetLock {
+    held: Mutex<bool>,
+    released: Condvar,
+}
+
+struct AssetLockGuard(Arc<AssetLock>);
+
+impl AssetLockGuard {
+    fn acquire(lock: Arc<AssetLock>) -> Self {
+        let mut held = lock.held.lock().unwrap();
+        while *held {
+            held = lock.released.wait(held).unwrap();
+        }
+        *held = true;
+        drop(held);
+        AssetLockGuard(lock)
+    }
+
+    #[cfg(test)]
+    fn try_acquire(lock: Arc<AssetLock>) -> Option<Self> {
+        let mut held = lock.held.lock().unwrap();
+        if *held {
+            return None;
+        }
+        *held = true;
+        drop(held);
+        Some(AssetLockGuard(lock))
+    }
+}
+
+impl Drop for AssetLockGuard {
+    fn drop(&mut self) {
+        *self.0.held.lock().unwrap() = false;
+        self.0.released.notify_one();
+    }
+}
+
+// The lines of a file from last to first, read a block at a time from its end
+struct RevLines {
+    file: fs::File,
+    block_size: u64,
+    // the offset up to which the file is left to read
+    pos: u64,
+    // the start of the earliest block read, which may be the end of a line in the previous block
+    partial: Vec<u8>,
+    // the complete lines of the earliest block read, in order
+    lines: Vec<Vec<u8>>,
+}
+
+impl RevLines {
+    fn new(file: fs::File, block_size: u64) -> Result<Self> {
+        let pos = file.metadata()?.len();
+        Ok(RevLines {
+            file,
+            block_size,
+            pos,
+            partial: vec![],
+            lines: vec![],
+        })
+    }
+
+    fn read_block(&mut self) -> io::Result<()> {
+        let size = self.pos.min(self.block_size);
+        self.pos -= size;
+        let mut block = vec![0; size as usize];
+        self.file.seek(SeekFrom::Start(self.pos))?;
+        self.file.read_exact(&mut block)?;
+        block.extend_from_slice(&self.partial);
+
+        let mut parts = block.split(|b| *b == b'\n');
+        self.partial = parts.next().unwrap().to_vec();
+        self.lines = parts
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_vec())
+            .collect();
+        Ok(())
+    }
+}
+
+impl Iterator for RevLines {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.lines.pop() {
+                return Some(Ok(line));
+            }
+            if self.pos == 0 {
+                let partial = std::mem::take(&mut self.partial);
+                return if partial.is_empty() {
+                    None
+                } else {
+                    Some(Ok(partial))
+                };
+            }
+            if let Err(err) = self.read_block() {
+                // stop reading after a failure
+                self.pos = 0;
+                self.partial.clear();
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+struct AssetFileHandle<'a> {
+    asset: &'a Asset,
+    // directory and full path to main asset json file, without the compressed extension
+    path: path::PathBuf,
+    // path for unique namespace identifier file
+    ns_path: Option<path::PathBuf>,
+    // whether to write a checksum file alongside the asset file
+    checksum: bool,
+    // whether to write the asset file compressed
+    compress: bool,
+}
+
+impl<'a> AssetFileHandle<'a> {
+    fn new(
+        asset: &'a Asset,
+        path: path::PathBuf,
+        base_dir: &path::Path,
+        checksum: bool,
+        compress: bool,
+    ) -> Self {
+        // XXX use sub-dirs inside map too, use the hash of the unique_key as filename?
+        let ns_dir = base_dir.join("_map");
+        let ns_path = make_unique_ns_filename(&asset.fields.entity, asset.fields.ticker.as_ref())
+            .map(|filename| ns_dir.join(filename));
+
+        AssetFileHandle {
+            asset,
+            path,
+            ns_path,
+            checksum,
+            compress,
+        }
+    }
+
+    // The asset file as currently stored, compressed or not
+    fn stored_path(&self) -> Option<path::PathBuf> {
+        find_stored(&self.path)
+    }
+
+    fn exists(&self) -> bool {
+        self.stored_path().is_some()
+    }
+
+    fn ns_exists(&self) -> bool {
+        self.ns_path.as_ref().map_or(false, |path| path.exists())
+    }
+
+    fn abs_path(&self) -> Result<path::PathBuf> {
+        Ok(self.stored_path().req()?.canonicalize()?)
+    }
+
+    fn checksum_path(&self) -> path::PathBuf {
+        self.path.with_extension(CHECKSUM_EXTENSION)
+    }
+
+    // The asset json bytes, decompressed
+    fn read(&self) -> Result<Vec<u8>> {
+        read_json_file(&self.stored_path().req()?)
+    }
+
+    fn write(&self) -> Result<()> {
+        let dir = self.path.parent().unwrap();
+        let ns_dir = self.ns_path.as_ref().map(|path| path.parent().unwrap());
+
+        if !dir.exists() {
+            fs::create_dir_all(&dir)?;
+        }
+        if let Some(ns_dir) = ns_dir {
+            if !ns_dir.exists() {
+                fs::create_dir(&ns_dir)?;
+            }
+        }
+
+        let json = serde_json::to_vec(&self.asset)?;
+        let (path, contents, other_path) = if self.compress {
+            (compressed_path(&self.path), gzip(&json)?, self.path.clone())
+        } else {
+            (self.path.clone(), json, compressed_path(&self.path))
+        };
+        write_atomic(&path, &contents).context("failed writing asset to fs")?;
+        // replaces the file stored in the other format, if any (as when normalizing). removed only
+        // once the new one is in place, find_stored() prefers the newer one until then.
+        if other_path.exists() {
+            fs::remove_file(&other_path)?;
+        }
+        if self.checksum {
+            let checksum = format_checksum(&path, &contents)?;
+            write_atomic(&self.checksum_path(), checksum.as_bytes())
+                .context("failed writing asset checksum to fs")?;
+        }
+
+        if let Some(ns_path) = &self.ns_path {
+            write_atomic(ns_path, self.asset.asset_id.to_hex().as_bytes())
+                .context("failed writing asset map to fs")?;
+        }
+
+        Ok(())
+    }
+
+    // Restore the asset file at its original path with its stored contents, after delete()
+    fn restore(&self, path: &path::Path, contents: &[u8]) -> Result<()> {
+        write_atomic(path, contents).context("failed restoring asset to fs")?;
+        if self.checksum {
+            let checksum = format_checksum(path, contents)?;
+            write_atomic(&self.checksum_path(), checksum.as_bytes())
+                .context("failed restoring asset checksum to fs")?;
+        }
+        if let Some(ns_path) = &self.ns_path {
+            write_atomic(ns_path, self.asset.asset_id.to_hex().as_bytes())
+                .context("failed restoring asset map to fs")?;
+        }
+        Ok(())
+    }
+
+    fn delete(&self) -> Result<()> {
+        if let Some(path) = self.stored_path() {
+            fs::remove_file(path)?;
+        }
+        // removed even when checksums are no longer enabled, to not leave stale ones behind
+        if self.checksum_path().exists() {
+            fs::remove_file(self.checksum_path())?;
+        }
+        if self.ns_exists() {
+            fs::remove_file(self.ns_path.as_ref().unwrap())?;
+        }
+        Ok(())
+    }
+}
+
+fn verify_with_chain_data(
+    asset: &Asset,
+    asset_data: Option<&Value>,
+    ctx: &VerifyContext,
+) -> Result<()> {
+    asset.verify_with(None, ctx)?;
+    let asset_data = asset_data.or_err("asset not found on chain")?;
+    verify_asset_issuance_data(asset, asset_data).context("failed verifying on-chain issuance")?;
+    Ok(())
+}
+
+pub fn format_admin_sig_msg(operation: &str, asset_id: &AssetId, timestamp: u64) -> String {
+    format!(
+        "admin {} {} in registry at {}",
+        operation, asset_id, timestamp
+    )
+}
+
+// The asset id of a `<asset id>.json` or `<asset id>.json.gz` asset file path
+fn path_asset_id(path: &path::Path) -> Option<AssetId> {
+    let path = plain_path(path);
+    if path.extension()? != "json" {
+        return None;
+    }
+    AssetId::from_hex(path.file_stem()?.to_str()?).ok()
+}
+
+// The compressed variant of a `<asset id>.json` path
+fn compressed_path(path: &path::Path) -> path::PathBuf {
+    path.with_extension(format!("json.{}", COMPRESSED_EXTENSION))
+}
+
+// The path without the compressed extension, if any
+fn plain_path(path: &path::Path) -> path::PathBuf {
+    if is_compressed(path) {
+        path.with_extension("")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+// The stored variant of the `<asset id>.json` path, either uncompressed or compressed. Both may
+// exist if writing was interrupted after the new variant was written but before the other one was
+// removed, in which case the most recently modified one is the current one.
+fn find_stored(path: &path::Path) -> Option<path::PathBuf> {
+    let compressed = compressed_path(path);
+    match (fs::metadata(path), fs::metadata(&compressed)) {
+        (Ok(plain), Ok(gz)) => match (plain.modified(), gz.modified()) {
+            (Ok(plain), Ok(gz)) if gz > plain => Some(compressed),
+            _ => Some(path.to_path_buf()),
+        },
+        (Ok(_), Err(_)) => Some(path.to_path_buf()),
+        (Err(_), Ok(_)) => Some(compressed),
+        (Err(_), Err(_)) => None,
+    }
+}
+
+// Whether the path is a partition directory, named by a hex prefix (of the given width, if any)
+fn is_partition_dir(path: &path::Path, width: Option<usize>) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| {
+            !name.is_empty()
+                && width.map_or(true, |width| name.len() == width)
+                && name.chars().all(|c| c.is_ascii_hexdigit())
+        })
+}
+
+// Like Child::wait_with_output(), but killing the child if it doesn't exit within the timeout.
+// The child's whole process group is killed, as processes left behind by it would otherwise keep
+// running (and keep its output pipes open).
+fn wait_with_timeout(mut child: Child, timeout: Duration) -> Result<Output> {
+    // read the pipes concurrently, so that a child writing a lot of output can't block on them
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            // the child leads its own process group (see run_hook())
+            unsafe {
+                libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+            }
+            child.wait()?;
+            bail!("hook timed out after {:?}", timeout);
+        }
+        thread::sleep(HOOK_POLL_INTERVAL);
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap(),
+        stderr: stderr.join().unwrap(),
+    })
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buf).ok();
+        }
+        buf
+    })
+}
+
+// Stored `<asset id>.<png|svg>` icons
+fn is_icon_file(path: &path::Path) -> bool {
+    let is_icon_extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| ICON_EXTENSIONS.contains(&extension));
+    is_icon_extension && path_asset_id(&path.with_extension("json")).is_some()
+}
+
+fn is_checksum_file(path: &path::Path) -> bool {
+    path.extension()
+        .map_or(false, |ext| ext == CHECKSUM_EXTENSION)
+        && path_asset_id(&path.with_extension("json")).is_some()
+}
+
+// The checksum file contents for the asset file, in the format of sha256sum
+fn content_hash(contents: &[u8]) -> String {
+    sha256::Hash::hash(contents).to_hex()
+}
+
+fn format_checksum(path: &path::Path, contents: &[u8]) -> Result<String> {
+    Ok(format!(
+        "{}  {}\n",
+        content_hash(contents),
+        path.file_name().req()?.to_string_lossy()
+    ))
+}
+
+// Registry bookkeeping files (like the changelog) and hidden files
+fn is_internal_file(path: &path::Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| name.starts_with('_') || name.starts_with('.'))
+}
+
+// Find asset files in the directory and its partition directories, regardless of their layout
+fn find_asset_files(dir: &path::Path, found: &mut Vec<path::PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if is_partition_dir(&path, None) {
+                find_asset_files(&path, found)?;
+            }
+        } else if path_asset_id(&path).is_some() {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Find all json files (compressed or not) in the directory and its sub-directories, skipping
+// internal ones
+fn find_json_files(dir: &path::Path, found: &mut Vec<path::PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_internal_file(&path) {
+            continue;
+        }
+        if path.is_dir() {
+            find_json_files(&path, found)?;
+        } else if plain_path(&path)
+            .extension()
+            .map_or(false, |ext| ext == "json")
+        {
+            // compressed `.json.gz` files too, as found in backups of compressing registries
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Log the entries added to and removed from a reloaded policy list, returning whether any were
+fn log_set_changes(name: &str, old: &HashSet<String>, new: &HashSet<String>) -> bool {
+    let mut added: Vec<&String> = new.difference(old).collect();
+    let mut removed: Vec<&String> = old.difference(new).collect();
+    if added.is_empty() && removed.is_empty() {
+        return false;
+    }
+    added.sort();
+    removed.sort();
+    info!(
+        "reloaded {}: added {:?}, removed {:?}",
+        name, added, removed
+    );
+    true
+}
+
+// Write the file through a temporary file in the same directory that is then renamed over it, so
+// that a crash mid-write never leaves a truncated file behind. The temporary file is hidden, to be
+// skipped when listing assets.
+fn write_atomic(path: &path::Path, contents: &[u8]) -> Result<()> {
+    let dir = path.parent().req()?;
+    let filename = path.file_name().req()?.to_string_lossy();
+    let tmp_path = dir.join(format!(".{}.tmp", filename));
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)?;
+
+    // persist the rename itself. directories cannot be opened for syncing on all platforms.
+    #[cfg(unix)]
+    fs::File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+fn make_unique_ns_filename(entity: &AssetEntity, ticker: Option<&String>) -> Option<String> {
+    ticker.map(|ticker| format!("{}@{}", ticker, entity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::gunzip;
+    use bitcoin::util::misc::signed_msg_hash;
+    use bitcoin::{BlockHash, Txid};
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Duration;
+
+    // A fresh temporary directory for the test's registry, unique to the test process
+    fn temp_registry_dir(name: &str) -> path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("asset-registry-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    // A chain backend using the mock esplora server
+    fn mock_chain() -> ChainQuery {
+        ChainQuery::new("http://localhost:58713".to_string())
+    }
+
+    // Store the b1405e test asset directly in the registry's storage, without verifying it or
+    // running the hook, returning it along with its stored path
+    fn store_test_asset(registry: &Registry) -> Result<(Asset, path::PathBuf)> {
+        let asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        let asset_path = registry.path_for(&asset.asset_id);
+        fs::create_dir_all(asset_path.parent().unwrap())?;
+        fs::copy("test/asset-b1405e.json", &asset_path)?;
+        Ok((asset, asset_path))
+    }
+
+    #[test]
+    fn test_export_elements_format() -> Result<()> {
+        let dir = temp_registry_dir("exporttest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let (asset, _) = store_test_asset(&registry)?;
+
+        let mut exported = vec![];
+        registry.export_elements_format(&mut exported)?;
+
+        let mut entries: HashMap<String, ElementsAssetEntry> = serde_json::from_slice(&exported)?;
+        assert_eq!(entries.len(), 1);
+        let entry = entries.remove(&asset.asset_id.to_hex()).req()?;
+        let imported = Asset::from_elements_entry(asset.asset_id, entry)?;
+        assert_eq!(
+            serde_json::to_value(&imported)?,
+            serde_json::to_value(&asset)?
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_export() -> Result<()> {
+        let dir = temp_registry_dir("exportall");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let mut asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        let other_id =
+            AssetId::from_hex("6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d")?;
+        for asset_id in &[asset.asset_id, other_id] {
+            asset.asset_id = *asset_id;
+            let asset_path = registry.path_for(asset_id);
+            fs::create_dir_all(asset_path.parent().unwrap())?;
+            fs::write(&asset_path, serde_json::to_string(&asset)?)?;
+        }
+
+        let mut ndjson = vec![];
+        registry.export(&mut ndjson, ExportFormat::Ndjson)?;
+        let lines: Vec<Asset> = String::from_utf8(ndjson.clone())?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<_, _>>()?;
+        let asset_ids: Vec<AssetId> = lines.iter().map(|asset| asset.asset_id).collect();
+        assert_eq!(asset_ids, registry.list()?);
+        assert_eq!(asset_ids[0], other_id);
+
+        let mut array = vec![];
+        registry.export(&mut array, ExportFormat::JsonArray)?;
+        let assets: Vec<Asset> = serde_json::from_slice(&array)?;
+        assert_eq!(assets.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&assets)?,
+            serde_json::to_value(&lines)?
+        );
+
+        // the output is deterministic
+        let mut again = vec![];
+        registry.export(&mut again, ExportFormat::Ndjson)?;
+        assert_eq!(again, ndjson);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_paged() -> Result<()> {
+        let dir = temp_registry_dir("pagedtest");
+        let registry = Registry::builder(&dir, mock_chain()).build();
+        let indexed = Registry::builder(&dir, mock_chain()).index(true).build();
+
+        let mut asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        for prefix in &["6f", "a0", "b1", "f3"] {
+            asset.asset_id =
+                AssetId::from_hex(&format!("{}{}", prefix, &asset.asset_id.to_hex()[2..]))?;
+            let asset_path = registry.path_for(&asset.asset_id);
+            fs::create_dir_all(asset_path.parent().unwrap())?;
+            fs::write(&asset_path, serde_json::to_string(&asset)?)?;
+        }
+        let all = registry.list()?;
+
+        let page = registry.list_paged(1, 2)?;
+        assert_eq!(page.asset_ids, all[1..3].to_vec());
+        assert_eq!((page.offset, page.total), (1, 4));
+        assert_eq!(registry.list_paged(3, 2)?.asset_ids, all[3..].to_vec());
+        assert!(registry.list_paged(10, 2)?.asset_ids.is_empty());
+
+        // backed by the index file when maintained, with the same results
+        indexed.sync_index()?;
+        for (offset, limit) in &[(0, 4), (1, 2), (3, 10), (4, 1)] {
+            assert_eq!(
+                indexed.list_paged(*offset, *limit)?,
+                registry.list_paged(*offset, *limit)?
+            );
+        }
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let src_dir = temp_registry_dir("importsrc");
+        let dir = temp_registry_dir("importtest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let json = fs::read_to_string("test/asset-b1405e.json")?;
+        fs::create_dir_all(src_dir.join("nested"))?;
+        fs::write(src_dir.join("nested/valid.json"), &json)?;
+        fs::write(
+            src_dir.join("corrupt.json"),
+            json.replace("PPP coin", "PPX coin"),
+        )?;
+        fs::write(src_dir.join("invalid.json"), "{")?;
+        // only the top-level name differs from the contract, which the commitment doesn't cover
+        let renamed = json.replace(
+            r#""name":"PPP coin","ticker":"PPP","precision""#,
+            r#""name":"PPX coin","ticker":"PPP","precision""#,
+        );
+        assert_ne!(renamed, json);
+        fs::write(src_dir.join("renamed.json"), renamed)?;
+        fs::write(src_dir.join("_index.json"), "{}")?;
+        fs::write(src_dir.join("notes.txt"), "")?;
+
+        let results = registry.import(&src_dir, false)?;
+        let files: Vec<_> = results
+            .iter()
+            .map(|(path, res)| (path.strip_prefix(&src_dir).unwrap(), res.is_ok()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (path::Path::new("corrupt.json"), false),
+                (path::Path::new("invalid.json"), false),
+                (path::Path::new("nested/valid.json"), true),
+                (path::Path::new("renamed.json"), false),
+            ]
+        );
+        let asset_id = results[2].1.as_ref().unwrap();
+        assert_eq!(registry.list()?, vec![*asset_id]);
+
+        // existing assets are reported as failures
+        let results = registry.import(&src_dir, false)?;
+        assert!(results.iter().all(|(_, res)| res.is_err()));
+
+        fs::remove_dir_all(&src_dir)?;
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_storage() -> Result<()> {
+        let src_dir = temp_registry_dir("storagesrc");
+        let dir = temp_registry_dir("storagetest");
+        let registry = Registry::builder(&dir, mock_chain())
+            .checksums(true)
+            .build();
+
+        fs::create_dir_all(&src_dir)?;
+        fs::copy("test/asset-b1405e.json", src_dir.join("asset.json"))?;
+        registry.import(&src_dir, false)?;
+        let asset_id = registry.list()?[0];
+        let path = registry.path_for(&asset_id);
+        let checksum = fs::read_to_string(path.with_extension("sha256"))?;
+        assert!(checksum.ends_with(&format!("  {}.json\n", asset_id.to_hex())));
+        assert_eq!(registry.verify_storage()?, vec![]);
+
+        // the content hash used for etags matches the checksum
+        let (json, hash) = registry.load_json_with_hash(&asset_id)?.req()?;
+        assert_eq!(json, fs::read(&path)?);
+        assert!(checksum.starts_with(&hash));
+        assert!(registry
+            .load_json_with_hash(&AssetId::from_hex(&"00".repeat(32))?)?
+            .is_none());
+
+        // corruption that still parses is detected
+        let contents = fs::read_to_string(&path)?;
+        fs::write(
+            &path,
+            contents.replace("\"precision\":0", "\"precision\":6"),
+        )?;
+        assert!(registry.load(&asset_id)?.is_some());
+        match &registry.verify_storage()?[..] {
+            [(id, StorageIssue::Mismatch { expected, actual })] => {
+                assert_eq!(id, &asset_id);
+                assert!(checksum.starts_with(expected.as_str()));
+                assert_ne!(expected, actual);
+            }
+            issues => panic!("unexpected storage issues {:?}", issues),
+        }
+
+        fs::write(&path, contents)?;
+        fs::remove_file(path.with_extension("sha256"))?;
+        assert_eq!(
+            registry.verify_storage()?,
+            vec![(asset_id, StorageIssue::MissingChecksum)]
+        );
+        assert_eq!(registry.sync_checksums()?, 1);
+        assert_eq!(fs::read_to_string(path.with_extension("sha256"))?, checksum);
+        assert_eq!(registry.verify_storage()?, vec![]);
+
+        fs::remove_dir_all(&src_dir)?;
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_all() -> Result<()> {
+        let src_dir = temp_registry_dir("normsrc");
+        let dir = temp_registry_dir("normtest");
+        let registry = Registry::builder(&dir, mock_chain())
+            .checksums(true)
+            .build();
+
+        fs::create_dir_all(&src_dir)?;
+        fs::copy("test/asset-b1405e.json", src_dir.join("asset.json"))?;
+        registry.import(&src_dir, false)?;
+        let asset_id = registry.list()?[0];
+        let path = registry.path_for(&asset_id);
+        let normalized = fs::read_to_string(&path)?;
+
+        // already normalized files are left as-is
+        let results = registry.normalize_all()?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.as_ref().ok(), Some(&false));
+
+        // files in any other serialization are rewritten, along with their checksum
+        let asset: Asset = serde_json::from_str(&normalized)?;
+        fs::write(&path, serde_json::to_string_pretty(&asset)?)?;
+        assert_eq!(registry.verify_storage()?.len(), 1);
+        let results = registry.normalize_all()?;
+        assert_eq!(results[0].1.as_ref().ok(), Some(&true));
+        assert_eq!(fs::read_to_string(&path)?, normalized);
+        assert_eq!(registry.verify_storage()?, vec![]);
+
+        // assets that no longer match their commitment are reported and not rewritten
+        let tampered = serde_json::to_string_pretty(&asset)?.replace("PPP coin", "PPQ coin");
+        fs::write(&path, &tampered)?;
+        let results = registry.normalize_all()?;
+        assert_eq!(results[0].0, asset_id);
+        assert!(results[0].1.is_err());
+        assert_eq!(fs::read_to_string(&path)?, tampered);
+
+        fs::remove_dir_all(&src_dir)?;
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_changelog() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+        crate::entity::tests::spawn_mock_verifier_server();
+
+        let dir = temp_registry_dir("normlogtest");
+        let registry = Registry::builder(&dir, mock_chain()).build();
+
+        let asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        registry.write(&asset)?;
+        let path = registry.path_for(&asset.asset_id);
+        fs::write(&path, serde_json::to_string_pretty(&asset)?)?;
+        assert!(registry.verify_changelog_tail(UNIX_EPOCH)?[0].1.is_err());
+
+        // normalized assets are logged, so that the changelog tail verifies afterwards
+        let results = registry.normalize_all()?;
+        assert_eq!(results[0].1.as_ref().ok(), Some(&true));
+        let changelog = registry.changelog()?;
+        assert_eq!(changelog.len(), 2);
+        assert_eq!(changelog[1].action, ChangeAction::Normalize);
+        let results = registry.verify_changelog_tail(UNIX_EPOCH)?;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_ok(), "{:?}", results[0].1);
+
+        // already normalized assets are not logged again
+        registry.normalize_all()?;
+        assert_eq!(registry.changelog()?.len(), 2);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_recent() -> Result<()> {
+        let dir = temp_registry_dir("recenttest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+        let (asset, asset_path) = store_test_asset(&registry)?;
+        let contents = fs::read(&asset_path)?;
+        let later = SystemTime::now() + Duration::from_secs(3600);
+
+        // without a changelog, the file modification times are used
+        assert_eq!(registry.list_recent(UNIX_EPOCH, 10)?.len(), 1);
+        assert!(registry.list_recent(later, 10)?.is_empty());
+
+        registry.append_changelog(&asset.asset_id, ChangeAction::Add, &contents)?;
+        let recent = registry.list_recent(UNIX_EPOCH, 10)?;
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].asset_id, asset.asset_id);
+        assert!(registry.list_recent(UNIX_EPOCH, 0)?.is_empty());
+        assert!(registry.list_recent(later, 10)?.is_empty());
+
+        // normalizations are skipped over, and deleted assets are not listed
+        registry.append_changelog(&asset.asset_id, ChangeAction::Normalize, &contents)?;
+        assert_eq!(registry.list_recent(UNIX_EPOCH, 10)?.len(), 1);
+        registry.append_changelog(&asset.asset_id, ChangeAction::Delete, &contents)?;
+        assert!(registry.list_recent(UNIX_EPOCH, 10)?.is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rev_lines() -> Result<()> {
+        let dir = temp_registry_dir("revlinestest");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("lines");
+        let lines: Vec<String> = (1..20).map(|i| "x".repeat(i)).collect();
+        fs::write(&path, lines.join("\n") + "\n")?;
+
+        // read across block boundaries of various sizes, including mid-line
+        for block_size in &[1, 3, 7, 64, 1024] {
+            let read = RevLines::new(fs::File::open(&path)?, *block_size)?
+                .map(|line| -> Result<String> { Ok(String::from_utf8(line?)?) })
+                .collect::<Result<Vec<String>>>()?;
+            let expected: Vec<String> = lines.iter().rev().cloned().collect();
+            assert_eq!(read, expected);
+        }
+
+        fs::write(&path, "")?;
+        assert_eq!(RevLines::new(fs::File::open(&path)?, 8)?.count(), 0);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_storage() -> Result<()> {
+        let dir = temp_registry_dir("gztest");
+        let plain = Registry::builder(&dir, mock_chain())
+            .checksums(true)
+            .build();
+        let compressing = Registry::builder(&dir, mock_chain())
+            .checksums(true)
+            .compress(true)
+            .build();
+
+        let (asset, path) = store_test_asset(&plain)?;
+        let asset_id = asset.asset_id;
+        let json = fs::read(&path)?;
+
+        // uncompressed files are still read by compressing registries, and get compressed when
+        // normalized
+        assert_eq!(compressing.load(&asset_id)?.unwrap().asset_id, asset_id);
+        let results = compressing.normalize_all()?;
+        assert_eq!(results[0].1.as_ref().ok(), Some(&true));
+        let gz_path = compressing.stored_path(&asset_id).unwrap();
+        assert!(gz_path.ends_with(format!("{}.json.gz", asset_id.to_hex())));
+        assert!(!path.exists());
+        assert_eq!(gunzip(&fs::read(&gz_path)?)?, json);
+        assert_eq!(
+            compressing.normalize_all()?[0].1.as_ref().ok(),
+            Some(&false)
+        );
+
+        // checksums cover the stored bytes, while the commitment is verified on the decompressed
+        // json
+        assert_eq!(compressing.verify_storage()?, vec![]);
+        let (loaded, hash) = compressing.load_json_with_hash(&asset_id)?.unwrap();
+        assert_eq!(loaded, json);
+        // served decompressed, so the etag hashes the decompressed json
+        assert_eq!(hash, content_hash(&json));
+        assert_eq!(compressing.list()?, vec![asset_id]);
+
+        // and are decompressed back by non-compressing registries
+        assert_eq!(plain.load(&asset_id)?.unwrap().asset_id, asset_id);
+        assert_eq!(plain.normalize_all()?[0].1.as_ref().ok(), Some(&true));
+        assert!(!gz_path.exists());
+        assert_eq!(fs::read(&path)?, json);
+        assert_eq!(plain.verify_storage()?, vec![]);
+
+        // when interrupted before the other variant was removed, the newer one is used
+        fs::write(&gz_path, gzip(b"{}")?)?;
+        assert_eq!(plain.stored_path(&asset_id), Some(gz_path.clone()));
+        assert_eq!(plain.list()?, vec![asset_id]);
+        thread::sleep(Duration::from_millis(20));
+        fs::write(&path, &json)?;
+        assert_eq!(plain.stored_path(&asset_id), Some(path.clone()));
+        assert_eq!(plain.load(&asset_id)?.unwrap().asset_id, asset_id);
+        fs::remove_file(&gz_path)?;
+
+        // compressed backups can be imported
+        let backup_dir = temp_registry_dir("gzbackup");
+        fs::create_dir_all(&backup_dir)?;
+        fs::write(
+            backup_dir.join(format!("{}.json.gz", asset_id.to_hex())),
+            gzip(&json)?,
+        )?;
+        let imported_dir = temp_registry_dir("gzimport");
+        let imported = Registry::builder(&imported_dir, mock_chain()).build();
+        let results = imported.import(&backup_dir, false)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.as_ref().ok(), Some(&asset_id));
+        assert_eq!(imported.load(&asset_id)?.unwrap().asset_id, asset_id);
+
+        fs::remove_dir_all(&backup_dir)?;
+        fs::remove_dir_all(&imported_dir)?;
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder() -> Result<()> {
+        let dir = temp_registry_dir("buildertest");
+        let ctx = VerifyContext {
+            name_max_len: 10,
+            ..Default::default()
+        };
+        let registry = Registry::builder(&dir, mock_chain())
+            .context(ctx)
+            .proof_evidence(true)
+            .uniqueness(UniquenessPolicy::Both)
+            .build();
+
+        assert_eq!(registry.context().name_max_len, 10);
+        assert!(registry.keep_proofs);
+        assert_eq!(registry.uniqueness, UniquenessPolicy::Both);
+        assert!(registry.hook_cmd.is_none() && registry.admin_pubkey.is_none());
+
+        assert!(Registry::builder(&dir, mock_chain())
+            .admin_pubkey(vec![0; 33])
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_policy() -> Result<()> {
+        let dir = temp_registry_dir("policytest");
+        fs::create_dir_all(&dir)?;
+        let symbols_path = dir.join("reserved-symbols");
+        let allowlist_path = dir.join("allowlist");
+        fs::write(&symbols_path, "BTC\n")?;
+        fs::write(&allowlist_path, "test.dev\n")?;
+
+        let files = PolicyFiles {
+            reserved_symbols: Some(symbols_path.clone()),
+            reserve_well_known_symbols: false,
+            domain_allowlist: Some(allowlist_path.clone()),
+        };
+        let registry = Registry::builder(&dir, mock_chain())
+            .context(VerifyContext {
+                name_max_len: 10,
+                ..Default::default()
+            })
+            .policy_files(files)?
+            .build();
+        let ctx = registry.context();
+        assert!(ctx.reserved_symbols.contains("btc"));
+        assert_eq!(ctx.domain_allowlist.as_ref().req()?.len(), 1);
+
+        assert!(!registry.reload_policy()?);
+
+        fs::write(&symbols_path, "BTC\nPPP\n")?;
+        assert!(registry.reload_policy()?);
+        assert!(registry.context().reserved_symbols.contains("ppp"));
+        // the rest of the context is kept, and earlier snapshots are unaffected
+        assert_eq!(registry.context().name_max_len, 10);
+        assert!(!ctx.reserved_symbols.contains("ppp"));
+
+        // invalid files keep all the previous lists
+        fs::write(&symbols_path, "BTC\n")?;
+        fs::write(&allowlist_path, "test.dev\nnot a domain\n")?;
+        assert!(registry.reload_policy().is_err());
+        let ctx = registry.context();
+        assert!(ctx.reserved_symbols.contains("ppp"));
+        assert_eq!(ctx.domain_allowlist.as_ref().req()?.len(), 1);
+
+        // registries without policy files have nothing to reload
+        assert!(!Registry::builder(&dir, mock_chain())
+            .build()
+            .reload_policy()?);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only() -> Result<()> {
+        let dir = temp_registry_dir("readonlytest");
+        let registry = Arc::new(Registry::read_only(&dir));
+        assert!(registry.is_read_only());
+
+        let (asset, asset_path) = store_test_asset(&registry)?;
+
+        // stored assets are served
+        assert_eq!(
+            registry.load(&asset.asset_id)?.req()?.asset_id,
+            asset.asset_id
+        );
+        assert_eq!(registry.list()?, vec![asset.asset_id]);
+
+        // but never changed or verified
+        let read_only = Some(&AssetError::ReadOnly);
+        assert_eq!(asset_error(&registry.write(&asset).unwrap_err()), read_only);
+        assert_eq!(
+            asset_error(&registry.validate(&asset).unwrap_err()),
+            read_only
+        );
+        assert_eq!(
+            asset_error(
+                &registry
+                    .remove(&asset.asset_id, &[0; 65], None, None)
+                    .unwrap_err()
+            ),
+            read_only
+        );
+        assert_eq!(
+            asset_error(&Arc::clone(&registry).verify_all(1).unwrap_err()),
+            read_only
+        );
+        assert_eq!(asset_error(&registry.chain().unwrap_err()), read_only);
+        assert!(asset_path.exists());
+
+        let report = registry.health();
+        assert!(matches!(report.chain, HealthCheck::Skipped));
+        assert!(report.is_healthy());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_health() -> Result<()> {
+        let dir = temp_registry_dir("healthtest");
+        fs::create_dir_all(&dir)?;
+
+        // a writable registry probes its directory without leaving the probe file behind
+        let registry = Registry::new(&dir, mock_chain(), None);
+        let report = registry.health();
+        assert!(matches!(report.directory, HealthCheck::Ok), "{:?}", report);
+        assert!(matches!(report.hook, HealthCheck::Skipped));
+        assert!(!dir.join(".health-check").exists());
+        assert!(!registry.is_write_locked());
+
+        let registry = Registry::new(&dir, mock_chain(), Some("/nonexistent/hook".into()));
+        assert!(matches!(registry.health().hook, HealthCheck::Failed(_)));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_list() -> Result<()> {
+        let dir = temp_registry_dir("listtest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let (asset, asset_path) = store_test_asset(&registry)?;
+        let partition_dir = asset_path.parent().unwrap();
+
+        // stray files are skipped
+        fs::write(partition_dir.join("notes.txt"), "")?;
+        fs::write(partition_dir.join("not-an-asset-id.json"), "{}")?;
+        fs::write(dir.join("notes.txt"), "")?;
+
+        assert_eq!(registry.list()?, vec![asset.asset_id]);
+        let assets = registry.list_assets()?;
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets[0].asset_id, asset.asset_id);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_partition_layout() -> Result<()> {
+        let dir = temp_registry_dir("layouttest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let (asset, asset_path) = store_test_asset(&registry)?;
+        assert!(asset_path.ends_with(format!("b1/{}.json", asset.asset_id.to_hex())));
+        fs::write(dir.join(CHANGELOG_FILE), "")?;
+
+        let registry = Registry::builder(&dir, mock_chain())
+            .partition_layout(PartitionLayout::new(2, 2)?)
+            .build();
+        let new_path = registry.path_for(&asset.asset_id);
+        assert!(new_path.ends_with(format!("b1/40/{}.json", asset.asset_id.to_hex())));
+        assert!(registry.list()?.is_empty());
+
+        assert_eq!(registry.migrate_layout()?, 1);
+        assert!(new_path.exists() && !asset_path.exists());
+        assert!(!dir
+            .join("b1")
+            .join(format!("{}.json", asset.asset_id.to_hex()))
+            .exists());
+        assert_eq!(registry.list()?, vec![asset.asset_id]);
+        assert!(dir.join(CHANGELOG_FILE).exists());
+        // already migrated
+        assert_eq!(registry.migrate_layout()?, 0);
+
+        let registry = Registry::builder(&dir, mock_chain())
+            .partition_layout(PartitionLayout::flat())
+            .build();
+        assert_eq!(registry.migrate_layout()?, 1);
+        assert!(dir
+            .join(format!("{}.json", asset.asset_id.to_hex()))
+            .exists());
+        assert!(!dir.join("b1").exists());
+        assert_eq!(registry.list()?, vec![asset.asset_id]);
+
+        assert!(PartitionLayout::new(2, 40).is_err());
+        assert!(PartitionLayout::new(1, 0).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_all() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+        crate::entity::tests::spawn_mock_verifier_server();
+
+        let dir = temp_registry_dir("verifyalltest");
+        let registry = Arc::new(Registry::new(&dir, mock_chain(), None));
+        let (asset, _) = store_test_asset(&registry)?;
+
+        // a corrupt asset is reported without aborting the run
+        let corrupt_id =
+            AssetId::from_hex("6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d")?;
+        let corrupt_path = registry.path_for(&corrupt_id);
+        fs::create_dir_all(corrupt_path.parent().unwrap())?;
+        fs::write(&corrupt_path, "{}")?;
+
+        let results = Arc::clone(&registry).verify_all(4)?;
+        let asset_ids: Vec<AssetId> = results.iter().map(|(asset_id, _)| *asset_id).collect();
+        assert_eq!(asset_ids, registry.list()?);
+        let (_, corrupt_res) = results.iter().find(|(id, _)| *id == corrupt_id).req()?;
+        assert!(corrupt_res.is_err());
+        let (_, valid_res) = results.iter().find(|(id, _)| *id == asset.asset_id).req()?;
+        assert!(valid_res.is_ok(), "{:?}", valid_res);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+
+        let dir = temp_registry_dir("audittest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let mut asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        let store = |asset: &Asset| -> Result<()> {
+            let asset_path = registry.path_for(&asset.asset_id);
+            fs::create_dir_all(asset_path.parent().unwrap())?;
+            fs::write(&asset_path, serde_json::to_string(asset)?)?;
+            Ok(())
+        };
+        store(&asset)?;
+        assert_eq!(
+            registry.audit(&asset.asset_id)?,
+            AuditStatus::Confirmed { height: 999 }
+        );
+
+        // reorged into a different block
+        let mut block = registry
+            .chain()?
+            .get_tx_status(&asset.issuance_txin.txid)?
+            .req()?;
+        block.block_hash = BlockHash::from_hex(&"00".repeat(32))?;
+        asset.issuance_block = Some(block);
+        store(&asset)?;
+        match registry.audit(&asset.asset_id)? {
+            AuditStatus::Mismatch { error } => assert!(error.contains("reorg"), "{}", error),
+            status => panic!("unexpected {:?}", status),
+        }
+
+        // reorged out entirely
+        asset.issuance_block = None;
+        asset.issuance_txin.txid =
+            Txid::from_hex("0000a545ff42c403839b0be69c1047144dc3e778c0d937d85c71538f169eebb5")?;
+        store(&asset)?;
+        assert_eq!(registry.audit(&asset.asset_id)?, AuditStatus::NotFound);
+
+        let results = registry.audit_all()?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.as_ref().unwrap(), &AuditStatus::NotFound);
+
+        // unknown assets and unreachable backends are errors
+        let unknown =
+            AssetId::from_hex("6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d")?;
+        assert!(registry.audit(&unknown).is_err());
+        let unreachable = Registry::new(
+            &dir,
+            ChainQuery::new("http://localhost:1".to_string()),
+            None,
+        );
+        assert!(unreachable.audit(&asset.asset_id).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+        crate::entity::tests::spawn_mock_verifier_server();
+
+        let dir = temp_registry_dir("validatetest");
+        fs::create_dir_all(&dir)?;
+        let hook_path = dir.join("hook.sh");
+        fs::write(
+            &hook_path,
+            "#!/bin/sh\ntouch \"$(dirname \"$0\")/hook-ran\"\n",
+        )?;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+
+        let registry = Registry::new(
+            &dir,
+            mock_chain(),
+            Some(hook_path.to_str().req()?.to_string()),
+        );
+
+        let mut asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        registry.validate(&asset)?;
+        assert!(!registry.path_for(&asset.asset_id).exists());
+        assert!(!dir.join("hook-ran").exists());
+
+        asset.fields.name = "Other coin".to_string();
+        assert!(registry.validate(&asset).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_by_ticker() -> Result<()> {
+        let dir = temp_registry_dir("tickertest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let (asset, asset_path) = store_test_asset(&registry)?;
+        let ticker = asset.fields.ticker.clone().req()?;
+
+        let found = registry.find_by_ticker(&ticker.to_lowercase())?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].asset_id, asset.asset_id);
+        assert!(registry.find_by_ticker("NOPE")?.is_empty());
+
+        // removed assets are dropped from the index
+        registry.update_field_index(&asset, false);
+        assert!(registry.find_by_ticker(&ticker)?.is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_uniqueness() -> Result<()> {
+        let dir = temp_registry_dir("uniqtest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let (existing, asset_path) = store_test_asset(&registry)?;
+
+        // unreadable files are skipped when building the index, rather than failing every check
+        let corrupt_path = registry.path_for(&AssetId::from_hex(
+            "b1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        )?);
+        fs::create_dir_all(corrupt_path.parent().unwrap())?;
+        fs::write(&corrupt_path, "{")?;
+
+        let mut asset = existing.clone();
+        asset.asset_id =
+            AssetId::from_hex("6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d")?;
+        asset.fields.ticker = Some("ppp".to_string());
+        asset.fields.name = "Other coin".to_string();
+
+        registry.check_uniqueness(&asset)?;
+
+        let registry = Registry::builder(&dir, mock_chain())
+            .uniqueness(UniquenessPolicy::TickerUnique)
+            .build();
+        let err = registry.check_uniqueness(&asset).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "ticker ppp already registered by asset {}",
+                existing.asset_id.to_hex()
+            )
+        );
+
+        let registry = Registry::builder(&dir, mock_chain())
+            .uniqueness(UniquenessPolicy::NameUnique)
+            .build();
+        registry.check_uniqueness(&asset)?;
+        asset.fields.name = "ppp COIN".to_string();
+        assert!(registry.check_uniqueness(&asset).is_err());
+
+        // the asset itself is not a collision
+        registry.check_uniqueness(&existing)?;
+
+        // the index is built once, with stale entries re-checked against the stored assets
+        fs::remove_file(&asset_path)?;
+        registry.check_uniqueness(&asset)?;
+        registry.update_field_index(&existing, false);
+        fs::copy("test/asset-b1405e.json", &asset_path)?;
+        registry.check_uniqueness(&asset)?;
+        registry.update_field_index(&existing, true);
+        assert!(registry.check_uniqueness(&asset).is_err());
+
+        // built from the index file when maintained, without loading the stored assets
+        fs::remove_file(&corrupt_path)?;
+        let indexed = Registry::builder(&dir, mock_chain())
+            .index(true)
+            .uniqueness(UniquenessPolicy::NameUnique)
+            .build();
+        indexed.sync_index()?;
+        fs::write(&corrupt_path, "{")?;
+        assert!(indexed.check_uniqueness(&asset).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_similar() -> Result<()> {
+        let dir = temp_registry_dir("similartest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+        let (existing, _) = store_test_asset(&registry)?;
+
+        // unreadable files are skipped rather than failing the lookup
+        let corrupt_path = registry.path_for(&AssetId::from_hex(
+            "b1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        )?);
+        fs::create_dir_all(corrupt_path.parent().unwrap())?;
+        fs::write(&corrupt_path, "{")?;
+
+        let mut fields = existing.fields.clone();
+        fields.name = "PPP c0in".to_string();
+        fields.ticker = None;
+        let similar = registry.find_similar(&fields)?;
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].0, existing.asset_id);
+        assert!(similar[0].1 >= SIMILARITY_THRESHOLD);
+
+        fields.name = "Unrelated token".to_string();
+        assert!(registry.find_similar(&fields)?.is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic() -> Result<()> {
+        let dir = temp_registry_dir("atomictest");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("file.json");
+
+        write_atomic(&path, b"{}")?;
+        write_atomic(&path, b"[]")?;
+        assert_eq!(fs::read(&path)?, b"[]");
+        // only the written file is left behind
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> Result<()> {
+        let dir = temp_registry_dir("statstest");
+        let registry = Registry::builder(&dir, mock_chain()).index(true).build();
+
+        let mut asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        for asset_id in &[
+            "b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05",
+            "b1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d",
+        ] {
+            asset.asset_id = AssetId::from_hex(asset_id)?;
+            let asset_path = registry.path_for(&asset.asset_id);
+            fs::create_dir_all(asset_path.parent().unwrap())?;
+            fs::write(&asset_path, serde_json::to_string(&asset)?)?;
+        }
+
+        // walks the tree without an index
+        let stats = registry.stats()?;
+        assert_eq!(stats.network, Network::Liquid);
+        assert_eq!(stats.total_assets, 3);
+        assert_eq!(stats.entity_types.get("domain"), Some(&3));
+        let partitions: Vec<_> = stats.partitions.into_iter().collect();
+        assert_eq!(
+            partitions,
+            vec![("6f".to_string(), 1), ("b1".to_string(), 2)]
+        );
+
+        // and reads the index once available
+        registry.sync_index()?;
+        fs::remove_file(registry.path_for(&asset.asset_id))?;
+        assert_eq!(registry.stats()?.total_assets, 3);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_for_entity() -> Result<()> {
+        let dir = temp_registry_dir("bundletest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+        let indexed = Registry::builder(&dir, mock_chain()).index(true).build();
+
+        let mut asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        for (asset_id, entity) in vec![
+            (
+                "b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05",
+                AssetEntity::DomainName("test.dev".to_string()),
+            ),
+            (
+                "6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d",
+                AssetEntity::DomainName("other.dev".to_string()),
+            ),
+            (
+                "c1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                AssetEntity::DomainTxt("test.dev".to_string()),
+            ),
+        ] {
+            asset.asset_id = AssetId::from_hex(asset_id)?;
+            asset.fields.entity = entity;
+            let asset_path = registry.path_for(&asset.asset_id);
+            fs::create_dir_all(asset_path.parent().unwrap())?;
+            fs::write(&asset_path, serde_json::to_string(&asset)?)?;
+        }
+        indexed.sync_index()?;
+
+        // unreadable files are skipped when scanning, and never loaded when using the index
+        let corrupt_path = registry.path_for(&AssetId::from_hex(
+            "b1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        )?);
+        fs::create_dir_all(corrupt_path.parent().unwrap())?;
+        fs::write(&corrupt_path, "{")?;
+
+        // the same domain is bundled regardless of how it is verified
+        let entities = [
+            AssetEntity::DomainName("test.dev".to_string()),
+            AssetEntity::DomainTxt("test.dev".to_string()),
+        ];
+        for registry in &[&registry, &indexed] {
+            for entity in &entities {
+                let bundle = registry.bundle_for_entity(entity)?;
+                let asset_ids: Vec<_> =
+                    bundle.iter().map(|asset| asset.asset_id.to_hex()).collect();
+                assert_eq!(
+                    asset_ids,
+                    vec![
+                        "b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05",
+                        "c1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                    ]
+                );
+            }
+        }
+        let nobody = AssetEntity::DomainName("nobody.dev".to_string());
+        assert!(indexed.bundle_for_entity(&nobody)?.is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_index() -> Result<()> {
+        let dir = temp_registry_dir("indextest");
+        let registry = Registry::builder(&dir, mock_chain()).index(true).build();
+
+        let (asset, asset_path) = store_test_asset(&registry)?;
+
+        // built when missing, and kept when up to date
+        assert_eq!(registry.index()?, None);
+        assert!(registry.sync_index()?);
+        assert!(!registry.sync_index()?);
+        let index = registry.index()?.req()?;
+        assert_eq!(index.len(), 1);
+        let entry = &index[&asset.asset_id.to_hex()];
+        assert_eq!(entry.name, "PPP coin");
+        assert_eq!(entry.ticker, Some("PPP".to_string()));
+        assert_eq!(entry.entity, asset.fields.entity);
+        // the index file is not mistaken for an asset
+        assert_eq!(registry.list()?, vec![asset.asset_id]);
+
+        // updated along with the stored assets
+        registry.update_index(&asset, false)?;
+        assert!(registry.index()?.req()?.is_empty());
+        assert!(!dir.join("._index.json.tmp").exists());
+
+        // rebuilt when stale
+        assert!(registry.sync_index()?);
+        assert_eq!(registry.index()?.req()?, index);
+
+        // including when an asset changed while the index wasn't maintained
+        let mut updated = asset.clone();
+        updated.fields.name = "Other coin".to_string();
+        fs::write(&asset_path, serde_json::to_string(&updated)?)?;
+        assert!(registry.sync_index()?);
+        assert_eq!(
+            registry.index()?.req()?[&asset.asset_id.to_hex()].name,
+            "Other coin"
+        );
+        assert!(!registry.sync_index()?);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_before_hook() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+        crate::entity::tests::spawn_mock_verifier_server();
+
+        let dir = temp_registry_dir("indexhooktest");
+        fs::create_dir_all(&dir)?;
+        let hook_path = dir.join("hook.sh");
+        // keeps a copy of the index as seen by the hook, failing when asked to
+        fs::write(
+            &hook_path,
+            "#!/bin/sh
+cp _index.json hook-index.json
+[ ! -f fail-hook ]
+",
+        )?;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+        let registry = Registry::builder(&dir, mock_chain())
+            .hook_cmd(Some(hook_path.to_str().req()?.to_string()))
+            .index(true)
+            .build();
+
+        // the hook publishes an index that already lists the asset
+        let asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        registry.write(&asset)?;
+        let published: BTreeMap<String, IndexEntry> =
+            serde_json::from_slice(&fs::read(dir.join("hook-index.json"))?)?;
+        assert!(published.contains_key(&asset.asset_id.to_hex()));
+
+        // and the index is reverted along with the asset when the hook fails
+        let failing_dir = temp_registry_dir("indexhookfailtest");
+        fs::create_dir_all(&failing_dir)?;
+        fs::write(failing_dir.join("fail-hook"), "")?;
+        let failing = Registry::builder(&failing_dir, mock_chain())
+            .hook_cmd(Some(hook_path.to_str().req()?.to_string()))
+            .index(true)
+            .build();
+        assert!(failing.write(&asset).is_err());
+        let published: BTreeMap<String, IndexEntry> =
+            serde_json::from_slice(&fs::read(failing_dir.join("hook-index.json"))?)?;
+        assert!(published.contains_key(&asset.asset_id.to_hex()));
+        assert!(failing.index()?.req()?.is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        fs::remove_dir_all(&failing_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_before_hook() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+        crate::entity::tests::spawn_mock_verifier_server();
+
+        let dir = temp_registry_dir("proofhooktest");
+        fs::create_dir_all(&dir)?;
+        let hook_path = dir.join("hook.sh");
+        // keeps a copy of the proof as seen by the hook, failing when asked to
+        fs::write(
+            &hook_path,
+            "#!/bin/sh
+cp _proofs/$1.json hook-proof.json
+[ ! -f fail-hook ]
+",
+        )?;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+        let registry = Registry::builder(&dir, mock_chain())
+            .hook_cmd(Some(hook_path.to_str().req()?.to_string()))
+            .proof_evidence(true)
+            .build();
+
+        // the hook sees the proof kept for the asset
+        let asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        registry.write(&asset)?;
+        assert!(dir.join("hook-proof.json").exists());
+        assert!(registry.proof_evidence(&asset.asset_id)?.is_some());
+
+        // and the proof is removed along with the asset when the hook fails
+        let failing_dir = temp_registry_dir("proofhookfailtest");
+        fs::create_dir_all(&failing_dir)?;
+        fs::write(failing_dir.join("fail-hook"), "")?;
+        let failing = Registry::builder(&failing_dir, mock_chain())
+            .hook_cmd(Some(hook_path.to_str().req()?.to_string()))
+            .proof_evidence(true)
+            .build();
+        assert!(failing.write(&asset).is_err());
+        assert!(failing_dir.join("hook-proof.json").exists());
+        assert!(failing.proof_evidence(&asset.asset_id)?.is_none());
+
+        fs::remove_dir_all(&dir)?;
+        fs::remove_dir_all(&failing_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove() -> Result<()> {
+        let dir = temp_registry_dir("removetest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+
+        let (asset, asset_path) = store_test_asset(&registry)?;
+
+        let ec = Secp256k1::signing_only();
+        let issuer_key =
+            bitcoin::PrivateKey::from_wif("cRmFPw94iHgnmUMui5brPsbH5F7wNmvgVkAGJYqZaK33F5vzCAST")?;
+        let msg_hash = signed_msg_hash(&format!("remove {} from registry", asset.asset_id));
+        let msg_secp = secp256k1::Message::from_slice(&msg_hash.into_inner())?;
+        let signature = ec.sign(&msg_secp, &issuer_key.key).serialize_compact();
+
+        assert!(registry
+            .remove(&asset.asset_id, &[0; 64], None, None)
+            .is_err());
+        registry.remove(&asset.asset_id, &signature, None, None)?;
+        assert!(!asset_path.exists());
+        // the now-empty partition directory is pruned
+        assert!(!asset_path.parent().unwrap().exists());
+
+        let err = registry
+            .remove(&asset.asset_id, &signature, None, None)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "asset does not exists");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_requires_admin() -> Result<()> {
+        let ec = Secp256k1::signing_only();
+        let admin_key = secp256k1::SecretKey::from_slice(&[0x42; 32])?;
+        let admin_pubkey = secp256k1::PublicKey::from_secret_key(&ec, &admin_key);
+        let dir = temp_registry_dir("removeadmintest");
+        let registry = Registry::builder(&dir, mock_chain())
+            .admin_pubkey(admin_pubkey.serialize().to_vec())?
+            .build();
+        let (asset, asset_path) = store_test_asset(&registry)?;
+
+        let issuer_key =
+            bitcoin::PrivateKey::from_wif("cRmFPw94iHgnmUMui5brPsbH5F7wNmvgVkAGJYqZaK33F5vzCAST")?;
+        let msg_hash = signed_msg_hash(&format!("remove {} from registry", asset.asset_id));
+        let msg_secp = secp256k1::Message::from_slice(&msg_hash.into_inner())?;
+        let signature = ec.sign(&msg_secp, &issuer_key.key).serialize_compact();
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let admin_auth = |operation: &str| -> Result<AdminAuth> {
+            let msg = format_admin_sig_msg(operation, &asset.asset_id, now);
+            let msg_secp = secp256k1::Message::from_slice(&signed_msg_hash(&msg).into_inner())?;
+            let signature = ec.sign(&msg_secp, &admin_key).serialize_compact().to_vec();
+            Ok(AdminAuth {
+                timestamp: now,
+                signature,
+            })
+        };
+
+        // the issuer's signature alone is not enough
+        let err = registry
+            .remove(&asset.asset_id, &signature, None, None)
+            .unwrap_err();
+        assert_eq!(
+            crate::errors::asset_error(&err),
+            Some(&AssetError::Unauthorized(
+                "admin authorization required".into()
+            ))
+        );
+        let wrong_op = admin_auth("overwrite")?;
+        assert!(registry
+            .remove(&asset.asset_id, &signature, None, Some(&wrong_op))
+            .is_err());
+        assert!(asset_path.exists());
+
+        let auth = admin_auth("delete")?;
+        registry.remove(&asset.asset_id, &signature, None, Some(&auth))?;
+        assert!(!asset_path.exists());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_hook_failure() -> Result<()> {
+        let dir = temp_registry_dir("removehooktest");
+        fs::create_dir_all(&dir)?;
+        let hook_path = dir.join("hook.sh");
+        fs::write(&hook_path, "#!/bin/sh\n[ ! -f fail-hook ]\n")?;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+        let registry = Registry::builder(&dir, mock_chain())
+            .hook_cmd(Some(hook_path.to_str().req()?.to_string()))
+            .index(true)
+            .build();
+        let (asset, asset_path) = store_test_asset(&registry)?;
+        let stored = fs::read(&asset_path)?;
+
+        let ec = Secp256k1::signing_only();
+        let issuer_key =
+            bitcoin::PrivateKey::from_wif("cRmFPw94iHgnmUMui5brPsbH5F7wNmvgVkAGJYqZaK33F5vzCAST")?;
+        let msg_hash = signed_msg_hash(&format!("remove {} from registry", asset.asset_id));
+        let msg_secp = secp256k1::Message::from_slice(&msg_hash.into_inner())?;
+        let signature = ec.sign(&msg_secp, &issuer_key.key).serialize_compact();
+
+        // the asset and its index entry are restored when the hook fails, with nothing logged
+        fs::write(dir.join("fail-hook"), "")?;
+        assert!(registry
+            .remove(&asset.asset_id, &signature, None, None)
+            .is_err());
+        assert_eq!(fs::read(&asset_path)?, stored);
+        assert!(registry
+            .index()?
+            .req()?
+            .contains_key(&asset.asset_id.to_hex()));
+        assert!(!dir.join(CHANGELOG_FILE).exists());
+
+        // and the deletion goes through (and is logged) once the hook succeeds
+        fs::remove_file(dir.join("fail-hook"))?;
+        registry.remove(&asset.asset_id, &signature, None, None)?;
+        assert!(!asset_path.exists());
+        assert!(!registry
+            .index()?
+            .req()?
+            .contains_key(&asset.asset_id.to_hex()));
+        assert!(fs::read_to_string(dir.join(CHANGELOG_FILE))?.contains(&asset.asset_id.to_hex()));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_registry_id() -> Result<()> {
+        let dir = temp_registry_dir("removeidtest");
+        // legacy deletions are disabled by default once a registry id is set
+        let registry = Registry::builder(&dir, mock_chain())
+            .registry_id("test-registry")?
+            .build();
+        assert!(Registry::builder(&dir, mock_chain())
+            .registry_id("test:registry")
+            .is_err());
+
+        let (asset, asset_path) = store_test_asset(&registry)?;
+
+        let ec = Secp256k1::signing_only();
+        let issuer_key =
+            bitcoin::PrivateKey::from_wif("cRmFPw94iHgnmUMui5brPsbH5F7wNmvgVkAGJYqZaK33F5vzCAST")?;
+        let sign = |msg: &str| -> Result<Vec<u8>> {
+            let msg_hash = signed_msg_hash(msg);
+            let msg_secp = secp256k1::Message::from_slice(&msg_hash.into_inner())?;
+            Ok(ec
+                .sign(&msg_secp, &issuer_key.key)
+                .serialize_compact()
+                .to_vec())
+        };
+
+        let msg = registry.deletion_msg(&asset.asset_id, 2);
+        assert_eq!(
+            msg,
+            format!(
+                "liquid-asset-delete:1:test-registry:{}:2",
+                asset.asset_id.to_hex()
+            )
+        );
+
+        // the legacy message and messages bound to other registries are rejected
+        let legacy_sig = sign(&format!("remove {} from registry", asset.asset_id))?;
+        let err = registry
+            .remove(&asset.asset_id, &legacy_sig, None, None)
+            .unwrap_err();
+        match asset_error(&err) {
+            Some(AssetError::InvalidSignature(_)) => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+        let other_sig = sign(&format!(
+            "liquid-asset-delete:1:other-registry:{}:2",
+            asset.asset_id.to_hex()
+        ))?;
+        assert!(registry
+            .remove(&asset.asset_id, &other_sig, Some(2), None)
+            .is_err());
+        assert!(asset_path.exists());
+        assert_eq!(registry.last_sequence(&asset.asset_id)?, None);
+
+        let signature = sign(&msg)?;
+        registry.remove(&asset.asset_id, &signature, Some(2), None)?;
+        assert!(!asset_path.exists());
+        assert_eq!(registry.last_sequence(&asset.asset_id)?, Some(2));
+
+        // once registered again, the authorization cannot be replayed, nor older ones used
+        fs::create_dir_all(asset_path.parent().unwrap())?;
+        fs::copy("test/asset-b1405e.json", &asset_path)?;
+        let err = registry
+            .remove(&asset.asset_id, &signature, Some(2), None)
+            .unwrap_err();
+        assert_eq!(
+            asset_error(&err),
+            Some(&AssetError::InvalidSignature(
+                "sequence number must be greater than 2".to_string()
+            ))
+        );
+        let older_sig = sign(&registry.deletion_msg(&asset.asset_id, 1))?;
+        assert!(registry
+            .remove(&asset.asset_id, &older_sig, Some(1), None)
+            .is_err());
+        assert!(asset_path.exists());
+
+        let newer_sig = sign(&registry.deletion_msg(&asset.asset_id, 3))?;
+        registry.remove(&asset.asset_id, &newer_sig, Some(3), None)?;
+        assert!(!asset_path.exists());
+        assert_eq!(registry.last_sequence(&asset.asset_id)?, Some(3));
+
+        // update authorizations share the sequence with deletions
+        fs::copy("test/asset-b1405e.json", &asset_path)?;
+        let update_sig = sign(&registry.update_msg(&asset.asset_id, 3).unwrap())?;
+        assert!(registry
+            .authorize_update(&asset.asset_id, &[update_sig], 3)
+            .is_err());
+        let update_sig = sign(&registry.update_msg(&asset.asset_id, 4).unwrap())?;
+        assert!(registry
+            .authorize_update(&asset.asset_id, &[newer_sig.clone()], 4)
+            .is_err());
+        registry.authorize_update(&asset.asset_id, &[update_sig.clone()], 4)?;
+        assert_eq!(registry.last_sequence(&asset.asset_id)?, Some(4));
+        assert!(registry
+            .authorize_update(&asset.asset_id, &[update_sig], 4)
+            .is_err());
+        fs::remove_file(&asset_path)?;
+
+        // with legacy deletions enabled, the unsigned sequence sent along a legacy signature is
+        // not recorded and cannot block later authorizations
+        let registry = Registry::builder(&dir, mock_chain())
+            .registry_id("test-registry")?
+            .legacy_deletions(true)
+            .build();
+        fs::copy("test/asset-b1405e.json", &asset_path)?;
+        registry.remove(&asset.asset_id, &legacy_sig, Some(u64::max_value()), None)?;
+        assert!(!asset_path.exists());
+        assert_eq!(registry.last_sequence(&asset.asset_id)?, Some(4));
+
+        fs::copy("test/asset-b1405e.json", &asset_path)?;
+        let next_sig = sign(&registry.deletion_msg(&asset.asset_id, 5))?;
+        registry.remove(&asset.asset_id, &next_sig, Some(5), None)?;
+        assert_eq!(registry.last_sequence(&asset.asset_id)?, Some(5));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "icons")]
+    #[test]
+    fn test_store_icons() -> Result<()> {
+        use crate::icon::tests::{test_png, TEST_SVG};
+        use crate::icon::verify_icon;
+
+        crate::entity::tests::spawn_mock_verifier_server();
+        let dir = temp_registry_dir("icontest");
+        let registry = Registry::builder(&dir, mock_chain())
+            .store_icons(true)
+            .build();
+
+        let (asset, asset_path) = store_test_asset(&registry)?;
+        assert!(registry.prepare_icon(None)?.is_none());
+
+        // the icon fetched during verification is stored, without fetching it again
+        let (fetched, _) = verify_icon("https://test.dev/icons/icon.png", &registry.ctx())?;
+        let icon = registry.prepare_icon(Some(&fetched))?.req()?;
+        assert_eq!(icon.1, IconFormat::Png);
+
+        registry.write_icon(&asset.asset_id, icon)?;
+        assert!(asset_path.with_extension("png").exists());
+        assert_eq!(registry.icon(&asset.asset_id)?.req()?.1, IconFormat::Png);
+
+        // icon files are not mistaken for assets
+        assert_eq!(registry.list()?.len(), 1);
+
+        registry.remove_icons(&asset.asset_id)?;
+        assert!(registry.icon(&asset.asset_id)?.is_none());
+
+        // svgs are rejected rather than stored verbatim, and svgs left by earlier versions are
+        // not served
+        assert!(registry.prepare_icon(Some(TEST_SVG.as_bytes())).is_err());
+        fs::write(asset_path.with_extension("svg"), TEST_SVG)?;
+        assert!(registry.icon(&asset.asset_id)?.is_none());
+        registry.remove_icons(&asset.asset_id)?;
+        assert!(!asset_path.with_extension("svg").exists());
+
+        assert!(registry.prepare_icon(Some(&test_png(true))).is_err());
+
+        // icons are only stored when enabled
+        let registry = Registry::builder(&dir, mock_chain()).build();
+        assert!(registry.prepare_icon(Some(&fetched))?.is_none());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_hook_reading_stdin() -> Result<()> {
+        crate::chain::tests::spawn_mock_esplora_server();
+        crate::entity::tests::spawn_mock_verifier_server();
+
+        let dir = temp_registry_dir("hooktest");
+        fs::create_dir_all(&dir)?;
+
+        // a hook that reads stdin until EOF, saving it along with the event argument, then waits
+        // to be released by the test
+        let hook_path = dir.join("hook.sh");
+        fs::write(
+            &hook_path,
+            "#!/bin/sh
+cat > hook-stdin
+echo $3 > hook-event
+for i in $(seq 100); do [ -f release-hook ] && break; sleep 0.05; done
+[ ! -f fail-hook ]
+",
+        )?;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+
+        let registry = Arc::new(
+            Registry::builder(&dir, mock_chain())
+                .hook_cmd(Some(hook_path.to_str().req()?.to_string()))
+                .build(),
+        );
+        let asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+        let asset_id = asset.asset_id;
+
+        let (tx, rx) = mpsc::channel();
+        let registry_ = Arc::clone(&registry);
+        let asset_ = asset.clone();
+        thread::spawn(move || tx.send(registry_.write(&asset_).is_ok()).unwrap());
+
+        // the write lock is held while the hook runs, after it read the asset until EOF
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !dir.join("hook-event").exists() {
+            assert!(Instant::now() < deadline, "hook did not read stdin");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(registry.is_write_locked());
+        fs::write(dir.join("release-hook"), "")?;
+
+        // and released once the write completes
+        assert!(rx.recv_timeout(Duration::from_secs(5))?, "write failed");
+        assert!(!registry.is_write_locked());
+
+        let piped: Asset = serde_json::from_slice(&fs::read(dir.join("hook-stdin"))?)?;
+        assert_eq!(piped.asset_id, asset_id);
+        assert_eq!(fs::read_to_string(dir.join("hook-event"))?, "add\n");
+
+        // or fails with the hook
+        let failing_dir = temp_registry_dir("hookfailtest");
+        fs::create_dir_all(&failing_dir)?;
+        fs::write(failing_dir.join("release-hook"), "")?;
+        fs::write(failing_dir.join("fail-hook"), "")?;
+        let failing = Registry::builder(&failing_dir, mock_chain())
+            .hook_cmd(Some(hook_path.to_str().req()?.to_string()))
+            .build();
+        assert!(failing.write(&asset).is_err());
+        assert!(!failing.is_write_locked());
+
+        fs::remove_dir_all(&dir)?;
+        fs::remove_dir_all(&failing_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_hook_timeout() -> Result<()> {
+        let dir = temp_registry_dir("hooktimeouttest");
+        fs::create_dir_all(&dir)?;
+
+        let hook_path = dir.join("hook.sh");
+        // with a background process that outlives the hook unless it is killed too
+        fs::write(
+            &hook_path,
+            "#!/bin/sh\n(sleep 0.5; touch survived) &\nsleep 10\n",
+        )?;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+        let hook_cmd = Some(hook_path.to_str().req()?.to_string());
+        let asset = Asset::load(path::PathBuf::from("test/asset-b1405e.json"))?;
+
+        let registry = Registry::builder(&dir, mock_chain())
+            .hook_cmd(hook_cmd.clone())
+            .hook_timeout(Some(Duration::from_millis(200)))
+            .build();
+        let started = Instant::now();
+        let err = registry
+            .exec_hook(&asset, &hook_path, HookEvent::Add, &[])
+            .unwrap_err();
+        assert!(err.to_string().starts_with("hook timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_secs(1));
+        assert!(!dir.join("survived").exists());
+
+        // the failure is only logged in non-fatal mode
+        let registry = Registry::builder(&dir, mock_chain())
+            .hook_cmd(hook_cmd)
+            .hook_timeout(Some(Duration::from_millis(200)))
+            .hook_fatal(false)
+            .build();
+        registry.exec_hook(&asset, &hook_path, HookEvent::Add, &[])?;
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_asset_locks() -> Result<()> {
+        let registry = Registry::new(&std::env::temp_dir(), mock_chain(), None);
+        let asset_a =
+            AssetId::from_hex("b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05")?;
+        let asset_b =
+            AssetId::from_hex("6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d")?;
+
+        let guard_a = registry.lock_asset(&asset_a);
+
+        // the same asset shares the lock, other assets are independent
+        assert!(AssetLockGuard::try_acquire(registry.asset_lock(&asset_a)).is_none());
+        assert!(AssetLockGuard::try_acquire(registry.asset_lock(&asset_b)).is_some());
+
+        // unused locks are cleaned up
+        registry.asset_lock(&asset_a);
+        assert_eq!(registry.asset_locks.lock().unwrap().len(), 1);
+
+        // released when the guard is dropped, including from another thread
+        thread::spawn(move || drop(guard_a)).join().unwrap();
+        assert!(AssetLockGuard::try_acquire(registry.asset_lock(&asset_a)).is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_admin_auth() -> Result<()> {
+        let ec = Secp256k1::signing_only();
+        let admin_key = secp256k1::SecretKey::from_slice(&[0x42; 32])?;
+        let admin_pubkey = secp256k1::PublicKey::from_secret_key(&ec, &admin_key);
+        let sign = |operation: &str, asset_id: &AssetId, timestamp: u64| -> Result<AdminAuth> {
+            let msg_hash = signed_msg_hash(&format_admin_sig_msg(operation, asset_id, timestamp));
+            let msg_secp = secp256k1::Message::from_slice(&msg_hash.into_inner())?;
+            let signature = ec.sign(&msg_secp, &admin_key).serialize_compact().to_vec();
+            Ok(AdminAuth {
+                timestamp,
+                signature,
+            })
+        };
+
+        let dir = temp_registry_dir("admintest");
+        let registry = Registry::new(&dir, mock_chain(), None);
+        let asset_id =
+            AssetId::from_hex("b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05")?;
+        let other_id =
+            AssetId::from_hex("6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d")?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let auth = sign("delete", &asset_id, now)?;
+        assert!(registry.verify_admin("delete", &asset_id, &auth).is_err());
+
+        let registry = Registry::builder(&dir, mock_chain())
+            .admin_pubkey(admin_pubkey.serialize().to_vec())?
+            .build();
+        registry.verify_admin("delete", &asset_id, &auth)?;
+
+        // single-use, bound to the operation and recent
+        assert!(registry.verify_admin("delete", &asset_id, &auth).is_err());
+        let auth = sign("delete", &asset_id, now + 1)?;
+        assert!(registry
+            .verify_admin("overwrite", &asset_id, &auth)
+            .is_err());
+        let stale = sign("delete", &asset_id, now - 3600)?;
+        assert!(registry.verify_admin("delete", &asset_id, &stale).is_err());
+        let future = sign("delete", &asset_id, now + 3600)?;
+        assert!(registry.verify_admin("delete", &asset_id, &future).is_err());
+
+        // still used after a restart
+        let restarted = Registry::builder(&dir, mock_chain())
+            .admin_pubkey(admin_pubkey.serialize().to_vec())?
+            .build();
+        let used = sign("delete", &asset_id, now)?;
+        assert!(restarted.verify_admin("delete", &asset_id, &used).is_err());
+
+        // tracked per operation and asset
+        let auth = sign("delete", &other_id, now - 10)?;
+        registry.verify_admin("delete", &other_id, &auth)?;
+
+        // rate limited
+        for i in 2..ADMIN_RATE_LIMIT as u64 {
+            let auth = sign("delete", &asset_id, now + i)?;
+            registry.verify_admin("delete", &asset_id, &auth)?;
+        }
+        let auth = sign("delete", &asset_id, now + ADMIN_RATE_LIMIT as u64)?;
+        let err = registry
+            .verify_admin("delete", &asset_id, &auth)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "too many admin operations, try again later"
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
 }
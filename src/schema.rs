@@ -0,0 +1,185 @@
+use regex::Regex;
+use serde_json::Value;
+
+use crate::errors::Result;
+
+lazy_static! {
+    // The JSON Schema that contracts are validated against before being deserialized
+    pub static ref CONTRACT_SCHEMA: Value =
+        serde_json::from_str(include_str!("../schema/contract.json"))
+            .expect("invalid contract schema");
+}
+
+pub fn validate_contract_schema(contract: &Value) -> Result<()> {
+    validate(contract, &CONTRACT_SCHEMA, "")
+}
+
+// Validate the value against the schema, supporting the subset of JSON Schema (draft-07) keywords
+// used by the contract schema. Failures are reported using the schema's `errorMessage` when it
+// has one (as popularized by ajv-errors), for field-specific messages.
+fn validate(value: &Value, schema: &Value, path: &str) -> Result<()> {
+    check(value, schema, path).or_else(|err| match schema["errorMessage"].as_str() {
+        Some(message) => bail!("{}", message),
+        None => Err(err),
+    })
+}
+
+fn check(value: &Value, schema: &Value, path: &str) -> Result<()> {
+    let types = match &schema["type"] {
+        Value::String(t) => vec![t.as_str()],
+        Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    };
+    ensure!(
+        types.is_empty() || types.iter().any(|t| has_type(value, t)),
+        "{} must be of type {}",
+        describe(path),
+        types.join(" or ")
+    );
+
+    match value {
+        Value::Object(object) => {
+            if let Some(required) = schema["required"].as_array() {
+                for key in required.iter().filter_map(Value::as_str) {
+                    ensure!(
+                        object.contains_key(key),
+                        "missing `{}` in {}",
+                        join_path(path, key),
+                        describe(path)
+                    );
+                }
+            }
+            if let Some(min) = schema["minProperties"].as_u64() {
+                ensure!(
+                    object.len() as u64 >= min,
+                    "{} must have at least {} fields",
+                    describe(path),
+                    min
+                );
+            }
+            if let Some(max) = schema["maxProperties"].as_u64() {
+                ensure!(
+                    object.len() as u64 <= max,
+                    "{} must have at most {} fields",
+                    describe(path),
+                    max
+                );
+            }
+            for (key, field) in object {
+                match schema["properties"].get(key) {
+                    Some(field_schema) => validate(field, field_schema, &join_path(path, key))?,
+                    None => ensure!(
+                        schema["additionalProperties"] != Value::Bool(false),
+                        "unknown field `{}` in {}",
+                        join_path(path, key),
+                        describe(path)
+                    ),
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(items_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate(item, items_schema, &format!("{}[{}]", path, i))?;
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(min) = schema["minimum"].as_f64() {
+                ensure!(number >= min, "{} must be at least {}", describe(path), min);
+            }
+            if let Some(max) = schema["maximum"].as_f64() {
+                ensure!(number <= max, "{} must be at most {}", describe(path), max);
+            }
+        }
+        Value::String(string) => {
+            if let Some(pattern) = schema["pattern"].as_str() {
+                ensure!(
+                    Regex::new(pattern)?.is_match(string),
+                    "{} must match {}",
+                    describe(path),
+                    pattern
+                );
+            }
+        }
+        Value::Bool(_) | Value::Null => (),
+    }
+    Ok(())
+}
+
+fn has_type(value: &Value, schema_type: &str) -> bool {
+    match schema_type {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_u64() || value.is_i64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn describe(path: &str) -> String {
+    if path.is_empty() {
+        "the contract".to_string()
+    } else {
+        format!("`{}`", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_contract_schema() -> Result<()> {
+        let contract = Asset::load(PathBuf::from("test/asset-b1405e.json"))?.contract;
+        validate_contract_schema(&contract)?;
+
+        // each fixture is an (invalid) contract along with the expected error
+        let fixtures: Vec<(Value, String)> =
+            serde_json::from_str(&std::fs::read_to_string("test/invalid-contracts.json")?)?;
+        assert!(fixtures.len() > 5);
+        for (contract, expected_error) in fixtures {
+            let err = validate_contract_schema(&contract).unwrap_err();
+            assert_eq!(err.to_string(), expected_error, "for {}", contract);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_keywords() -> Result<()> {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "list": { "type": "array", "items": { "type": "integer", "maximum": 3 } },
+                "any": {}
+            }
+        });
+        validate(
+            &json!({ "list": [ 1, 2 ], "any": null, "extra": 1 }),
+            &schema,
+            "",
+        )?;
+
+        let err = validate(&json!({ "list": [ 1, 5 ] }), &schema, "").unwrap_err();
+        assert_eq!(err.to_string(), "`list[1]` must be at most 3");
+        let err = validate(&json!({ "list": [ 1.5 ] }), &schema, "").unwrap_err();
+        assert_eq!(err.to_string(), "`list[0]` must be of type integer");
+        let err = validate(&json!([]), &schema, "").unwrap_err();
+        assert_eq!(err.to_string(), "the contract must be of type object");
+        Ok(())
+    }
+}
@@ -1,22 +1,27 @@
 use std::net;
 use std::path::PathBuf;
+use std::thread;
 
-use bitcoin_hashes::hex::FromHex;
+use bitcoin_hashes::hex::{FromHex, ToHex};
 use elements::{issuance::ContractHash, AssetId};
 use hyper::rt::{Future, Stream};
 use hyper::service::service_fn;
 use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
 use serde_json::Value;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[cfg(feature = "cli")]
 use structopt::StructOpt;
 
-use crate::asset::Asset;
-use crate::chain::ChainQuery;
-use crate::errors::{join_err, Result, ResultExt};
-use crate::registry::Registry;
-use crate::util::serde_from_base64;
+use crate::asset::{verify_contract_size, Asset, AssetRequest};
+use crate::chain::{rpc_auth, ChainQuery};
+use crate::context::{
+    load_root_cert, DomainBlocklist, FetchLimiter, PolicyFiles, RetryPolicy, VerifyContext,
+};
+use crate::errors::{asset_error, join_err, AssetError, Error, OptionExt, Result, ResultExt};
+use crate::network::Network;
+use crate::registry::{AdminAuth, PartitionLayout, Registry, RegistryBuilder, UniquenessPolicy};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "cli", derive(StructOpt))]
@@ -55,27 +60,646 @@ pub struct Config {
     )]
     hook_cmd: Option<String>,
 
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "hook-timeout",
+            env,
+            help = "Kill the hook script and fail the update if it runs for longer (in seconds)"
+        )
+    )]
+    hook_timeout: Option<u64>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "hook-non-fatal",
+            help = "Keep registry updates when the hook script fails, only logging a warning"
+        )
+    )]
+    hook_non_fatal: bool,
+
     #[cfg_attr(
         feature = "cli",
         structopt(
             short,
             long = "esplora-url",
             env,
+            required_unless_one = &["read-only", "elementsd-rpc-url"],
+            conflicts_with = "elementsd-rpc-url",
             help = "url for querying chain state using the esplora api"
         )
     )]
-    esplora_url: String,
+    esplora_url: Option<String>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "elementsd-rpc-url",
+            env,
+            help = "url of an elementsd json-rpc to query chain state from instead of esplora (requires -txindex=1)"
+        )
+    )]
+    elementsd_rpc_url: Option<String>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "elementsd-rpc-auth",
+            env,
+            conflicts_with = "elementsd-rpc-cookie",
+            help = "elementsd rpc credentials, as <user>:<password>"
+        )
+    )]
+    elementsd_rpc_auth: Option<String>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "elementsd-rpc-cookie",
+            env,
+            parse(from_os_str),
+            help = "path to the elementsd rpc .cookie file"
+        )
+    )]
+    elementsd_rpc_cookie: Option<PathBuf>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "esplora-timeout",
+            env,
+            default_value = "30",
+            help = "Timeout for requests to the esplora api or elementsd rpc (in seconds)"
+        )
+    )]
+    esplora_timeout: u64,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "read-only",
+            help = "Only serve the stored assets, rejecting registrations and deletions (no esplora needed)"
+        )
+    )]
+    read_only: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "name-min-len",
+            env,
+            default_value = "1",
+            help = "Minimum asset name length"
+        )
+    )]
+    name_min_len: usize,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "name-max-len",
+            env,
+            default_value = "255",
+            help = "Maximum asset name length"
+        )
+    )]
+    name_max_len: usize,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "name-max-bytes",
+            env,
+            default_value = "255",
+            help = "Maximum asset name size in bytes (utf-8 encoded)"
+        )
+    )]
+    name_max_bytes: usize,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "contract-max-size",
+            env,
+            default_value = "8192",
+            help = "Maximum size of submitted contracts (in bytes)"
+        )
+    )]
+    contract_max_size: usize,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "tickerless-min-name-chars",
+            env,
+            default_value = "0",
+            help = "Minimum non-whitespace characters in the name of assets without a ticker"
+        )
+    )]
+    tickerless_min_name_chars: usize,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            env,
+            default_value = "liquid",
+            help = "Network name (liquid, liquidtestnet or elementsregtest)"
+        )
+    )]
+    network: Network,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            env,
+            default_value = "none",
+            help = "Reject assets with an already registered ticker or name (none, ticker, name or both)"
+        )
+    )]
+    uniqueness: UniquenessPolicy,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "single-unit-nfts",
+            help = "Require assets with precision 0 to be issued with an amount of exactly 1"
+        )
+    )]
+    single_unit_nfts: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "reserved-asset",
+            parse(try_from_str = AssetId::from_hex),
+            help = "Additional asset ids that cannot be registered"
+        )
+    )]
+    reserved_assets: Vec<AssetId>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "reserved-symbols-file",
+            parse(from_os_str),
+            help = "File with tickers and names that cannot be registered, one per line"
+        )
+    )]
+    reserved_symbols_file: Option<PathBuf>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "reserve-well-known-symbols",
+            help = "Reject tickers and names of well-known symbols (like BTC, L-BTC and USD)"
+        )
+    )]
+    reserve_well_known_symbols: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "domain-allowlist-file",
+            parse(from_os_str),
+            help = "Only accept assets of the entity domains in this file, one per line (*.example.com allows subdomains)"
+        )
+    )]
+    domain_allowlist_file: Option<PathBuf>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "domain-blocklist-file",
+            parse(from_os_str),
+            help = "Reject assets of the entity domains in this file and their subdomains, one per line (*.xyz blocks a suffix). Reloaded when modified."
+        )
+    )]
+    domain_blocklist_file: Option<PathBuf>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "policy-reload-interval",
+            default_value = "30",
+            help = "How often to check the reserved symbols and domain allowlist files for changes to reload (in seconds, 0 to disable)"
+        )
+    )]
+    policy_reload_interval: u64,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "proof-case-insensitive",
+            help = "Compare domain proof pages case-insensitively (less strict)"
+        )
+    )]
+    proof_case_insensitive: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "proof-max-age",
+            env,
+            help = "Require timestamped domain proofs no older than this (in seconds)"
+        )
+    )]
+    proof_max_age: Option<u64>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "proof-www-equivalence",
+            help = "Accept domain proofs hosted on the www./apex counterpart of the domain, when missing on the domain itself"
+        )
+    )]
+    www_equivalence: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "proof-path-prefix",
+            env,
+            default_value = "/.well-known/liquid-asset-proof-",
+            help = "Path of domain proof pages, followed by the asset id (for registries of other asset namespaces)"
+        )
+    )]
+    proof_path_prefix: String,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "proof-dns-txt-fallback",
+            help = "Accept a DNS TXT record as the domain proof when the proof page is unavailable"
+        )
+    )]
+    dns_txt_fallback: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "tor-proxy",
+            env,
+            help = "SOCKS5 proxy for verifying .onion domains (e.g. 127.0.0.1:9050)"
+        )
+    )]
+    tor_proxy: Option<String>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "tls-root-cert",
+            env,
+            parse(from_os_str),
+            help = "Additional root certificate (PEM) trusted for fetching proofs, like a private CA"
+        )
+    )]
+    tls_root_cert: Option<PathBuf>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "danger-accept-invalid-certs",
+            help = "Accept invalid TLS certificates when fetching proofs. INSECURE, for testing only"
+        )
+    )]
+    danger_accept_invalid_certs: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "http-timeout",
+            env,
+            default_value = "10",
+            help = "Timeout for fetching proofs (in seconds)"
+        )
+    )]
+    http_timeout: u64,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "fetch-attempts",
+            env,
+            default_value = "1",
+            help = "Attempts for fetching domain proofs that fail with a network or server error"
+        )
+    )]
+    fetch_attempts: u32,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "fetch-backoff",
+            env,
+            default_value = "1",
+            help = "Wait before retrying a failed fetch, doubled after each retry (in seconds)"
+        )
+    )]
+    fetch_backoff: u64,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "fetch-rate-limit",
+            env,
+            help = "Maximum domain proof fetches per minute from each host (unlimited by default)"
+        )
+    )]
+    fetch_rate_limit: Option<u32>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "oracle-url",
+            env,
+            help = "External service to approve assets after verification"
+        )
+    )]
+    oracle_url: Option<String>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "twitter-bearer-token",
+            env,
+            help = "Twitter API token, enables verifying twitter entities"
+        )
+    )]
+    twitter_bearer_token: Option<String>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "nostr-relay",
+            help = "Nostr relay urls, enables verifying nostr entities"
+        )
+    )]
+    nostr_relays: Vec<String>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "keep-proofs",
+            help = "Keep the fetched domain proofs of registered assets as evidence"
+        )
+    )]
+    keep_proofs: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "registry-id",
+            help = "Registry id that issuers bind their deletion signatures to"
+        )
+    )]
+    registry_id: Option<String>,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "legacy-deletions",
+            help = "Also accept deletions signed over the legacy messages that carry no sequence number, with --registry-id (these can be replayed)"
+        )
+    )]
+    legacy_deletions: bool,
+
+    #[cfg(feature = "icons")]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "store-icons",
+            help = "Store the icons of registered assets alongside their json files"
+        )
+    )]
+    store_icons: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            env,
+            default_value = "1",
+            help = "Number of nested sub-directory levels to partition assets into"
+        )
+    )]
+    partition_levels: usize,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            env,
+            default_value = "2",
+            help = "Asset id hex characters per partition sub-directory name"
+        )
+    )]
+    partition_width: usize,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "migrate-layout",
+            help = "Move existing assets to the configured partition layout on startup"
+        )
+    )]
+    migrate_layout: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "normalize-assets",
+            help = "Rewrite existing asset files in the current serialization on startup, reporting any that fail their commitment"
+        )
+    )]
+    normalize_assets: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "index",
+            help = "Maintain an _index.json file of all assets, rebuilt on startup if missing or stale"
+        )
+    )]
+    index: bool,
+
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "checksums",
+            help = "Store the sha256 of each asset file alongside it, for detecting on-disk corruption"
+        )
+    )]
+    checksums: bool,
+
+    #[cfg(feature = "compression")]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "compress-assets",
+            help = "Store newly written asset files gzip-compressed (as <asset id>.json.gz)"
+        )
+    )]
+    compress_assets: bool,
 }
 
 //type ResponseFuture = Box<Future<Item = Response<Body>, Error = hyper::Error> + Send>;
 
+// Log to stderr, or with the `trace` feature, through a tracing subscriber that also prints the
+// spans (like the asset id and verification step) each log message was emitted within
+#[cfg(not(feature = "trace"))]
+fn init_logging(verbose: usize) {
+    stderrlog::new().verbosity(verbose + 2).init().ok();
+}
+
+#[cfg(feature = "trace")]
+fn init_logging(verbose: usize) {
+    let level = match verbose {
+        0 => tracing::Level::INFO,
+        1 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    // also installs a `log` adapter, so that the log messages are emitted as tracing events
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .try_init()
+        .ok();
+}
+
 pub fn start_server(config: Config) -> Result<()> {
     info!("Web server config: {:?}", config);
 
-    stderrlog::new().verbosity(config.verbose + 2).init().ok();
+    init_logging(config.verbose);
+
+    let chain = if config.read_only {
+        None
+    } else {
+        let timeout = Duration::from_secs(config.esplora_timeout);
+        let chain = match &config.elementsd_rpc_url {
+            Some(rpc_url) => {
+                let auth = rpc_auth(
+                    config.elementsd_rpc_auth.as_deref(),
+                    config.elementsd_rpc_cookie.as_deref(),
+                )?;
+                ChainQuery::elements_rpc(rpc_url.clone(), auth, timeout)
+            }
+            None => {
+                let esplora_url = config.esplora_url.clone().or_err("missing --esplora-url")?;
+                ChainQuery::with_timeout(esplora_url, timeout)
+            }
+        };
+        Some(chain.with_network(config.network))
+    };
+    ensure!(
+        config.proof_path_prefix.starts_with('/'),
+        "--proof-path-prefix must start with a /"
+    );
+    if config.danger_accept_invalid_certs {
+        warn!("--danger-accept-invalid-certs is set, domain proofs are NOT authenticated! never use this in production");
+    }
+    let policy_files = PolicyFiles {
+        reserved_symbols: config.reserved_symbols_file,
+        reserve_well_known_symbols: config.reserve_well_known_symbols,
+        domain_allowlist: config.domain_allowlist_file,
+    };
 
-    let chain = ChainQuery::new(config.esplora_url);
-    let registry = Arc::new(Registry::new(&config.db_path, chain, config.hook_cmd));
+    let ctx = VerifyContext {
+        name_min_len: config.name_min_len,
+        name_max_len: config.name_max_len,
+        name_max_bytes: config.name_max_bytes,
+        contract_max_size: config.contract_max_size,
+        tickerless_min_name_chars: config.tickerless_min_name_chars,
+        network: config.network,
+        reserved_assets: config.reserved_assets.into_iter().collect(),
+        domain_blocklist: match &config.domain_blocklist_file {
+            Some(path) => Some(DomainBlocklist::load(path)?),
+            None => None,
+        },
+        proof_case_insensitive: config.proof_case_insensitive,
+        proof_max_age: config.proof_max_age.map(Duration::from_secs),
+        www_equivalence: config.www_equivalence,
+        proof_path_prefix: config.proof_path_prefix,
+        dns_txt_fallback: config.dns_txt_fallback,
+        single_unit_nfts: config.single_unit_nfts,
+        tor_proxy: config.tor_proxy,
+        tls_root_cert: config
+            .tls_root_cert
+            .as_deref()
+            .map(load_root_cert)
+            .transpose()?,
+        danger_accept_invalid_certs: config.danger_accept_invalid_certs,
+        http_timeout: Duration::from_secs(config.http_timeout),
+        fetch_retry: RetryPolicy::new(
+            config.fetch_attempts,
+            Duration::from_secs(config.fetch_backoff),
+        ),
+        fetch_limiter: config.fetch_rate_limit.map(FetchLimiter::new),
+        oracle_url: config.oracle_url,
+        twitter_bearer_token: config.twitter_bearer_token,
+        nostr_relays: config.nostr_relays,
+        ..Default::default()
+    };
+    let layout = PartitionLayout::new(config.partition_levels, config.partition_width)?;
+    let builder = match chain {
+        Some(chain) => Registry::builder(&config.db_path, chain),
+        None => RegistryBuilder::read_only(&config.db_path),
+    }
+    .hook_cmd(config.hook_cmd)
+    .hook_timeout(config.hook_timeout.map(Duration::from_secs))
+    .hook_fatal(!config.hook_non_fatal)
+    .context(ctx)
+    .policy_files(policy_files.clone())?
+    .proof_evidence(config.keep_proofs)
+    .uniqueness(config.uniqueness)
+    .partition_layout(layout)
+    .index(config.index)
+    .checksums(config.checksums);
+    // without it, legacy deletions are only accepted by registries without an id
+    let builder = if config.legacy_deletions {
+        builder.legacy_deletions(true)
+    } else {
+        builder
+    };
+    let builder = match &config.registry_id {
+        Some(registry_id) => builder.registry_id(registry_id)?,
+        None => builder,
+    };
+    #[cfg(feature = "icons")]
+    let builder = builder.store_icons(config.store_icons);
+    #[cfg(feature = "compression")]
+    let builder = builder.compress(config.compress_assets);
+    let registry = Arc::new(builder.build());
+    if config.migrate_layout {
+        registry
+            .migrate_layout()
+            .context("failed migrating partition layout")?;
+    }
+    if config.normalize_assets {
+        let results = registry
+            .normalize_all()
+            .context("failed normalizing assets")?;
+        for (asset_id, result) in results {
+            if let Err(err) = result {
+                warn!("asset {} cannot be normalized: {}", asset_id.to_hex(), err);
+            }
+        }
+    }
+    if config.index {
+        registry.sync_index().context("failed syncing index")?;
+    }
+    if config.checksums {
+        registry
+            .sync_checksums()
+            .context("failed syncing checksums")?;
+    }
+
+    if config.policy_reload_interval > 0 {
+        let interval = Duration::from_secs(config.policy_reload_interval);
+        let registry = Arc::clone(&registry);
+        thread::spawn(move || watch_policy_files(&registry, &policy_files, interval));
+    }
 
     let make_service = move || {
         let registry = Arc::clone(&registry);
@@ -84,35 +708,49 @@ pub fn start_server(config: Config) -> Result<()> {
             let registry = Arc::clone(&registry);
             let method = req.method().clone();
             let uri = req.uri().clone();
+            let if_none_match = req
+                .headers()
+                .get(header::IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from);
 
             info!("processing {} {}", method, uri);
 
             Box::new(req.into_body().concat2().and_then(move |body| {
-                Ok(match handle_req(method, uri, body, &registry) {
-                    Ok(resp) => {
-                        info!("replying with {:?}", resp);
-
-                        Response::builder()
-                            .status(resp.status())
-                            .header(header::CONTENT_TYPE, resp.content_type())
-                            .body(resp.body())
-                            .unwrap()
-                    }
-
-                    Err(err) => {
-                        warn!("error processing request: {:?}", err);
-
-                        #[cfg(not(feature = "dev"))]
-                        let body = join_err(&err);
-                        #[cfg(feature = "dev")]
-                        let body = format!("{:#?}", err);
-
-                        Response::builder()
-                            .status(StatusCode::BAD_REQUEST)
-                            .body(Body::from(body))
-                            .unwrap()
-                    }
-                })
+                Ok(
+                    match handle_req(method, uri, if_none_match, body, &registry) {
+                        Ok(resp) => {
+                            info!("replying with {:?}", resp);
+
+                            let mut builder = Response::builder();
+                            builder
+                                .status(resp.status())
+                                .header(header::CONTENT_TYPE, resp.content_type());
+                            if let Some(etag) = resp.etag() {
+                                builder.header(header::ETAG, etag);
+                            }
+                            builder.body(resp.body()).unwrap()
+                        }
+
+                        Err(err) => {
+                            warn!("error processing request: {:?}", err);
+
+                            #[cfg(not(feature = "dev"))]
+                            let body = join_err(&err);
+                            #[cfg(feature = "dev")]
+                            let body = format!("{:#?}", err);
+
+                            let mut resp = Response::builder();
+                            resp.status(error_status(&err));
+                            if let Some(AssetError::FetchRateLimited(_, retry_after)) =
+                                asset_error(&err)
+                            {
+                                resp.header(header::RETRY_AFTER, retry_after.to_string().as_str());
+                            }
+                            resp.body(Body::from(body)).unwrap()
+                        }
+                    },
+                )
             }))
         })
     };
@@ -125,10 +763,35 @@ pub fn start_server(config: Config) -> Result<()> {
     Ok(())
 }
 
+// The http status code for failed requests, according to the kind of failure
+fn error_status(err: &Error) -> StatusCode {
+    match asset_error(err) {
+        Some(AssetError::NotFound) => StatusCode::NOT_FOUND,
+        Some(AssetError::AlreadyExists) | Some(AssetError::Conflict(_)) => StatusCode::CONFLICT,
+        Some(AssetError::InvalidSignature(_)) | Some(AssetError::Unauthorized(_)) => {
+            StatusCode::UNAUTHORIZED
+        }
+        Some(AssetError::Reserved(_))
+        | Some(AssetError::OracleRejected)
+        | Some(AssetError::DomainNotPermitted) => StatusCode::FORBIDDEN,
+        Some(AssetError::RateLimited) | Some(AssetError::FetchRateLimited(..)) => {
+            StatusCode::TOO_MANY_REQUESTS
+        }
+        Some(AssetError::ReadOnly) => StatusCode::FORBIDDEN,
+        Some(AssetError::ChainUnreachable)
+        | Some(AssetError::ChainTimeout)
+        | Some(AssetError::ChainServerError(_)) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
 #[derive(Debug)]
 enum Resp {
     Json(StatusCode, Value),
     Plain(StatusCode, String),
+    // The stored asset json along with its etag, or only the etag when the client's copy is current
+    Tagged(Vec<u8>, String),
+    NotModified(String),
 }
 
 impl Resp {
@@ -142,21 +805,31 @@ impl Resp {
         Resp::Plain(code, message.into())
     }
     fn body(&self) -> Body {
-        Body::from(match self {
-            Resp::Plain(_, message) => message.into(),
-            Resp::Json(_, value) => serde_json::to_string(value).unwrap(),
-        })
+        match self {
+            Resp::Plain(_, message) => Body::from(message.clone()),
+            Resp::Json(_, value) => Body::from(serde_json::to_string(value).unwrap()),
+            Resp::Tagged(json, _) => Body::from(json.clone()),
+            Resp::NotModified(_) => Body::empty(),
+        }
     }
     fn content_type(&self) -> &'static str {
         match self {
             Resp::Plain(..) => "text/plain",
-            Resp::Json(..) => "application/json",
+            Resp::Json(..) | Resp::Tagged(..) | Resp::NotModified(_) => "application/json",
         }
     }
     fn status(&self) -> StatusCode {
         match self {
             Resp::Plain(status, _) => *status,
             Resp::Json(status, _) => *status,
+            Resp::Tagged(..) => StatusCode::OK,
+            Resp::NotModified(_) => StatusCode::NOT_MODIFIED,
+        }
+    }
+    fn etag(&self) -> Option<&str> {
+        match self {
+            Resp::Tagged(_, etag) | Resp::NotModified(etag) => Some(etag),
+            _ => None,
         }
     }
 }
@@ -164,60 +837,161 @@ impl Resp {
 fn handle_req(
     method: Method,
     uri: hyper::Uri,
+    if_none_match: Option<String>,
     body: hyper::Chunk,
     registry: &Registry,
 ) -> Result<Resp> {
-    match (method, uri.path()) {
-        (Method::POST, "/") => handle_update(body, registry),
-        (Method::GET, path) => handle_get(&path[1..], registry),
-        (Method::DELETE, path) => handle_delete(&path[1..], body, registry),
-        (Method::POST, "/contract/validate") => handle_contract_validate(body),
+    // assets are available both under /asset/<asset-id> and directly under /<asset-id>
+    let path = uri.path();
+    let asset_path = if path.starts_with("/asset/") {
+        &path["/asset/".len()..]
+    } else {
+        &path[1..]
+    };
+
+    match (method, path) {
+        (Method::POST, "/") | (Method::POST, "/asset") => handle_update(body, registry),
+        (Method::GET, "/health") => handle_health(registry),
+        (Method::GET, "/stats") => handle_stats(registry),
+        (Method::GET, _) => handle_get(asset_path, if_none_match.as_deref(), registry),
+        (Method::DELETE, _) => handle_delete(asset_path, body, registry),
+        (Method::POST, "/contract/validate") => handle_contract_validate(body, registry),
+        (Method::POST, "/asset/validate") => handle_validate(body, registry),
 
         _ => Ok(Resp::plain(StatusCode::NOT_FOUND, "Not Found")),
     }
 }
 
-fn handle_get(asset_id: &str, registry: &Registry) -> Result<Resp> {
+// Only served from the index, as computing the stats without one loads every stored asset
+fn handle_stats(registry: &Registry) -> Result<Resp> {
+    if !registry.has_index() {
+        return Ok(Resp::plain(
+            StatusCode::NOT_FOUND,
+            "Stats are only available with --index",
+        ));
+    }
+    Ok(Resp::json(StatusCode::OK, registry.stats()?))
+}
+
+fn handle_get(asset_id: &str, if_none_match: Option<&str>, registry: &Registry) -> Result<Resp> {
     let asset_id = AssetId::from_hex(asset_id)?;
 
-    Ok(match registry.load(&asset_id)? {
-        Some(asset) => Resp::json(StatusCode::OK, asset),
+    Ok(match registry.load_json_with_hash(&asset_id)? {
+        Some((json, hash)) => {
+            // the stored json is served as-is, so that the etag is the hash of the served bytes
+            let etag = format!("\"{}\"", hash);
+            if if_none_match.map_or(false, |header| etag_matches(header, &etag)) {
+                Resp::NotModified(etag)
+            } else {
+                Resp::Tagged(json, etag)
+            }
+        }
         None => Resp::plain(StatusCode::NOT_FOUND, "Not Found"),
     })
 }
 
+// Match an If-None-Match header value (a comma-separated list of etags, or *) against the etag.
+// Weak validators are compared by their opaque tag, as If-None-Match uses weak comparison.
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+fn handle_health(registry: &Registry) -> Result<Resp> {
+    let report = registry.health();
+    let status = if report.is_healthy() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(Resp::json(status, report))
+}
+
+// Oversized contracts are rejected before looking up the asset on-chain
+fn parse_asset_request(body: hyper::Chunk, registry: &Registry) -> Result<Asset> {
+    // parsed along with the raw contract bytes, which the asset id commits to
+    let body = body.to_vec();
+    let request = AssetRequest::from_json(std::str::from_utf8(&body)?)
+        .context("failed parsing json request")?;
+    verify_contract_size(&request.contract, &registry.context())?;
+
+    Asset::from_request(request, registry.chain()?)
+}
+
+// Reload the policy files whenever they are modified. Invalid files are logged and ignored until
+// they are modified again, keeping the previously loaded lists.
+fn watch_policy_files(registry: &Registry, files: &PolicyFiles, interval: Duration) {
+    let mut stamps = files.stamps();
+    loop {
+        thread::sleep(interval);
+        let new_stamps = files.stamps();
+        if new_stamps == stamps {
+            continue;
+        }
+        stamps = new_stamps;
+        match registry.reload_policy() {
+            Ok(true) => info!("reloaded policy files"),
+            Ok(false) => debug!("policy files modified without changes"),
+            Err(err) => warn!("failed reloading policy files: {}", join_err(&err)),
+        }
+    }
+}
+
 fn handle_update(body: hyper::Chunk, registry: &Registry) -> Result<Resp> {
-    let asset = Asset::from_request(
-        serde_json::from_slice(&body.to_vec()).context("failed parsing json request")?,
-        registry.chain(),
-    )?;
+    let asset = parse_asset_request(body, registry)?;
 
     debug!("write asset: {:?}", asset);
 
     registry.write(&asset)?;
 
+    // reply with the asset as stored, including its verified issuance block
+    let asset = registry.load(&asset.asset_id)?.unwrap_or(asset);
     Ok(Resp::json(StatusCode::CREATED, &asset))
 }
 
+fn handle_validate(body: hyper::Chunk, registry: &Registry) -> Result<Resp> {
+    let asset = parse_asset_request(body, registry)?;
+
+    debug!("validate asset: {:?}", asset);
+
+    registry.validate(&asset)?;
+
+    Ok(Resp::json(StatusCode::OK, &asset))
+}
+
 fn handle_delete(asset_id: &str, body: hyper::Chunk, registry: &Registry) -> Result<Resp> {
     let asset_id = AssetId::from_hex(asset_id)?;
-    let asset = match registry.load(&asset_id)? {
-        None => return Ok(Resp::plain(StatusCode::NOT_FOUND, "Not found")),
-        Some(asset) => asset,
-    };
+    if registry.load(&asset_id)?.is_none() {
+        return Ok(Resp::plain(StatusCode::NOT_FOUND, "Not found"));
+    }
 
     let body = String::from_utf8(body.to_vec())?;
     let request: DeletionRequest =
         serde_json::from_str(&body).context("failed parsing json request")?;
 
-    registry.delete(&asset, &request.signature)?;
+    let signatures = request
+        .signature
+        .iter()
+        .chain(&request.signatures)
+        .map(|signature| Ok(base64::decode(signature).context("invalid signature base64")?))
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(!signatures.is_empty(), "missing signature");
+
+    registry.remove_with_sigs(
+        &asset_id,
+        &signatures,
+        request.sequence,
+        request.admin_auth.as_ref(),
+    )?;
 
     Ok(Resp::plain(StatusCode::OK, "Asset deleted"))
 }
 
-fn handle_contract_validate(body: hyper::Chunk) -> Result<Resp> {
+fn handle_contract_validate(body: hyper::Chunk, registry: &Registry) -> Result<Resp> {
     let request: ValidationRequest =
         serde_json::from_slice(&body.to_vec()).context("invalid validation request")?;
+    verify_contract_size(&request.contract, &registry.context())?;
 
     Asset::validate_contract(&request.contract, &request.contract_hash)?;
     Ok(Resp::plain(StatusCode::OK, "valid"))
@@ -225,8 +999,14 @@ fn handle_contract_validate(body: hyper::Chunk) -> Result<Resp> {
 
 #[derive(Deserialize)]
 struct DeletionRequest {
-    #[serde(deserialize_with = "serde_from_base64")]
-    signature: Vec<u8>,
+    // base64 encoded. multisig issuers provide multiple `signatures` instead.
+    signature: Option<String>,
+    #[serde(default)]
+    signatures: Vec<String>,
+    // the sequence number signed for in the deletion message, see asset::IssuerMsg
+    sequence: Option<u64>,
+    // required when the registry has an admin key configured
+    admin_auth: Option<AdminAuth>,
 }
 
 #[derive(Deserialize)]
@@ -239,12 +1019,13 @@ struct ValidationRequest {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::wait_for_port;
     use crate::{asset::Asset, chain, client::Client, entity, errors::OptionExt};
     use bitcoin::util::misc::signed_msg_hash;
     use bitcoin::PrivateKey;
-    use bitcoin_hashes::{hex::ToHex, Hash};
+    use bitcoin_hashes::{hex::ToHex, sha256, Hash};
     use secp256k1::Secp256k1;
-    use std::{str::FromStr, thread, time::Duration};
+    use std::{str::FromStr, thread};
 
     lazy_static! {
         static ref CLIENT: Client = Client::new("http://localhost:49013".parse().unwrap());
@@ -257,8 +1038,57 @@ mod tests {
         let config = Config {
             verbose: 1,
             hook_cmd: None,
+            hook_timeout: None,
+            hook_non_fatal: false,
             addr: "127.0.0.1:49013".parse().unwrap(),
-            esplora_url: "http://localhost:58713".to_string(),
+            esplora_url: Some("http://localhost:58713".to_string()),
+            elementsd_rpc_url: None,
+            elementsd_rpc_auth: None,
+            elementsd_rpc_cookie: None,
+            esplora_timeout: 30,
+            read_only: false,
+            name_min_len: 1,
+            name_max_len: 255,
+            name_max_bytes: 255,
+            contract_max_size: 8192,
+            tickerless_min_name_chars: 0,
+            network: Network::Liquid,
+            uniqueness: UniquenessPolicy::None,
+            single_unit_nfts: false,
+            reserved_assets: vec![],
+            reserved_symbols_file: None,
+            reserve_well_known_symbols: false,
+            domain_allowlist_file: None,
+            domain_blocklist_file: None,
+            policy_reload_interval: 0,
+            proof_case_insensitive: false,
+            proof_max_age: None,
+            www_equivalence: false,
+            proof_path_prefix: "/.well-known/liquid-asset-proof-".to_string(),
+            dns_txt_fallback: false,
+            tor_proxy: None,
+            tls_root_cert: None,
+            danger_accept_invalid_certs: false,
+            http_timeout: 10,
+            fetch_attempts: 1,
+            fetch_backoff: 1,
+            fetch_rate_limit: None,
+            oracle_url: None,
+            twitter_bearer_token: None,
+            nostr_relays: vec![],
+            keep_proofs: false,
+            registry_id: None,
+            legacy_deletions: false,
+            #[cfg(feature = "icons")]
+            store_icons: false,
+            partition_levels: 1,
+            partition_width: 2,
+            migrate_layout: false,
+            normalize_assets: false,
+            index: false,
+            checksums: false,
+            #[cfg(feature = "compression")]
+            compress_assets: false,
             db_path: std::env::temp_dir()
                 .join(format!("asset-registry-testdb-{}", std::process::id())),
         };
@@ -277,7 +1107,7 @@ mod tests {
 
         spawn_test_server();
 
-        thread::sleep(Duration::from_millis(250));
+        wait_for_port(49013);
     }
 
     #[test]
@@ -305,11 +1135,27 @@ mod tests {
         let msg_secp = secp256k1::Message::from_slice(&msg_hash.into_inner())?;
         let signature = EC.sign(&msg_secp, &ISSUER_KEY.key).serialize_compact();
 
-        CLIENT.delete(&asset.asset_id, &signature)?;
+        CLIENT.delete(&asset.asset_id, &signature, None)?;
 
         ensure!(CLIENT.get(&asset.asset_id)?.is_none());
         info!("asset deleted succesfully");
 
+        // the asset id commits to the exact contract bytes, which are not canonicalized
+        let rclient = reqwest::blocking::Client::new();
+        let contract = serde_json::to_string(&asset.contract)?.replace(
+            r#""name":"PPP coin","ticker""#,
+            r#""name": "PPP coin", "ticker""#,
+        );
+        let resp = rclient
+            .post("http://localhost:49013/asset/validate")
+            .body(format!(
+                r#"{{"asset_id":"{}","contract":{}}}"#,
+                asset.asset_id, contract
+            ))
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 400);
+        assert!(resp.text()?.contains("invalid asset commitment"));
+
         // re-register for followup tests
         CLIENT.register(&asset_req)?;
 
@@ -371,6 +1217,70 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test4_get_conditional() -> Result<()> {
+        let rclient = reqwest::blocking::Client::new();
+        let url = "http://localhost:49013/asset/b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05";
+
+        let resp = rclient.get(url).send()?;
+        assert_eq!(resp.status().as_u16(), 200);
+        let etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .or_err("missing etag")?
+            .to_str()?
+            .to_string();
+        // the etag is the hash of the served bytes
+        let body = resp.bytes()?;
+        assert_eq!(etag, format!("\"{}\"", sha256::Hash::hash(&body).to_hex()));
+
+        // unchanged assets are not re-sent
+        let resp = rclient
+            .get(url)
+            .header(reqwest::header::IF_NONE_MATCH, etag.as_str())
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 304);
+        assert_eq!(
+            resp.headers().get(reqwest::header::ETAG).req()?,
+            etag.as_str()
+        );
+        assert!(resp.text()?.is_empty());
+
+        let resp = rclient
+            .get(url)
+            .header(
+                reqwest::header::IF_NONE_MATCH,
+                format!("\"00\", W/{}", etag),
+            )
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 304);
+
+        // stale etags get the full asset
+        let resp = rclient
+            .get(url)
+            .header(reqwest::header::IF_NONE_MATCH, "\"00\"")
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            resp.headers().get(reqwest::header::ETAG).req()?,
+            etag.as_str()
+        );
+        let asset: Asset = resp.json()?;
+        assert_eq!(asset.name(), "PPP coin");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("\"x\" , \"abc\"", "\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"abcd\"", "\"abc\""));
+        assert!(!etag_matches("abc", "\"abc\""));
+    }
+
     #[test]
     fn test5_multiple_tickerless() -> Result<()> {
         let asset1 = CLIENT.register(&serde_json::from_value(json!({
@@ -418,6 +1328,49 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test7_asset_routes() -> Result<()> {
+        let rclient = reqwest::blocking::Client::new();
+        let asset_id = "b1405e4eefa91c6690198b4f85d73e8e0babee08f73b2c8af411486dc28dbc05";
+
+        let resp = rclient
+            .get(&format!("http://localhost:49013/asset/{}", asset_id))
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 200);
+        let asset: Asset = resp.json()?;
+        assert_eq!(asset.id().to_hex(), asset_id);
+
+        // already registered by test1
+        let resp = rclient
+            .post("http://localhost:49013/asset")
+            .json(&json!({ "asset_id": asset_id, "contract": asset.contract }))
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        let resp = rclient
+            .post("http://localhost:49013/asset/validate")
+            .json(&json!({ "asset_id": asset_id, "contract": asset.contract }))
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        let resp = rclient
+            .get(&format!("http://localhost:49013/asset/{}", "00".repeat(32)))
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 404);
+
+        // the test server runs without --index
+        let resp = rclient.get("http://localhost:49013/stats").send()?;
+        assert_eq!(resp.status().as_u16(), 404);
+
+        let resp = rclient
+            .delete(&format!("http://localhost:49013/asset/{}", asset_id))
+            .json(&json!({ "signature": base64::encode(&[0; 64][..]) }))
+            .send()?;
+        assert_eq!(resp.status().as_u16(), 401);
+
+        Ok(())
+    }
+
     #[test]
     fn test_validate_contract() -> Result<()> {
         try_contract(
@@ -462,6 +1415,21 @@ mod tests {
             Some("invalid `ticker`"),
         )?;
 
+        try_contract(
+            json!({
+                "entity":{"domain":"test.dev"},
+                "issuer_pubkey": "037c7db0528e8b7b58e698ac104764f6852d74b5a7335bffcdad0ce799dd7742ec",
+                "name":"PPP coin",
+                "ticker":"PPP",
+                "precision":"8",
+                "version":0
+            }),
+            ContractHash::from_str(
+                "ac5a08996e50a12b38e2ad9e5e3ff2899db889b08422361d9fbed65d7b9c209b",
+            )?,
+            Some("`precision` must be an integer 0-8"),
+        )?;
+
         try_contract(
             json!({
                 "entity":{"domain":"test.dev"},
@@ -0,0 +1,20 @@
+// Structured spans around the verification pipeline, emitted with the `trace` feature so that
+// a single submission can be followed across its steps. The spans carry the asset id, domain and
+// verification step as fields. Without the feature these compile to nothing.
+
+// Enter a span for the remainder of the enclosing block. Must not be held across an await point.
+macro_rules! enter_span {
+    ($name:expr $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!($name $(, $($fields)*)?);
+        #[cfg(feature = "trace")]
+        let _entered = _span.enter();
+    };
+}
+
+// Run one named verification step within its own span
+#[cfg_attr(not(feature = "trace"), allow(unused_variables))]
+pub(crate) fn in_step<T>(step: &'static str, f: impl FnOnce() -> T) -> T {
+    enter_span!("step", step);
+    f()
+}
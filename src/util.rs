@@ -1,10 +1,15 @@
-use std::fmt;
+use std::{fmt, fs, path};
 
+use bitcoin::bech32::{self, FromBase32};
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{Builder, Script};
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::util::bip143::SigHashCache;
 use bitcoin::util::misc::signed_msg_hash;
-use bitcoin::Txid;
-use bitcoin_hashes::{hex::ToHex, Hash};
-use regex::RegexSet;
-use secp256k1::Secp256k1;
+use bitcoin::{OutPoint, SigHashType, Transaction, TxIn, TxOut, Txid};
+use bitcoin_hashes::{hash160, hex::ToHex, sha256, Hash, HashEngine};
+use regex::{Regex, RegexSet};
+use secp256k1::{PublicKey, Secp256k1};
 use serde::{Deserialize, Deserializer, Serializer};
 
 use crate::errors::{OptionExt, Result, ResultExt};
@@ -44,11 +49,262 @@ pub fn verify_bitcoin_msg(
         .context("signature veritification failed")?)
 }
 
+// Public keys must be valid secp256k1 points in the compressed SEC encoding (33 bytes with a 02 or
+// 03 prefix), which signed message verification assumes. Uncompressed and hybrid encodings are
+// rejected even though secp256k1 would parse them.
 pub fn verify_pubkey(pubkey: &[u8]) -> Result<()> {
-    secp256k1::PublicKey::from_slice(pubkey)?;
+    match (pubkey.len(), pubkey.first()) {
+        (33, Some(0x02)) | (33, Some(0x03)) => (),
+        (65, Some(0x04)) => {
+            bail!("uncompressed public keys are not supported, use the compressed encoding")
+        }
+        (65, Some(0x06)) | (65, Some(0x07)) => {
+            bail!("hybrid public keys are not supported, use the compressed encoding")
+        }
+        (len, _) => bail!(
+            "invalid public key encoding (expected 33 bytes with a 02 or 03 prefix, got {} bytes)",
+            len
+        ),
+    }
+    PublicKey::from_slice(pubkey).context("public key is not a valid secp256k1 point")?;
     Ok(())
 }
 
+// Verify a signature by the public key over the message, either in the legacy bitcoin signed
+// message format (64 bytes, or 65 with the recovery flag) or as a BIP-322 signature
+pub fn verify_msg_sig(
+    ec: &Secp256k1<secp256k1::VerifyOnly>,
+    pubkey: &[u8],
+    signature: &[u8],
+    msg: &str,
+) -> Result<()> {
+    if signature.len() == 64 || signature.len() == 65 {
+        verify_bitcoin_msg(ec, pubkey, signature, msg)
+    } else {
+        verify_bip322(ec, pubkey, signature, msg)
+    }
+}
+
+// Verify a BIP-322 "simple" signature (a serialized witness stack) over the message, made by the
+// P2WPKH or the P2TR address (key path spend, with no script tree) of the compressed public key
+pub fn verify_bip322(
+    ec: &Secp256k1<secp256k1::VerifyOnly>,
+    pubkey: &[u8],
+    signature: &[u8],
+    msg: &str,
+) -> Result<()> {
+    let witness: Vec<Vec<u8>> =
+        deserialize(signature).context("invalid bip322 signature encoding")?;
+    match &witness[..] {
+        [sig, witness_pubkey] => {
+            ensure!(
+                &witness_pubkey[..] == pubkey,
+                "bip322 signature is by a different key"
+            );
+            verify_bip322_p2wpkh(ec, pubkey, sig, msg)
+        }
+        [sig] => verify_bip322_p2tr(pubkey, sig, msg),
+        _ => bail!(
+            "unsupported bip322 witness, only P2WPKH and P2TR key path signatures are accepted"
+        ),
+    }
+}
+
+fn verify_bip322_p2wpkh(
+    ec: &Secp256k1<secp256k1::VerifyOnly>,
+    pubkey: &[u8],
+    sig: &[u8],
+    msg: &str,
+) -> Result<()> {
+    ensure!(
+        sig.last() == Some(&0x01),
+        "unsupported bip322 sighash type, only SIGHASH_ALL is accepted"
+    );
+
+    let pubkey_hash = hash160::Hash::hash(pubkey);
+    let script_pubkey = Builder::new()
+        .push_int(0)
+        .push_slice(&pubkey_hash[..])
+        .into_script();
+    let script_code = Builder::new()
+        .push_opcode(opcodes::all::OP_DUP)
+        .push_opcode(opcodes::all::OP_HASH160)
+        .push_slice(&pubkey_hash[..])
+        .push_opcode(opcodes::all::OP_EQUALVERIFY)
+        .push_opcode(opcodes::all::OP_CHECKSIG)
+        .into_script();
+    let to_sign = bip322_to_sign(&bip322_to_spend(msg, script_pubkey));
+    let sighash = SigHashCache::new(&to_sign).signature_hash(0, &script_code, 0, SigHashType::All);
+
+    let sig = secp256k1::Signature::from_der(&sig[..sig.len() - 1])
+        .context("invalid bip322 signature")?;
+    let msg = secp256k1::Message::from_slice(&sighash[..])?;
+    Ok(ec
+        .verify(&msg, &sig, &PublicKey::from_slice(pubkey)?)
+        .context("bip322 signature verification failed")?)
+}
+
+// The taproot output key is derived and the signature verified with the secp256k1 version that
+// provides schnorrsig (see SCHNORR_EC), as the one used for ecdsa has no schnorr support
+fn verify_bip322_p2tr(pubkey: &[u8], sig: &[u8], msg: &str) -> Result<()> {
+    ensure!(
+        sig.len() == 64 || (sig.len() == 65 && sig[64] == 0x01),
+        "unsupported bip322 taproot signature, only SIGHASH_DEFAULT/ALL are accepted"
+    );
+
+    // the internal key is the x coordinate of the public key, tweaked with no script tree
+    let internal_key = pubkey.get(1..33).req()?;
+    let mut engine = tagged_hash_engine("TapTweak");
+    engine.input(internal_key);
+    let tweak = sha256::Hash::from_engine(engine);
+    let mut output_key = secp256k1_schnorr::schnorrsig::PublicKey::from_slice(internal_key)
+        .context("invalid x-only public key")?;
+    output_key.tweak_add_assign(&SCHNORR_EC, &tweak[..])?;
+    let output_key = output_key.serialize();
+
+    let script_pubkey = Builder::new()
+        .push_int(1)
+        .push_slice(&output_key[..])
+        .into_script();
+    let to_spend = bip322_to_spend(msg, script_pubkey);
+    let to_sign = bip322_to_sign(&to_spend);
+    let sighash = bip341_sighash(
+        &to_sign,
+        &to_spend.output[0],
+        sig.get(64).copied().unwrap_or(0),
+    );
+
+    verify_schnorr_sig(&output_key, &sighash.into_inner(), &sig[..64])
+        .context("bip322 signature verification failed")?;
+    Ok(())
+}
+
+// The BIP341 sighash of the transaction's single input, spending the output via the key path
+// with SIGHASH_DEFAULT or SIGHASH_ALL. Not provided by the rust-bitcoin version used, which
+// predates taproot.
+fn bip341_sighash(tx: &Transaction, spent: &TxOut, sighash_type: u8) -> sha256::Hash {
+    let txin = &tx.input[0];
+    let outputs: Vec<u8> = tx.output.iter().flat_map(serialize).collect();
+
+    let mut engine = tagged_hash_engine("TapSighash");
+    engine.input(&[0, sighash_type]); // epoch and sighash type
+    engine.input(&serialize(&tx.version));
+    engine.input(&serialize(&tx.lock_time));
+    engine.input(&sha256::Hash::hash(&serialize(&txin.previous_output))[..]);
+    engine.input(&sha256::Hash::hash(&serialize(&spent.value))[..]);
+    engine.input(&sha256::Hash::hash(&serialize(&spent.script_pubkey))[..]);
+    engine.input(&sha256::Hash::hash(&serialize(&txin.sequence))[..]);
+    engine.input(&sha256::Hash::hash(&outputs)[..]);
+    engine.input(&[0]); // spend type: key path with no annex
+    engine.input(&serialize(&0u32)); // input index
+    sha256::Hash::from_engine(engine)
+}
+
+fn bip322_message_hash(msg: &str) -> sha256::Hash {
+    let mut engine = tagged_hash_engine("BIP0322-signed-message");
+    engine.input(msg.as_bytes());
+    sha256::Hash::from_engine(engine)
+}
+
+// BIP-322's virtual to_spend transaction, paying to the script pubkey of the signing address
+fn bip322_to_spend(msg: &str, script_pubkey: Script) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Builder::new()
+                .push_int(0)
+                .push_slice(&bip322_message_hash(msg)[..])
+                .into_script(),
+            sequence: 0,
+            witness: vec![],
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey,
+        }],
+    }
+}
+
+// BIP-322's virtual to_sign transaction, spending to_spend to an empty OP_RETURN
+fn bip322_to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend.txid(), 0),
+            script_sig: Script::new(),
+            sequence: 0,
+            witness: vec![],
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .into_script(),
+        }],
+    }
+}
+
+lazy_static! {
+    // a context of the secp256k1 version providing schnorrsig, which cannot be shared with the
+    // one used for ecdsa
+    static ref SCHNORR_EC: secp256k1_schnorr::Secp256k1<secp256k1_schnorr::VerifyOnly> =
+        secp256k1_schnorr::Secp256k1::verification_only();
+}
+
+// Verify a BIP340 schnorr signature over a 32 bytes message (as used by nostr), given the
+// x-only public key
+pub fn verify_schnorr_sig(pubkey: &[u8; 32], msg: &[u8; 32], signature: &[u8]) -> Result<()> {
+    use secp256k1_schnorr::{schnorrsig, Message};
+
+    let pubkey = schnorrsig::PublicKey::from_slice(pubkey).context("invalid x-only public key")?;
+    let signature = schnorrsig::Signature::from_slice(signature).context("invalid signature")?;
+    let msg = Message::from_slice(msg)?;
+    Ok(SCHNORR_EC
+        .schnorrsig_verify(&signature, &msg, &pubkey)
+        .context("signature verification failed")?)
+}
+
+fn tagged_hash_engine(tag: &str) -> sha256::HashEngine {
+    let tag_hash = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag_hash[..]);
+    engine.input(&tag_hash[..]);
+    engine
+}
+
+// Decode a nostr public key from its bech32 `npub` encoding
+pub fn decode_npub(npub: &str) -> Result<[u8; 32]> {
+    ensure!(
+        npub.to_lowercase() == npub,
+        "should be provided in lower-case"
+    );
+    let (hrp, data) = bech32::decode(npub).context("invalid bech32 encoding")?;
+    ensure!(hrp == "npub", "expected an npub");
+    let pubkey = decode_hex32_bytes(&Vec::<u8>::from_base32(&data)?)?;
+    secp256k1_schnorr::schnorrsig::PublicKey::from_slice(&pubkey)
+        .context("invalid x-only public key")?;
+    Ok(pubkey)
+}
+
+// Decode 32 bytes from lower-case hex, like the ids and public keys of nostr events
+pub fn decode_hex32(hex_str: &str) -> Result<[u8; 32]> {
+    ensure!(
+        hex_str.len() == 64 && hex_str.to_lowercase() == hex_str,
+        "expected 32 bytes of lower-case hex"
+    );
+    decode_hex32_bytes(&hex::decode(hex_str)?)
+}
+
+fn decode_hex32_bytes(bytes: &[u8]) -> Result<[u8; 32]> {
+    ensure!(bytes.len() == 32, "expected 32 bytes");
+    let mut array = [0u8; 32];
+    array.copy_from_slice(bytes);
+    Ok(array)
+}
+
 // Utility to transform booleans into Options
 pub trait BoolOpt: Sized {
     fn as_option(self) -> Option<()>;
@@ -64,6 +320,106 @@ impl BoolOpt for bool {
     }
 }
 
+lazy_static! {
+    static ref RE_CUSTOM_KIND: Regex = Regex::new(r"^[a-z0-9][a-z0-9\-]{0,63}$").unwrap();
+    // no slashes, the entity is used as part of file names
+    static ref RE_CUSTOM_VALUE: Regex = Regex::new(r"^[[:alnum:]._@:+\-]{1,255}$").unwrap();
+}
+
+pub fn verify_custom_entity(kind: &str, value: &str) -> Result<()> {
+    ensure!(RE_CUSTOM_KIND.is_match(kind), "invalid `kind`");
+    ensure!(RE_CUSTOM_VALUE.is_match(value), "invalid `value`");
+    Ok(())
+}
+
+lazy_static! {
+    static ref RE_TWITTER_HANDLE: Regex = Regex::new(r"^[a-zA-Z0-9_]{1,15}$").unwrap();
+}
+
+// Twitter handles, without the leading @
+pub fn verify_twitter_handle(handle: &str) -> Result<()> {
+    ensure!(
+        RE_TWITTER_HANDLE.is_match(handle),
+        "must only contain up to 15 alphanumeric characters or underscores"
+    );
+    // handles are case-insensitive, require a canonical form
+    ensure!(
+        handle.to_lowercase() == handle,
+        "should be provided in lower-case"
+    );
+    Ok(())
+}
+
+lazy_static! {
+    static ref RE_GITHUB_USER: Regex = Regex::new(r"^[a-z0-9](-?[a-z0-9]){0,38}$").unwrap();
+}
+
+pub fn verify_github_user(user: &str) -> Result<()> {
+    ensure!(
+        user.to_lowercase() == user,
+        "should be provided in lower-case"
+    );
+    ensure!(
+        user.len() <= 39 && RE_GITHUB_USER.is_match(user),
+        "must only contain up to 39 alphanumeric characters or single inner hyphens"
+    );
+    Ok(())
+}
+
+// Normalize a string for lookalike comparisons: ignore case and non-alphanumeric separators, and
+// map commonly confused characters (digits and cyrillic homoglyphs) to a single representative
+pub fn confusable_skeleton(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '0' | 'о' => 'o',
+            '1' | 'i' | 'і' => 'l',
+            '3' | 'е' => 'e',
+            '4' | 'а' => 'a',
+            '5' => 's',
+            '8' => 'b',
+            'с' => 'c',
+            'р' => 'p',
+            'х' => 'x',
+            'т' => 't',
+            'к' => 'k',
+            'м' => 'm',
+            'н' => 'h',
+            'в' => 'b',
+            'у' => 'y',
+            c => c,
+        })
+        .collect()
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+
+    prev[b.len()]
+}
+
+// Similarity score between 0 (unrelated) and 1 (lookalikes), based on the edit distance between
+// the confusable skeletons of both strings
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (confusable_skeleton(a), confusable_skeleton(b));
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / max_len as f64
+}
+
 // Domain name validation code extracted from https://github.com/rushmorem/publicsuffix/blob/master/src/lib.rs,
 // MIT, Copyright (c) 2016 Rushmore Mushambi
 // (with some changes annotated with "shesek" comments)
@@ -78,16 +434,28 @@ lazy_static! {
     };
 }
 
+// shesek: internationalized domains are accepted in either their Unicode or Punycode form. The
+// rules below are checked against the Punycode form, as used for the actual requests.
 pub fn verify_domain_name(domain: &str) -> Result<()> {
     ensure!(!domain.starts_with('.'), "cannot start with a dot");
-    ensure!(
-        idna_to_ascii(domain)? == domain.to_string(),
-        "should be provided in ASCII/Punycode form, not IDNA Unicode"
-    );
+    let ascii = domain_to_ascii(domain)?;
+    if domain.is_ascii() {
+        ensure!(ascii == domain, "invalid domain");
+    } else {
+        // only the normalized form is accepted, so that each domain has a single unicode
+        // representation
+        ensure!(
+            domain_to_unicode(&ascii) == domain,
+            "should be provided in normalized (NFC, lower-case) form"
+        );
+    }
     ensure!(
         domain.to_lowercase() == domain,
         "should be provided in lower-case"
     );
+    verify_domain_scripts(&domain_to_unicode(&ascii))?;
+
+    let domain = ascii.as_str();
     ensure!(domain.len() <= 255, "must be up to 255 characters");
 
     let mut labels: Vec<&str> = domain.split('.').collect();
@@ -113,12 +481,56 @@ pub fn verify_domain_name(domain: &str) -> Result<()> {
     Ok(())
 }
 
-fn idna_to_ascii(domain: &str) -> Result<String> {
+// The Punycode form of internationalized domains, as used for requests (and DNS lookups)
+pub fn domain_to_ascii(domain: &str) -> Result<String> {
     Ok(idna::domain_to_ascii(domain)
         .ok()
         .or_err("invalid domain")?)
 }
 
+// The Unicode form of internationalized domains, for display
+pub fn domain_to_unicode(domain: &str) -> String {
+    idna::domain_to_unicode(domain).0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+}
+
+// Reject labels that mix letters of scripts with many look-alike characters, which could be used
+// for homograph domains impersonating another (like a latin domain with a cyrillic "а")
+fn verify_domain_scripts(domain: &str) -> Result<()> {
+    for label in domain.split('.') {
+        let mut scripts = label.chars().filter_map(confusable_script);
+        if let Some(first) = scripts.next() {
+            if let Some(other) = scripts.find(|script| *script != first) {
+                bail!(
+                    "label `{}` mixes {:?} and {:?} characters",
+                    label,
+                    first,
+                    other
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn confusable_script(c: char) -> Option<Script> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+    match c as u32 {
+        0x41..=0x5a | 0x61..=0x7a | 0xc0..=0x24f | 0x1e00..=0x1eff => Some(Script::Latin),
+        0x370..=0x3ff | 0x1f00..=0x1fff => Some(Script::Greek),
+        0x400..=0x52f | 0x2de0..=0x2dff | 0xa640..=0xa69f => Some(Script::Cyrillic),
+        _ => None,
+    }
+}
+
 /// Deserializes a base64 string to a `Vec<u8>`.
 pub fn serde_from_base64<'de, D>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error>
 where
@@ -139,6 +551,35 @@ where
         .and_then(|string| hex::decode(&string).map_err(|err| Error::custom(err.to_string())))
 }
 
+/// Deserializes a list of hex strings to a `Some(Vec<Vec<u8>>)`.
+pub fn serde_from_hex_list<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<Vec<u8>>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|string| hex::decode(string).map_err(|err| Error::custom(err.to_string())))
+        .collect::<std::result::Result<_, _>>()
+        .map(Some)
+}
+
+/// Serializes an optional list of Vec<u8>s into a list of hex strings.
+pub fn serde_to_hex_list<S>(
+    list: &Option<Vec<Vec<u8>>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match list {
+        Some(list) => serializer.collect_seq(list.iter().map(|buffer| buffer.to_hex())),
+        None => serializer.serialize_none(),
+    }
+}
+
 /// Serializes a Vec<u8> into a hex string.
 pub fn serde_to_hex<T, S>(buffer: &T, serializer: S) -> std::result::Result<S::Ok, S::Error>
 where
@@ -148,19 +589,183 @@ where
     serializer.serialize_str(&buffer.as_ref().to_hex())
 }
 
+// Extension appended to the json files of assets stored compressed (`<asset id>.json.gz`)
+pub const COMPRESSED_EXTENSION: &str = "gz";
+
+// Whether the file is stored gzip-compressed, according to its extension
+pub fn is_compressed(path: &path::Path) -> bool {
+    path.extension()
+        .map_or(false, |ext| ext == COMPRESSED_EXTENSION)
+}
+
+// Read the json file, transparently decompressing it when stored compressed
+pub fn read_json_file(path: &path::Path) -> Result<Vec<u8>> {
+    let contents = fs::read(path)?;
+    if is_compressed(path) {
+        Ok(gunzip(&contents).context("invalid compressed file")?)
+    } else {
+        Ok(contents)
+    }
+}
+
+#[cfg(feature = "compression")]
+pub fn gzip(contents: &[u8]) -> Result<Vec<u8>> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(contents)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(feature = "compression")]
+pub fn gunzip(contents: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut decompressed = vec![];
+    flate2::read::GzDecoder::new(contents).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "compression"))]
+pub fn gzip(_contents: &[u8]) -> Result<Vec<u8>> {
+    bail!("compressed asset files require the `compression` feature")
+}
+
+#[cfg(not(feature = "compression"))]
+pub fn gunzip(_contents: &[u8]) -> Result<Vec<u8>> {
+    bail!("compressed asset files require the `compression` feature")
+}
+
+// Wait for a local test server to start accepting connections
+#[cfg(test)]
+pub fn wait_for_port(port: u16) {
+    use std::net::{SocketAddr, TcpStream};
+    use std::{thread, time::Duration};
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    for _ in 0..100 {
+        if TcpStream::connect_timeout(&addr, Duration::from_millis(100)).is_ok() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    panic!("test server on port {} did not start listening", port);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_verify_pubkey() -> Result<()> {
+        let compressed =
+            hex::decode("037c7db0528e8b7b58e698ac104764f6852d74b5a7335bffcdad0ce799dd7742ec")?;
+        verify_pubkey(&compressed)?;
+
+        let uncompressed = PublicKey::from_slice(&compressed)?.serialize_uncompressed();
+        assert!(PublicKey::from_slice(&uncompressed).is_ok());
+        assert_eq!(
+            verify_pubkey(&uncompressed).unwrap_err().to_string(),
+            "uncompressed public keys are not supported, use the compressed encoding"
+        );
+
+        // hybrid encodings carry the parity of y in the prefix, like compressed ones
+        let mut hybrid = uncompressed;
+        hybrid[0] = 0x06 | (uncompressed[64] & 1);
+        assert_eq!(
+            verify_pubkey(&hybrid).unwrap_err().to_string(),
+            "hybrid public keys are not supported, use the compressed encoding"
+        );
+
+        // malformed
+        for pubkey in &[&compressed[..32], &compressed[1..], &[][..]] {
+            assert!(verify_pubkey(pubkey)
+                .unwrap_err()
+                .to_string()
+                .starts_with("invalid public key encoding"));
+        }
+        let mut wrong_prefix = compressed.clone();
+        wrong_prefix[0] = 0x05;
+        assert!(verify_pubkey(&wrong_prefix).is_err());
+
+        // off-curve: there is no point with x=0, and x must be below the field size
+        for x in &["00", "ff"] {
+            let pubkey = hex::decode(format!("02{}", x.repeat(32)))?;
+            assert_eq!(
+                verify_pubkey(&pubkey).unwrap_err().to_string(),
+                "public key is not a valid secp256k1 point"
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_verify_domain_name() {
         assert!(verify_domain_name("foo.com").is_ok());
         assert!(verify_domain_name("foO.com").is_err());
         assert!(verify_domain_name(">foo.com").is_err());
-        assert!(verify_domain_name("δοκιμή.com").is_err());
+        assert!(verify_domain_name("δοκιμή.com").is_ok());
         assert!(verify_domain_name("xn--jxalpdlp.com").is_ok());
     }
 
+    #[test]
+    fn test_idn_domains() {
+        assert!(verify_domain_name("café.com").is_ok());
+        assert!(verify_domain_name("xn--caf-dma.com").is_ok());
+        assert_eq!(domain_to_ascii("café.com").unwrap(), "xn--caf-dma.com");
+        assert_eq!(domain_to_unicode("xn--caf-dma.com"), "café.com");
+
+        // not normalized: upper-case, and with a combining accent (NFD)
+        assert!(verify_domain_name("CAFÉ.com").is_err());
+        assert!(verify_domain_name("cafe\u{301}.com").is_err());
+
+        // latin with a cyrillic "а", in either form
+        let homograph = "pаypal.com";
+        assert!(verify_domain_name(homograph).is_err());
+        assert!(verify_domain_name(&domain_to_ascii(homograph).unwrap()).is_err());
+        // but scripts may differ across labels
+        assert!(verify_domain_name("пример.café.com").is_ok());
+    }
+
+    #[test]
+    fn test_verify_twitter_handle() {
+        assert!(verify_twitter_handle("liquid_bitcoin").is_ok());
+        assert!(verify_twitter_handle("@liquid").is_err());
+        assert!(verify_twitter_handle("Liquid").is_err());
+        assert!(verify_twitter_handle("a_very_long_handle").is_err());
+        assert!(verify_twitter_handle("").is_err());
+    }
+
+    #[test]
+    fn test_verify_github_user() {
+        assert!(verify_github_user("shesek").is_ok());
+        assert!(verify_github_user("elements-project").is_ok());
+        assert!(verify_github_user("Shesek").is_err());
+        assert!(verify_github_user("-shesek").is_err());
+        assert!(verify_github_user("shesek-").is_err());
+        assert!(verify_github_user("elements--project").is_err());
+        assert!(verify_github_user(&"a".repeat(40)).is_err());
+    }
+
+    #[test]
+    fn test_verify_custom_entity() {
+        assert!(verify_custom_entity("employee-id", "E1234").is_ok());
+        assert!(verify_custom_entity("Employee", "E1234").is_err());
+        assert!(verify_custom_entity("employee-id", "../E1234").is_err());
+        assert!(verify_custom_entity("employee-id", "").is_err());
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(similarity("Tether USD", "Tether-USD"), 1.0);
+        assert_eq!(similarity("TETHER", "T3THER"), 1.0);
+        assert_eq!(similarity("Tether", "Теther"), 1.0); // cyrillic Т and е
+        assert!(similarity("Tether USD", "Tether USDX") > 0.8);
+        assert!(similarity("Tether USD", "Foo Coin") < 0.5);
+    }
+
     #[test]
     fn test_bitcoin_msg_sign() -> Result<()> {
         let ec = Secp256k1::verification_only();
@@ -174,4 +779,129 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_bip322() -> Result<()> {
+        let ec = Secp256k1::verification_only();
+
+        // test vectors from BIP-322, for the address bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l
+        assert_eq!(
+            bip322_message_hash("").to_hex(),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            bip322_message_hash("Hello World").to_hex(),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let pubkey =
+            hex::decode("02c7f12003196442943d8588e01aee840423cc54fc1521526a3b85c2b0cbd58872")?;
+        let script_pubkey = Builder::new()
+            .push_int(0)
+            .push_slice(&hash160::Hash::hash(&pubkey)[..])
+            .into_script();
+        for (msg, to_spend_txid, to_sign_txid) in &[
+            (
+                "",
+                "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7",
+                "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6",
+            ),
+            (
+                "Hello World",
+                "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b",
+                "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf",
+            ),
+        ] {
+            let to_spend = bip322_to_spend(msg, script_pubkey.clone());
+            assert_eq!(to_spend.txid().to_hex(), *to_spend_txid);
+            assert_eq!(bip322_to_sign(&to_spend).txid().to_hex(), *to_sign_txid);
+        }
+
+        let p2wpkh_empty = base64::decode("AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=")?;
+        let p2wpkh_hello = base64::decode("AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=")?;
+        let p2tr_hello = base64::decode("AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==")?;
+
+        verify_bip322(&ec, &pubkey, &p2wpkh_empty, "")?;
+        verify_bip322(&ec, &pubkey, &p2wpkh_hello, "Hello World")?;
+
+        assert!(verify_bip322(&ec, &pubkey, &p2wpkh_empty, "Hello World").is_err());
+        assert!(verify_bip322(&ec, &pubkey, &p2wpkh_hello, "Hello World!").is_err());
+
+        verify_bip322(&ec, &pubkey, &p2tr_hello, "Hello World")?;
+        assert!(verify_bip322(&ec, &pubkey, &p2tr_hello, "Hello World!").is_err());
+
+        let other_pubkey =
+            hex::decode("026be637f97bc191c27522577bd6fe284b54404321652fcc4eb62aa0f4cfd6d172")?;
+        assert!(verify_bip322(&ec, &other_pubkey, &p2wpkh_hello, "Hello World").is_err());
+        assert!(verify_bip322(&ec, &other_pubkey, &p2tr_hello, "Hello World").is_err());
+        assert!(verify_bip322(&ec, &pubkey, &[0x00], "Hello World").is_err());
+
+        // the format is detected by verify_msg_sig
+        verify_msg_sig(&ec, &pubkey, &p2tr_hello, "Hello World")?;
+        let legacy_sig = base64::decode("H7719XlaZJT6H4HrD9KXga7yfd0MR8lSKc34TN/u0nhpecU9bVfaUDcpJtOFodfxf+IyFIE5V2A9878mM5bWvbE=")?;
+        verify_msg_sig(&ec, &other_pubkey, &legacy_sig, "test")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nostr_keys_and_sigs() -> Result<()> {
+        let npub = "npub1a5dpktresju538f4gcelv3wj385z2pr8had7snvvx7yv0ttuhuzq8af424";
+        let pubkey = decode_npub(npub)?;
+        assert_eq!(
+            pubkey.to_hex(),
+            "ed1a1b2c7984b9489d354633f645d289e8250467bf5be84d8c3788c7ad7cbf04"
+        );
+        assert!(decode_npub(&npub.to_uppercase()).is_err());
+        assert!(decode_npub(&npub.replace("npub", "nsec")).is_err());
+        assert!(
+            decode_npub("npub1a5dpktresju538f4gcelv3wj385z2pr8had7snvvx7yv0ttuhuzq8af425").is_err()
+        );
+
+        assert_eq!(decode_hex32(&pubkey.to_hex())?, pubkey);
+        assert!(decode_hex32(&pubkey.to_hex().to_uppercase()).is_err());
+        assert!(decode_hex32("00").is_err());
+
+        // a nostr event id signed by the npub
+        let msg = decode_hex32("44f5d85073419d2ab28ec23f4f77d83a87e017faaf040ec5ab2874dc7c66f5a2")?;
+        let sig = hex::decode("e28214e5b98449f88a5605be8502eebbf2ff829f695db7d4a4e3220dc8b15ecf98ffb26385939ca15138bbcc0f05f737dac538340162a22bbbce1659c9648094")?;
+        verify_schnorr_sig(&pubkey, &msg, &sig)?;
+
+        let mut bad_sig = sig.clone();
+        bad_sig[63] ^= 1;
+        assert!(verify_schnorr_sig(&pubkey, &msg, &bad_sig).is_err());
+        let mut other_msg = msg;
+        other_msg[0] ^= 1;
+        assert!(verify_schnorr_sig(&pubkey, &other_msg, &sig).is_err());
+        assert!(verify_schnorr_sig(&pubkey, &msg, &sig[..63]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bip340_vectors() -> Result<()> {
+        // (public key, message, signature, valid) from BIP340's test-vectors.csv
+        let vectors = [
+            // 0-3: valid signatures
+            ("F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9", "0000000000000000000000000000000000000000000000000000000000000000", "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0", true),
+            ("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A", true),
+            ("DD308AFEC5777E13121FA72B9CC1B7CC0139715309B086C960E18FD969774EB8", "7E2D58D8B3BCDF1ABADEC7829054F90DDA9805AAB56C77333024B9D0A508B75C", "5831AAEED7B44BB74E5EAB94BA9D4294C49BCF2A60728D8B4C200F50DD313C1BAB745879A5AD954A72C45A91C3A51D3C7ADEA98D82F8481E0E1E03674A6F3FB7", true),
+            ("25D1DFF95105F5253C4022F628A996AD3A0D95FBF21D468A1B33F8C160D8F517", "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF", "7EB0509757E246F19449885651611CB965ECC1A187DD51B64FDA1EDC9637D5EC97582B9CB13DB3933705B32BA982AF5AF25FD78881EBB32771FC5922EFC66EA3", true),
+            // 5: public key not on the curve
+            ("EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false),
+            // 6: R has an odd y
+            ("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "FFF97BD5755EEEA420453A14355235D382F6472F8568A18B2F057A14602975563CC27944640AC607CD107AE10923D9EF7A73C643E166BE5EBEAFA34B1AC553E2", false),
+        ];
+        for (pubkey, msg, sig, valid) in vectors.iter() {
+            let pubkey = decode_hex32_bytes(&hex::decode(pubkey)?)?;
+            let msg = decode_hex32_bytes(&hex::decode(msg)?)?;
+            let sig = hex::decode(sig)?;
+            assert_eq!(
+                verify_schnorr_sig(&pubkey, &msg, &sig).is_ok(),
+                *valid,
+                "{}",
+                hex::encode(&sig)
+            );
+        }
+        Ok(())
+    }
 }